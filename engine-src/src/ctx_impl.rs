@@ -7,6 +7,7 @@ use sdl3::{
 };
 
 use crate::TContext;
+use crate::engine::gl2d::blend::BlendMode;
 
 impl TContext {
     pub fn init(
@@ -27,6 +28,7 @@ impl TContext {
             global_vbo: RwLock::new(None),
             global_program: RwLock::new(None),
             shader_programs: RwLock::new(shader_programs),
+            blend_modes: RwLock::new(vec![BlendMode::default()]),
         };
         ctx
     }
@@ -46,6 +48,10 @@ impl TContext {
         &self.sdl_window
     }
 
+    pub fn get_video_subsystem(&self) -> &VideoSubsystem {
+        &self.sdl_video_subsystem
+    }
+
     pub fn get_event(&self) -> EventPump {
         self.sdl_context
             .event_pump()
@@ -240,4 +246,59 @@ impl TContext {
             })
             .insert(program_name, shader_program);
     }
+
+    /// Returns the current blend mode(the top of the blend mode stack).
+    pub fn get_blend_mode(&self) -> BlendMode {
+        self.blend_modes
+            .read()
+            .unwrap_or_else(|err| panic!("Failed to acquire read lock for blend_modes: {:?}", err))
+            .last()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Replaces the current blend mode(the top of the blend mode stack).
+    pub fn set_blend_mode(&self, mode: BlendMode) {
+        let mut blend_modes = self
+            .blend_modes
+            .write()
+            .unwrap_or_else(|err| panic!("Failed to acquire write lock for blend_modes: {:?}", err));
+        match blend_modes.last_mut() {
+            Some(current) => *current = mode,
+            None => blend_modes.push(mode),
+        }
+    }
+
+    /// Pushes a new current blend mode on top of the blend mode stack.
+    pub fn push_blend_mode(&self, mode: BlendMode) {
+        self.blend_modes
+            .write()
+            .unwrap_or_else(|err| panic!("Failed to acquire write lock for blend_modes: {:?}", err))
+            .push(mode);
+    }
+
+    /// Pops the current blend mode off the blend mode stack and returns it, making the
+    /// previous mode current again.
+    ///
+    /// Returns None if nothing was pushed, the bottom blend mode is never popped.
+    pub fn pop_blend_mode(&self) -> Option<BlendMode> {
+        let mut blend_modes = self
+            .blend_modes
+            .write()
+            .unwrap_or_else(|err| panic!("Failed to acquire write lock for blend_modes: {:?}", err));
+        if blend_modes.len() > 1 {
+            blend_modes.pop()
+        } else {
+            None
+        }
+    }
+
+    /// Uses the given shader program and sets the gl blending state(and the program's
+    /// `premultiplyAlpha` uniform) to the current blend mode, call this right before drawing.
+    pub fn apply_blend_mode(&self, gl: &glow::Context, program: glow::NativeProgram) {
+        unsafe {
+            gl.use_program(Some(program));
+        }
+        self.get_blend_mode().apply(gl, program);
+    }
 }
@@ -0,0 +1,215 @@
+//! # Events Module
+//!
+//! Hands the sdl events of the window to python, and controls SDL3's text input.
+//!
+//! Typed text does not come from key events, as one key press can type several characters,
+//! none(dead keys) or open an input method(IME) for Chinese, Japanese or Korean text. While
+//! text input is started, typed text arrives as `TextInputEvent`s and the text the IME is
+//! still composing as `TextEditingEvent`s.
+//! Reference: https://wiki.libsdl.org/SDL3/Tutorials/TextInput
+
+extern crate sdl3; // SDL3 bindings
+
+use pyo3::prelude::*;
+use sdl3::{
+    event::{Event, WindowEvent},
+    rect::Rect,
+};
+
+use crate::engine::helpers::get_tctx;
+
+/// Declaration of the events submodule for the parent python module.
+/// Reference: https://pyo3.rs/v0.23.4/module.html
+pub fn register_events_module(parent_module: &Bound<'_, PyModule>) -> PyResult<()> {
+    let child_module = PyModule::new(parent_module.py(), "events")?;
+    child_module.add_class::<QuitEvent>()?;
+    child_module.add_class::<KeyEvent>()?;
+    child_module.add_class::<WindowResizedEvent>()?;
+    child_module.add_class::<TextInputEvent>()?;
+    child_module.add_class::<TextEditingEvent>()?;
+    child_module.add_function(wrap_pyfunction!(poll_events, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(start_text_input, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(set_text_input_area, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(stop_text_input, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(is_text_input_active, &child_module)?)?;
+    parent_module.add_submodule(&child_module)
+}
+
+/// The window was closed.
+#[pyclass(frozen)]
+#[derive(Clone, Debug)]
+pub struct QuitEvent {}
+
+#[pymethods]
+impl QuitEvent {
+    fn __repr__(&self) -> String {
+        String::from("QuitEvent()")
+    }
+}
+
+/// A key was pressed or released, `key` is the SDL name of its keycode("A", "Return", "Escape", ...).
+#[pyclass(get_all, frozen)]
+#[derive(Clone, Debug)]
+pub struct KeyEvent {
+    pub key: String,
+    pub pressed: bool,
+    /// Whether the event comes from the key being held down.
+    pub repeat: bool,
+}
+
+#[pymethods]
+impl KeyEvent {
+    fn __repr__(&self) -> String {
+        format!("KeyEvent(key={:?}, pressed={}, repeat={})", self.key, self.pressed, self.repeat)
+    }
+}
+
+/// The window was resized to the given size(in pixels).
+#[pyclass(get_all, frozen)]
+#[derive(Clone, Debug)]
+pub struct WindowResizedEvent {
+    pub width: i32,
+    pub height: i32,
+}
+
+#[pymethods]
+impl WindowResizedEvent {
+    fn __repr__(&self) -> String {
+        format!("WindowResizedEvent(width={}, height={})", self.width, self.height)
+    }
+}
+
+/// Text was typed(or committed by the IME), to be inserted at the text field's cursor.
+#[pyclass(get_all, frozen)]
+#[derive(Clone, Debug)]
+pub struct TextInputEvent {
+    pub text: String,
+}
+
+#[pymethods]
+impl TextInputEvent {
+    fn __repr__(&self) -> String {
+        format!("TextInputEvent(text={:?})", self.text)
+    }
+}
+
+/// The text the IME is composing changed. It is not typed yet, text fields show it at their
+/// cursor(usually underlined) until a `TextInputEvent` commits it, an empty text ends the
+/// composition.
+///
+/// `start` is the cursor position in the composition and `length` the number of selected
+/// characters from there, both counted in characters.
+#[pyclass(get_all, frozen)]
+#[derive(Clone, Debug)]
+pub struct TextEditingEvent {
+    pub text: String,
+    pub start: i32,
+    pub length: i32,
+}
+
+#[pymethods]
+impl TextEditingEvent {
+    fn __repr__(&self) -> String {
+        format!("TextEditingEvent(text={:?}, start={}, length={})", self.text, self.start, self.length)
+    }
+}
+
+/// Returns the events which happened since the last call, events the engine does not
+/// handle yet are left out.
+///
+/// _Example(python)_:
+/// ```python
+/// for event in tge.events.poll_events():
+///     if isinstance(event, tge.events.QuitEvent):
+///         running = False
+///     elif isinstance(event, tge.events.TextInputEvent):
+///         name += event.text
+/// ```
+#[pyfunction]
+pub fn poll_events(py: Python<'_>) -> PyResult<Vec<PyObject>> {
+    let ctx = get_tctx();
+    let mut event_pump = ctx.get_event();
+
+    let mut events = Vec::new();
+    for event in event_pump.poll_iter() {
+        let event = match event {
+            Event::Quit { .. } => QuitEvent {}.into_pyobject(py)?.into_any(),
+            Event::KeyDown { keycode: Some(keycode), repeat, .. } => KeyEvent {
+                key: keycode.name(),
+                pressed: true,
+                repeat,
+            }
+            .into_pyobject(py)?
+            .into_any(),
+            Event::KeyUp { keycode: Some(keycode), repeat, .. } => KeyEvent {
+                key: keycode.name(),
+                pressed: false,
+                repeat,
+            }
+            .into_pyobject(py)?
+            .into_any(),
+            Event::Window {
+                win_event: WindowEvent::Resized(width, height),
+                ..
+            } => WindowResizedEvent { width, height }.into_pyobject(py)?.into_any(),
+            Event::TextInput { text, .. } => TextInputEvent { text }.into_pyobject(py)?.into_any(),
+            Event::TextEditing { text, start, length, .. } => {
+                TextEditingEvent { text, start, length }.into_pyobject(py)?.into_any()
+            }
+            _ => continue, // Ignore all other events
+        };
+        events.push(event.unbind());
+    }
+    Ok(events)
+}
+
+/// Starts sending typed text as `TextInputEvent`s and `TextEditingEvent`s, on some
+/// platforms this also shows the on screen keyboard.
+///
+/// `rect` is the area [x, y, width, height](in pixels) of the text field being typed into,
+/// IMEs show their candidate window next to it(see `set_text_input_area`).
+///
+/// _Example(python)_:
+/// ```python
+/// tge.events.start_text_input((40, 300, 240, 28))
+/// ```
+#[pyfunction]
+#[pyo3(signature = (rect=None))]
+pub fn start_text_input(rect: Option<[i32; 4]>) -> PyResult<()> {
+    if let Some(rect) = rect {
+        set_text_input_area(rect, 0)?;
+    }
+    let ctx = get_tctx();
+    ctx.get_video_subsystem().text_input().start(ctx.get_window());
+    Ok(())
+}
+
+/// Moves the area of the text field being typed into, `cursor` being the offset(in pixels)
+/// of the text cursor from the left of the area. Call this as the cursor moves so that the
+/// IME candidate window follows it.
+#[pyfunction]
+#[pyo3(signature = (rect, cursor=0))]
+pub fn set_text_input_area(rect: [i32; 4], cursor: i32) -> PyResult<()> {
+    let [x, y, width, height] = rect;
+    let ctx = get_tctx();
+    // The window is cloned as set_rect takes it by value, it only clones a reference to it.
+    ctx.get_video_subsystem().text_input().set_rect(
+        ctx.get_window().clone(),
+        Rect::new(x, y, width.max(0) as u32, height.max(0) as u32),
+        cursor,
+    );
+    Ok(())
+}
+
+/// Stops sending text input events(and hides the on screen keyboard).
+#[pyfunction]
+pub fn stop_text_input() {
+    let ctx = get_tctx();
+    ctx.get_video_subsystem().text_input().stop(ctx.get_window());
+}
+
+#[pyfunction]
+pub fn is_text_input_active() -> bool {
+    let ctx = get_tctx();
+    ctx.get_video_subsystem().text_input().is_active(ctx.get_window())
+}
@@ -0,0 +1,116 @@
+//! # Blend Module
+//!
+//! This submodule of the supermodule 'gl2d' controls how everything the engine draws is
+//! blended with what is already on the screen.
+//!
+//! The current blend mode is kept in the global context as a stack, the draw functions
+//! apply the mode at the top of the stack(or alpha blending if it is empty) right before
+//! they draw, so no draw function leaves blending state behind for the next one.
+
+extern crate glow; // OpenGL bindings
+extern crate sdl3; // SDL3 bindings
+
+use glow::*;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+
+use crate::engine::helpers::get_tctx;
+
+/// Declaration of the blend submodule for the parent python module.
+/// Reference: https://pyo3.rs/v0.23.4/module.html
+pub fn register_blend_module(parent_module: &Bound<'_, PyModule>) -> PyResult<()> {
+    let child_module = PyModule::new(parent_module.py(), "blend")?;
+    child_module.add_class::<BlendMode>()?;
+    child_module.add_function(wrap_pyfunction!(set_blend_mode, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(get_blend_mode, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(push_blend_mode, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(pop_blend_mode, &child_module)?)?;
+    parent_module.add_submodule(&child_module)
+}
+
+/// The ways a drawn color can be combined with the color already on the screen.
+///
+/// NOTE: The blend mode changes gl state, so anything batching draws together has to
+/// include it in its batch key, that is why it is hashable.
+#[pyclass(eq, eq_int, hash, frozen)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Regular transparency for colors that are not premultiplied by their alpha.
+    #[default]
+    Alpha,
+    /// Transparency for colors(and textures) whose rgb is already multiplied by their alpha.
+    PremultipliedAlpha,
+    /// Adds the color to the screen, for glows, fire and light effects.
+    Additive,
+    /// Multiplies the screen by the color, for shadows and tinting.
+    Multiply,
+    /// The inverse of multiply, brightens the screen without blowing it out like additive.
+    Screen,
+    /// Overwrites the screen with the color, including its alpha.
+    Replace,
+}
+
+impl BlendMode {
+    /// Returns whether the mode's blend factors need the drawn color premultiplied by its
+    /// alpha. The engine's shaders output straight alpha, so they premultiply it themselves
+    /// when their `premultiplyAlpha` uniform is set.
+    pub fn premultiplies_source(self) -> bool {
+        matches!(self, BlendMode::Multiply | BlendMode::Screen)
+    }
+
+    /// Sets the gl blending state for this blend mode, and the `premultiplyAlpha` uniform of
+    /// the given program, which must be in use.
+    pub fn apply(self, gl: &Context, program: NativeProgram) {
+        unsafe {
+            let premultiply_location = gl.get_uniform_location(program, "premultiplyAlpha");
+            gl.uniform_1_i32(premultiply_location.as_ref(), self.premultiplies_source() as i32);
+
+            if self == BlendMode::Replace {
+                gl.disable(BLEND);
+                return;
+            }
+
+            gl.enable(BLEND);
+            gl.blend_equation(FUNC_ADD);
+            match self {
+                BlendMode::Alpha => {
+                    gl.blend_func_separate(SRC_ALPHA, ONE_MINUS_SRC_ALPHA, ONE, ONE_MINUS_SRC_ALPHA)
+                }
+                BlendMode::PremultipliedAlpha => gl.blend_func(ONE, ONE_MINUS_SRC_ALPHA),
+                BlendMode::Additive => gl.blend_func_separate(SRC_ALPHA, ONE, ZERO, ONE),
+                // screen * color + screen * (1 - alpha), the color being premultiplied.
+                BlendMode::Multiply => gl.blend_func_separate(DST_COLOR, ONE_MINUS_SRC_ALPHA, ZERO, ONE),
+                // screen + color * (1 - screen), the color being premultiplied.
+                BlendMode::Screen => gl.blend_func_separate(ONE_MINUS_DST_COLOR, ONE, ZERO, ONE),
+                BlendMode::Replace => unreachable!(),
+            }
+        }
+    }
+}
+
+/// Replaces the current blend mode(the top of the blend mode stack) with the given mode.
+#[pyfunction]
+pub fn set_blend_mode(mode: BlendMode) {
+    get_tctx().set_blend_mode(mode);
+}
+
+/// Returns the blend mode the next draw will use.
+#[pyfunction]
+pub fn get_blend_mode() -> BlendMode {
+    get_tctx().get_blend_mode()
+}
+
+/// Makes the given mode the current blend mode, until the matching pop_blend_mode
+/// restores the previous one.
+#[pyfunction]
+pub fn push_blend_mode(mode: BlendMode) {
+    get_tctx().push_blend_mode(mode);
+}
+
+/// Restores the blend mode that was current before the last push_blend_mode.
+#[pyfunction]
+pub fn pop_blend_mode() -> PyResult<BlendMode> {
+    get_tctx()
+        .pop_blend_mode()
+        .ok_or_else(|| PyRuntimeError::new_err("pop_blend_mode called without a matching push_blend_mode"))
+}
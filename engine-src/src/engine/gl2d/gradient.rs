@@ -0,0 +1,177 @@
+//! # Gradient Module
+//!
+//! This submodule of the supermodule 'gl2d' adds linear and radial gradient fills to the
+//! shape draw functions. The gradient is evaluated per pixel in default_frag.glsl, so
+//! gradients with many stops look the same on a rectangle as on a many sided polygon.
+//!
+//! Every draw function that uses the default shader takes a `Paint`, which is either
+//! a plain [r, g, b, a] color or a `Gradient`.
+
+extern crate glow; // OpenGL bindings
+extern crate sdl3; // SDL3 bindings
+
+use glow::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Maximum number of color stops in a gradient, must match MAX_GRADIENT_STOPS in default_frag.glsl.
+pub const MAX_GRADIENT_STOPS: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GradientKind {
+    Linear { angle: f32 },
+    Radial { center: [f32; 2], radius: f32 },
+}
+
+/// A linear or radial color gradient made of color stops.
+///
+/// Each stop is a tuple (offset, [r, g, b, a]), the offset going from 0.0 at the start
+/// of the gradient to 1.0 at its end. Colors before the first stop and after the last
+/// stop are the colors of those stops.
+///
+/// _Example(python)_:
+/// ```python
+/// sky = tge.shapes.Gradient.linear(180.0, [(0.0, [0.2, 0.4, 0.9, 1.0]), (1.0, [0.9, 0.7, 0.5, 1.0])])
+/// tge.shapes.tengine_draw_rect(0.0, 0.0, 1280.0, 400.0, sky)
+/// ```
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    kind: GradientKind,
+    stops: Vec<(f32, [f32; 4])>,
+}
+
+#[pymethods]
+impl Gradient {
+    /// Creates a linear gradient across the bounds of the shape it fills, going in the
+    /// direction of the given angle(in degrees) like css does: 0.0 goes from the bottom
+    /// to the top, 90.0 from the left to the right and 180.0 from the top to the bottom.
+    #[staticmethod]
+    pub fn linear(angle: f32, stops: Vec<(f32, [f32; 4])>) -> PyResult<Self> {
+        Gradient::new(GradientKind::Linear { angle }, stops)
+    }
+
+    /// Creates a radial gradient around the given center(in screen coordinates), with the
+    /// last stop at the given radius(in pixels).
+    #[staticmethod]
+    pub fn radial(center: (f32, f32), radius: f32, stops: Vec<(f32, [f32; 4])>) -> PyResult<Self> {
+        Gradient::new(
+            GradientKind::Radial {
+                center: [center.0, center.1],
+                radius,
+            },
+            stops,
+        )
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Gradient({:?}, stops={:?})", self.kind, self.stops)
+    }
+}
+
+impl Gradient {
+    fn new(kind: GradientKind, mut stops: Vec<(f32, [f32; 4])>) -> PyResult<Self> {
+        if stops.is_empty() {
+            return Err(PyValueError::new_err("A gradient needs at least one color stop"));
+        }
+        if stops.len() > MAX_GRADIENT_STOPS {
+            return Err(PyValueError::new_err(format!(
+                "A gradient can have at most {} color stops, got {}",
+                MAX_GRADIENT_STOPS,
+                stops.len()
+            )));
+        }
+        // Stops can be given in any order, the shader expects them sorted.
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Gradient { kind, stops })
+    }
+
+    /// Returns the points the gradient starts and ends at(or the center and
+    /// (radius, 0.0) for radial gradients), for a shape with the given bounds
+    /// [min_x, min_y, max_x, max_y].
+    fn geometry(&self, bounds: [f32; 4]) -> ([f32; 2], [f32; 2]) {
+        match self.kind {
+            GradientKind::Linear { angle } => {
+                let [min_x, min_y, max_x, max_y] = bounds;
+                let (width, height) = (max_x - min_x, max_y - min_y);
+                let center = [(min_x + max_x) / 2.0, (min_y + max_y) / 2.0];
+
+                // The gradient line goes through the center, and is long enough for the
+                // corners of the bounds to get the first and last stop colors.
+                let (sin, cos) = angle.to_radians().sin_cos();
+                let direction = [sin, -cos];
+                let half_length = ((width * sin).abs() + (height * cos).abs()) / 2.0;
+                (
+                    [center[0] - direction[0] * half_length, center[1] - direction[1] * half_length],
+                    [center[0] + direction[0] * half_length, center[1] + direction[1] * half_length],
+                )
+            }
+            GradientKind::Radial { center, radius } => (center, [radius, 0.0]),
+        }
+    }
+}
+
+/// What a shape is filled with, either a color [r, g, b, a] or a gradient.
+#[derive(FromPyObject, Clone, Debug, PartialEq)]
+pub enum Paint {
+    Color([f32; 4]),
+    Gradient(Gradient),
+}
+
+impl From<[f32; 4]> for Paint {
+    fn from(color: [f32; 4]) -> Self {
+        Paint::Color(color)
+    }
+}
+
+impl From<Gradient> for Paint {
+    fn from(gradient: Gradient) -> Self {
+        Paint::Gradient(gradient)
+    }
+}
+
+/// Sets the color and gradient uniforms of the default shader program for the given paint,
+/// `bounds` being the bounding box [min_x, min_y, max_x, max_y] of the shape being drawn.
+pub fn set_paint_uniforms(gl: &Context, program: NativeProgram, paint: &Paint, bounds: [f32; 4]) {
+    unsafe {
+        let color = match paint {
+            Paint::Color(color) => *color,
+            // The gradient colors are multiplied by color, so they are left as is.
+            Paint::Gradient(_) => [1.0; 4],
+        };
+        let color_location = gl.get_uniform_location(program, "color");
+
+        if let Some(ref loc) = color_location {
+            gl.uniform_4_f32(Some(loc), color[0], color[1], color[2], color[3]);
+        } else {
+            eprintln!("Error: Uniform location for 'color' not found.");
+        }
+
+        let paint_type_location = gl.get_uniform_location(program, "paintType");
+        let Paint::Gradient(gradient) = paint else {
+            gl.uniform_1_i32(paint_type_location.as_ref(), 0);
+            return;
+        };
+        let paint_type = match gradient.kind {
+            GradientKind::Linear { .. } => 1,
+            GradientKind::Radial { .. } => 2,
+        };
+        gl.uniform_1_i32(paint_type_location.as_ref(), paint_type);
+
+        let (start, end) = gradient.geometry(bounds);
+        let start_location = gl.get_uniform_location(program, "gradientStart");
+        gl.uniform_2_f32(start_location.as_ref(), start[0], start[1]);
+        let end_location = gl.get_uniform_location(program, "gradientEnd");
+        gl.uniform_2_f32(end_location.as_ref(), end[0], end[1]);
+
+        let offsets: Vec<f32> = gradient.stops.iter().map(|stop| stop.0).collect();
+        let colors: Vec<f32> = gradient.stops.iter().flat_map(|stop| stop.1).collect();
+
+        let count_location = gl.get_uniform_location(program, "stopCount");
+        gl.uniform_1_i32(count_location.as_ref(), gradient.stops.len() as i32);
+        let offsets_location = gl.get_uniform_location(program, "stopOffsets");
+        gl.uniform_1_f32_slice(offsets_location.as_ref(), &offsets);
+        let colors_location = gl.get_uniform_location(program, "stopColors");
+        gl.uniform_4_f32_slice(colors_location.as_ref(), &colors);
+    }
+}
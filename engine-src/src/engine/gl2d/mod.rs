@@ -1 +1,5 @@
+pub mod blend;
+pub mod gradient;
+pub mod path;
+pub mod sdf;
 pub mod shapes;
@@ -0,0 +1,692 @@
+//! # Path Module
+//!
+//! This submodule of the supermodule 'gl2d' provides a vector path builder made of
+//! lines, quadratic/cubic bezier curves and arcs, that can either be filled or stroked.
+//!
+//! Curves are flattened adaptively into line segments before drawing, so that short
+//! curves don't waste vertices and long curves still look smooth.
+//!
+//! NOTE: Filling uses the stencil buffer ("stencil then cover"), so that self-intersecting
+//! and concave paths are filled correctly with both the nonzero and even-odd rules without
+//! triangulating them on the cpu. The window must be created with a stencil buffer,
+//! which `init_gl_window` takes care of.
+
+extern crate glow; // OpenGL bindings
+extern crate sdl3; // SDL3 bindings
+
+use glow::*;
+use pyo3::prelude::*;
+
+use crate::engine::gl2d::gradient::{Paint, set_paint_uniforms};
+use crate::engine::helpers::get_tctx;
+
+/// Maximum allowed distance(in pixels) between a flattened curve and the real curve.
+const TESSELLATION_TOLERANCE: f32 = 0.25;
+
+/// Maximum recursion depth when subdividing a bezier curve.
+const MAX_SUBDIVISION_LEVEL: u32 = 10;
+
+/// Points closer than this are treated as the same point.
+const DISTANCE_EPSILON: f32 = 0.01;
+
+/// The rule used to decide which parts of a path are inside when filling it.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    NonZero,
+    EvenOdd,
+}
+
+/// The shape drawn at the corners where two segments of a stroked path meet.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+/// The shape drawn at the two ends of an open stroked path.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum PathCommand {
+    MoveTo([f32; 2]),
+    LineTo([f32; 2]),
+    CubicTo([f32; 2], [f32; 2], [f32; 2]),
+    Close,
+}
+
+/// A single flattened subpath of a path.
+#[derive(Clone, Debug, Default)]
+struct Contour {
+    points: Vec<[f32; 2]>,
+    closed: bool,
+}
+
+/// A 2d vector path, built with move_to, line_to, quad_to, cubic_to, arc_to
+/// and close, which can then be filled or stroked.
+///
+/// _Example(python)_:
+/// ```python
+/// path = tge.shapes.Path()
+/// path.move_to(100.0, 100.0)
+/// path.cubic_to(200.0, 0.0, 300.0, 200.0, 400.0, 100.0)
+/// path.stroke(4.0, [1.0, 0.8, 0.0, 1.0], tge.shapes.LineJoin.Round, tge.shapes.LineCap.Round)
+/// ```
+#[pyclass]
+#[derive(Clone, Debug, Default)]
+pub struct Path {
+    commands: Vec<PathCommand>,
+    // The current pen position and the start of the current subpath.
+    current: Option<[f32; 2]>,
+    subpath_start: [f32; 2],
+}
+
+#[pymethods]
+impl Path {
+    #[new]
+    pub fn new() -> Self {
+        Path::default()
+    }
+
+    /// Starts a new subpath at the given point.
+    pub fn move_to(&mut self, x: f32, y: f32) {
+        self.commands.push(PathCommand::MoveTo([x, y]));
+        self.current = Some([x, y]);
+        self.subpath_start = [x, y];
+    }
+
+    /// Adds a straight line from the current point to the given point.
+    pub fn line_to(&mut self, x: f32, y: f32) {
+        if self.current.is_none() {
+            self.move_to(x, y);
+            return;
+        }
+        self.commands.push(PathCommand::LineTo([x, y]));
+        self.current = Some([x, y]);
+    }
+
+    /// Adds a quadratic bezier curve from the current point to (x, y) with
+    /// the control point (cx, cy).
+    pub fn quad_to(&mut self, cx: f32, cy: f32, x: f32, y: f32) {
+        let p0 = self.current_or_move_to(cx, cy);
+
+        // A quadratic curve is a cubic curve with both control points at 2/3 of the
+        // way to the quadratic control point.
+        let c1 = [
+            p0[0] + 2.0 / 3.0 * (cx - p0[0]),
+            p0[1] + 2.0 / 3.0 * (cy - p0[1]),
+        ];
+        let c2 = [x + 2.0 / 3.0 * (cx - x), y + 2.0 / 3.0 * (cy - y)];
+        self.cubic_to(c1[0], c1[1], c2[0], c2[1], x, y);
+    }
+
+    /// Adds a cubic bezier curve from the current point to (x, y) with the
+    /// control points (c1x, c1y) and (c2x, c2y).
+    pub fn cubic_to(&mut self, c1x: f32, c1y: f32, c2x: f32, c2y: f32, x: f32, y: f32) {
+        self.current_or_move_to(c1x, c1y);
+        self.commands
+            .push(PathCommand::CubicTo([c1x, c1y], [c2x, c2y], [x, y]));
+        self.current = Some([x, y]);
+    }
+
+    /// Adds a circular arc of the given radius that is tangent to the line from the
+    /// current point to (x1, y1) and to the line from (x1, y1) to (x2, y2), connected
+    /// to the current point by a straight line(same as the html canvas arcTo).
+    pub fn arc_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, radius: f32) {
+        let p0 = self.current_or_move_to(x1, y1);
+        let p1 = [x1, y1];
+        let p2 = [x2, y2];
+
+        // Degenerate arcs turn into a straight line to (x1, y1).
+        if radius < DISTANCE_EPSILON
+            || points_equal(p0, p1)
+            || points_equal(p1, p2)
+            || distance_to_line(p1, p0, p2) < DISTANCE_EPSILON
+        {
+            self.line_to(x1, y1);
+            return;
+        }
+
+        let d0 = normalize(sub(p0, p1));
+        let d1 = normalize(sub(p2, p1));
+        let angle = dot(d0, d1).clamp(-1.0, 1.0).acos();
+        let tangent_len = radius / (angle / 2.0).tan();
+
+        if tangent_len > 10000.0 {
+            self.line_to(x1, y1);
+            return;
+        }
+
+        let t0 = add(p1, scale(d0, tangent_len));
+        let t1 = add(p1, scale(d1, tangent_len));
+        let bisector = normalize(add(d0, d1));
+        let center = add(p1, scale(bisector, radius / (angle / 2.0).sin()));
+
+        let a0 = (t0[1] - center[1]).atan2(t0[0] - center[0]);
+        let mut a1 = (t1[1] - center[1]).atan2(t1[0] - center[0]);
+
+        // Sweep the short way around the circle, in the direction the path turns.
+        let clockwise = cross(sub(p1, p0), sub(p2, p1)) > 0.0;
+        if clockwise && a1 < a0 {
+            a1 += std::f32::consts::TAU;
+        } else if !clockwise && a1 > a0 {
+            a1 -= std::f32::consts::TAU;
+        }
+
+        self.line_to(t0[0], t0[1]);
+        self.arc_segments(center, radius, a0, a1);
+    }
+
+    /// Closes the current subpath with a straight line back to its starting point.
+    pub fn close(&mut self) {
+        if self.current.is_some() {
+            self.commands.push(PathCommand::Close);
+            self.current = Some(self.subpath_start);
+        }
+    }
+
+    /// Removes all the subpaths from the path, so that it can be reused.
+    pub fn clear(&mut self) {
+        *self = Path::default();
+    }
+
+    /// Fills the path with the given color [r, g, b, a] or gradient, using the given fill rule.
+    #[pyo3(signature = (color, rule = FillRule::NonZero))]
+    pub fn fill(&self, color: Paint, rule: FillRule) {
+        let contours = self.flatten(TESSELLATION_TOLERANCE);
+        let Some([min_x, min_y, max_x, max_y]) = bounds(&contours, 3) else {
+            return;
+        };
+
+        let ctx = get_tctx();
+        let gl = ctx.get_gl();
+        let (vao, vbo, program) = ctx.get_gl_parameters();
+
+        ctx.apply_blend_mode(&gl, program);
+
+        unsafe {
+            gl.bind_vertex_array(Some(vao));
+            gl.bind_buffer(ARRAY_BUFFER, Some(vbo));
+            gl.vertex_attrib_pointer_f32(0, 2, FLOAT, false, 2 * std::mem::size_of::<f32>() as i32, 0);
+            gl.enable_vertex_attrib_array(0);
+
+            // Pass 1: Write the winding number of every pixel into the stencil buffer
+            // by drawing a triangle fan for each contour, without touching the colors.
+            gl.enable(STENCIL_TEST);
+            gl.disable(CULL_FACE);
+            gl.color_mask(false, false, false, false);
+            gl.stencil_mask(0xff);
+            gl.stencil_func(ALWAYS, 0, 0xff);
+            match rule {
+                FillRule::NonZero => {
+                    gl.stencil_op_separate(FRONT, KEEP, KEEP, INCR_WRAP);
+                    gl.stencil_op_separate(BACK, KEEP, KEEP, DECR_WRAP);
+                }
+                FillRule::EvenOdd => {
+                    gl.stencil_op(KEEP, KEEP, INVERT);
+                }
+            }
+
+            for contour in contours.iter().filter(|c| c.points.len() >= 3) {
+                let vertices: Vec<f32> = contour.points.iter().flatten().copied().collect();
+                gl.buffer_data_u8_slice(ARRAY_BUFFER, bytemuck::cast_slice(&vertices), DYNAMIC_DRAW);
+                gl.draw_arrays(TRIANGLE_FAN, 0, contour.points.len() as i32);
+            }
+
+            // Pass 2: Cover the bounds of the path, coloring only the pixels which are
+            // inside according to the fill rule and resetting the stencil as we go.
+            gl.color_mask(true, true, true, true);
+            let test_mask = match rule {
+                FillRule::NonZero => 0xff,
+                FillRule::EvenOdd => 0x01,
+            };
+            gl.stencil_func(NOTEQUAL, 0, test_mask);
+            gl.stencil_op(ZERO, ZERO, ZERO);
+
+            set_paint_uniforms(&gl, program, &color, [min_x, min_y, max_x, max_y]);
+
+            let cover = quad_vertices(min_x, min_y, max_x, max_y);
+            gl.buffer_data_u8_slice(ARRAY_BUFFER, bytemuck::cast_slice(&cover), DYNAMIC_DRAW);
+            gl.draw_arrays(TRIANGLES, 0, 6);
+
+            gl.disable(STENCIL_TEST);
+
+            gl.bind_buffer(ARRAY_BUFFER, None);
+            gl.bind_vertex_array(None);
+        }
+    }
+
+    /// Strokes the outline of the path with a line of the given width and color
+    /// [r, g, b, a] or gradient, using the given line join and line cap.
+    ///
+    /// Miter joins longer than `miter_limit` times the stroke width fall back to
+    /// bevel joins.
+    #[pyo3(signature = (width, color, join = LineJoin::Miter, cap = LineCap::Butt, miter_limit = 4.0))]
+    pub fn stroke(&self, width: f32, color: Paint, join: LineJoin, cap: LineCap, miter_limit: f32) {
+        let contours = self.flatten(TESSELLATION_TOLERANCE);
+        let Some([min_x, min_y, max_x, max_y]) = bounds(&contours, 1) else {
+            return;
+        };
+        let vertices = stroke_vertices(&contours, width / 2.0, join, cap, miter_limit);
+        if vertices.is_empty() {
+            return;
+        }
+        let vertex_count = (vertices.len() / 2) as i32;
+
+        let ctx = get_tctx();
+        let gl = ctx.get_gl();
+        let (vao, vbo, program) = ctx.get_gl_parameters();
+
+        ctx.apply_blend_mode(&gl, program);
+
+        unsafe {
+            gl.bind_vertex_array(Some(vao));
+            gl.bind_buffer(ARRAY_BUFFER, Some(vbo));
+            gl.buffer_data_u8_slice(ARRAY_BUFFER, bytemuck::cast_slice(&vertices), DYNAMIC_DRAW);
+            gl.vertex_attrib_pointer_f32(0, 2, FLOAT, false, 2 * std::mem::size_of::<f32>() as i32, 0);
+            gl.enable_vertex_attrib_array(0);
+
+            let half_width = width / 2.0;
+            let stroke_bounds = [min_x - half_width, min_y - half_width, max_x + half_width, max_y + half_width];
+            set_paint_uniforms(&gl, program, &color, stroke_bounds);
+
+            // The stroke triangles overlap at the joins, so we only let the first
+            // triangle touching a pixel color it, otherwise translucent strokes would
+            // show darker spots wherever the triangles overlap.
+            gl.enable(STENCIL_TEST);
+            gl.stencil_mask(0xff);
+            gl.stencil_func(EQUAL, 0, 0xff);
+            gl.stencil_op(KEEP, KEEP, INCR);
+            gl.draw_arrays(TRIANGLES, 0, vertex_count);
+
+            // Reset the stencil buffer back to zero for the next draw.
+            gl.color_mask(false, false, false, false);
+            gl.stencil_func(ALWAYS, 0, 0xff);
+            gl.stencil_op(ZERO, ZERO, ZERO);
+            gl.draw_arrays(TRIANGLES, 0, vertex_count);
+            gl.color_mask(true, true, true, true);
+
+            gl.disable(STENCIL_TEST);
+
+            gl.bind_buffer(ARRAY_BUFFER, None);
+            gl.bind_vertex_array(None);
+        }
+    }
+}
+
+impl Path {
+    /// Returns the current point, starting a new subpath at the given point if
+    /// the path is empty.
+    fn current_or_move_to(&mut self, x: f32, y: f32) -> [f32; 2] {
+        match self.current {
+            Some(point) => point,
+            None => {
+                self.move_to(x, y);
+                [x, y]
+            }
+        }
+    }
+
+    /// Adds an arc around the given center from angle a0 to angle a1(in radians) as a
+    /// series of cubic bezier curves, each spanning at most a quarter of a circle.
+    fn arc_segments(&mut self, center: [f32; 2], radius: f32, a0: f32, a1: f32) {
+        let sweep = a1 - a0;
+        let segments = (sweep.abs() / std::f32::consts::FRAC_PI_2).ceil().max(1.0) as u32;
+        let step = sweep / segments as f32;
+        let kappa = 4.0 / 3.0 * (step / 4.0).tan() * radius;
+
+        let mut angle = a0;
+        for _ in 0..segments {
+            let next = angle + step;
+            let (sin0, cos0) = angle.sin_cos();
+            let (sin1, cos1) = next.sin_cos();
+
+            self.cubic_to(
+                center[0] + cos0 * radius - sin0 * kappa,
+                center[1] + sin0 * radius + cos0 * kappa,
+                center[0] + cos1 * radius + sin1 * kappa,
+                center[1] + sin1 * radius - cos1 * kappa,
+                center[0] + cos1 * radius,
+                center[1] + sin1 * radius,
+            );
+            angle = next;
+        }
+    }
+
+    /// Converts the path commands into contours made of straight line segments.
+    fn flatten(&self, tolerance: f32) -> Vec<Contour> {
+        let mut contours: Vec<Contour> = Vec::new();
+        let mut current = Contour::default();
+        // A subpath which was only moved to is dropped, but a zero length line is
+        // kept, so that it still gets round or square caps when stroked.
+        let mut has_segments = false;
+
+        for command in &self.commands {
+            match *command {
+                PathCommand::MoveTo(point) => {
+                    if has_segments {
+                        contours.push(std::mem::take(&mut current));
+                    }
+                    current.points.clear();
+                    current.points.push(point);
+                    has_segments = false;
+                }
+                PathCommand::LineTo(point) => {
+                    push_point(&mut current.points, point);
+                    has_segments = true;
+                }
+                PathCommand::CubicTo(c1, c2, end) => {
+                    let start = *current.points.last().unwrap_or(&c1);
+                    flatten_cubic(&mut current.points, start, c1, c2, end, tolerance, 0);
+                    has_segments = true;
+                }
+                PathCommand::Close => {
+                    current.closed = true;
+                    let start = current.points.first().copied();
+                    contours.push(std::mem::take(&mut current));
+                    // Anything drawn after close starts from the beginning of the closed subpath.
+                    current.points.extend(start);
+                    has_segments = false;
+                }
+            }
+        }
+        if has_segments {
+            contours.push(current);
+        }
+
+        for contour in contours.iter_mut() {
+            // A closed contour doesn't need its end point repeated.
+            if contour.closed && contour.points.len() > 1 {
+                let first = contour.points[0];
+                if points_equal(first, *contour.points.last().unwrap()) {
+                    contour.points.pop();
+                }
+            }
+        }
+        contours
+    }
+}
+
+/// Recursively subdivides a cubic bezier curve until each piece is flat enough to be drawn
+/// as a straight line, and pushes the end points of those lines.
+///
+/// Reference: https://github.com/memononen/nanovg/blob/master/src/nanovg.c (nvg__tesselateBezier)
+fn flatten_cubic(
+    out: &mut Vec<[f32; 2]>,
+    p0: [f32; 2],
+    p1: [f32; 2],
+    p2: [f32; 2],
+    p3: [f32; 2],
+    tolerance: f32,
+    level: u32,
+) {
+    let dx = p3[0] - p0[0];
+    let dy = p3[1] - p0[1];
+    let d1 = ((p1[0] - p3[0]) * dy - (p1[1] - p3[1]) * dx).abs();
+    let d2 = ((p2[0] - p3[0]) * dy - (p2[1] - p3[1]) * dx).abs();
+
+    if level >= MAX_SUBDIVISION_LEVEL || (d1 + d2) * (d1 + d2) < tolerance * (dx * dx + dy * dy) {
+        push_point(out, p3);
+        return;
+    }
+
+    // Split the curve in half using de Casteljau's algorithm.
+    let p01 = midpoint(p0, p1);
+    let p12 = midpoint(p1, p2);
+    let p23 = midpoint(p2, p3);
+    let p012 = midpoint(p01, p12);
+    let p123 = midpoint(p12, p23);
+    let p0123 = midpoint(p012, p123);
+
+    flatten_cubic(out, p0, p01, p012, p0123, tolerance, level + 1);
+    flatten_cubic(out, p0123, p123, p23, p3, tolerance, level + 1);
+}
+
+/// Builds the triangles(as a flat list of x, y pairs) that make up the stroke of
+/// the given contours, with half_width being half of the stroke width.
+fn stroke_vertices(
+    contours: &[Contour],
+    half_width: f32,
+    join: LineJoin,
+    cap: LineCap,
+    miter_limit: f32,
+) -> Vec<f32> {
+    let mut out: Vec<f32> = Vec::new();
+    if half_width <= 0.0 {
+        return out;
+    }
+
+    for contour in contours {
+        let mut points = contour.points.clone();
+        let n = points.len();
+        if n < 2 {
+            // A lone point is only visible with round or square caps.
+            if let (Some(&p), false) = (points.first(), contour.closed) {
+                match cap {
+                    LineCap::Round => push_round_fan(&mut out, p, half_width, 0.0, std::f32::consts::TAU),
+                    LineCap::Square => push_quad(
+                        &mut out,
+                        [p[0] - half_width, p[1] - half_width],
+                        [p[0] + half_width, p[1] - half_width],
+                        [p[0] + half_width, p[1] + half_width],
+                        [p[0] - half_width, p[1] + half_width],
+                    ),
+                    LineCap::Butt => {}
+                }
+            }
+            continue;
+        }
+
+        // Square caps are butt caps on a line extended by half the stroke width.
+        if !contour.closed && cap == LineCap::Square {
+            let start_dir = normalize(sub(points[0], points[1]));
+            let end_dir = normalize(sub(points[n - 1], points[n - 2]));
+            points[0] = add(points[0], scale(start_dir, half_width));
+            points[n - 1] = add(points[n - 1], scale(end_dir, half_width));
+        }
+
+        let segment_count = if contour.closed { n } else { n - 1 };
+        for i in 0..segment_count {
+            let a = points[i];
+            let b = points[(i + 1) % n];
+            let normal = scale(perpendicular(normalize(sub(b, a))), half_width);
+            push_quad(&mut out, add(a, normal), add(b, normal), sub(b, normal), sub(a, normal));
+        }
+
+        // Joins between every pair of consecutive segments.
+        let (first_join, last_join) = if contour.closed { (0, n) } else { (1, n - 1) };
+        for i in first_join..last_join {
+            let prev = points[(i + n - 1) % n];
+            let point = points[i];
+            let next = points[(i + 1) % n];
+            push_join(&mut out, prev, point, next, half_width, join, miter_limit);
+        }
+
+        // Round caps at both ends of an open contour.
+        if !contour.closed && cap == LineCap::Round {
+            let start_dir = normalize(sub(points[0], points[1]));
+            let end_dir = normalize(sub(points[n - 1], points[n - 2]));
+            for (p, dir) in [(points[0], start_dir), (points[n - 1], end_dir)] {
+                let angle = dir[1].atan2(dir[0]);
+                push_round_fan(
+                    &mut out,
+                    p,
+                    half_width,
+                    angle - std::f32::consts::FRAC_PI_2,
+                    angle + std::f32::consts::FRAC_PI_2,
+                );
+            }
+        }
+    }
+    out
+}
+
+/// Pushes the triangles filling the outer corner between the segments prev->point
+/// and point->next.
+fn push_join(
+    out: &mut Vec<f32>,
+    prev: [f32; 2],
+    point: [f32; 2],
+    next: [f32; 2],
+    half_width: f32,
+    join: LineJoin,
+    miter_limit: f32,
+) {
+    let d0 = normalize(sub(point, prev));
+    let d1 = normalize(sub(next, point));
+    let turn = cross(d0, d1);
+    if turn.abs() < 1e-6 && dot(d0, d1) > 0.0 {
+        // The segments are collinear, so there is no gap to fill.
+        return;
+    }
+
+    // The outer side of the corner is the side opposite to the turn.
+    let side = if turn > 0.0 { -1.0 } else { 1.0 };
+    let n0 = scale(perpendicular(d0), half_width * side);
+    let n1 = scale(perpendicular(d1), half_width * side);
+    let outer0 = add(point, n0);
+    let outer1 = add(point, n1);
+
+    match join {
+        LineJoin::Bevel => push_triangle(out, point, outer0, outer1),
+        LineJoin::Round => {
+            let a0 = n0[1].atan2(n0[0]);
+            let mut a1 = n1[1].atan2(n1[0]);
+            // Always sweep the short way around from n0 to n1.
+            if a1 - a0 > std::f32::consts::PI {
+                a1 -= std::f32::consts::TAU;
+            } else if a0 - a1 > std::f32::consts::PI {
+                a1 += std::f32::consts::TAU;
+            }
+            push_round_fan(out, point, half_width, a0, a1);
+        }
+        LineJoin::Miter => {
+            // The miter length(relative to the stroke width) is 1 / sin(theta / 2),
+            // theta being the angle between the two segments.
+            let miter_dir = add(n0, n1);
+            let miter_dir_len = length(miter_dir);
+            let cos_half = miter_dir_len / (2.0 * half_width);
+            if cos_half < 1e-4 || 1.0 / cos_half > miter_limit {
+                push_triangle(out, point, outer0, outer1);
+                return;
+            }
+            let tip = add(point, scale(miter_dir, half_width / (cos_half * miter_dir_len)));
+            push_triangle(out, point, outer0, tip);
+            push_triangle(out, point, tip, outer1);
+        }
+    }
+}
+
+/// Pushes a fan of triangles approximating the circular sector around center,
+/// from angle a0 to angle a1(in radians).
+fn push_round_fan(out: &mut Vec<f32>, center: [f32; 2], radius: f32, a0: f32, a1: f32) {
+    // Pick the number of segments so the error stays below the tessellation tolerance.
+    let max_step = 2.0 * (radius / (radius + TESSELLATION_TOLERANCE)).acos();
+    let segments = ((a1 - a0).abs() / max_step).ceil().clamp(2.0, 128.0) as u32;
+    let step = (a1 - a0) / segments as f32;
+
+    let mut previous = add(center, [a0.cos() * radius, a0.sin() * radius]);
+    for i in 1..=segments {
+        let angle = a0 + step * i as f32;
+        let point = add(center, [angle.cos() * radius, angle.sin() * radius]);
+        push_triangle(out, center, previous, point);
+        previous = point;
+    }
+}
+
+fn push_triangle(out: &mut Vec<f32>, a: [f32; 2], b: [f32; 2], c: [f32; 2]) {
+    out.extend_from_slice(&[a[0], a[1], b[0], b[1], c[0], c[1]]);
+}
+
+fn push_quad(out: &mut Vec<f32>, a: [f32; 2], b: [f32; 2], c: [f32; 2], d: [f32; 2]) {
+    push_triangle(out, a, b, c);
+    push_triangle(out, a, c, d);
+}
+
+/// Pushes the point, unless it is the same as the last point.
+fn push_point(points: &mut Vec<[f32; 2]>, point: [f32; 2]) {
+    if points.last().is_none_or(|&last| !points_equal(last, point)) {
+        points.push(point);
+    }
+}
+
+/// Returns the bounding box [min_x, min_y, max_x, max_y] of all the points of the
+/// contours with at least min_points points.
+fn bounds(contours: &[Contour], min_points: usize) -> Option<[f32; 4]> {
+    contours
+        .iter()
+        .filter(|c| c.points.len() >= min_points)
+        .flat_map(|c| c.points.iter())
+        .fold(None, |acc, p| {
+            let [min_x, min_y, max_x, max_y] =
+                acc.unwrap_or([f32::MAX, f32::MAX, f32::MIN, f32::MIN]);
+            Some([min_x.min(p[0]), min_y.min(p[1]), max_x.max(p[0]), max_y.max(p[1])])
+        })
+}
+
+fn quad_vertices(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> [f32; 12] {
+    [
+        min_x, min_y, max_x, min_y, min_x, max_y, max_x, min_y, max_x, max_y, min_x, max_y,
+    ]
+}
+
+// Small 2d vector helpers.
+
+fn add(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn scale(a: [f32; 2], s: f32) -> [f32; 2] {
+    [a[0] * s, a[1] * s]
+}
+
+fn dot(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn cross(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+fn length(a: [f32; 2]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+fn normalize(a: [f32; 2]) -> [f32; 2] {
+    let len = length(a);
+    if len > 1e-6 { scale(a, 1.0 / len) } else { [0.0, 0.0] }
+}
+
+fn perpendicular(a: [f32; 2]) -> [f32; 2] {
+    [-a[1], a[0]]
+}
+
+fn midpoint(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [(a[0] + b[0]) * 0.5, (a[1] + b[1]) * 0.5]
+}
+
+fn points_equal(a: [f32; 2], b: [f32; 2]) -> bool {
+    length(sub(a, b)) < DISTANCE_EPSILON
+}
+
+/// Distance of the point p from the infinite line through a and b.
+fn distance_to_line(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let ab = sub(b, a);
+    let len = length(ab);
+    if len < 1e-6 {
+        return length(sub(p, a));
+    }
+    cross(ab, sub(p, a)).abs() / len
+}
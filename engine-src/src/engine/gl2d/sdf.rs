@@ -0,0 +1,242 @@
+//! # SDF Module
+//!
+//! This submodule of the supermodule 'gl2d' draws anti-aliased circles, rounded rectangles,
+//! rings and lines. Instead of approximating the shape with triangles, each shape is drawn
+//! as a single quad and the fragment shader(sdf_frag.glsl) evaluates the signed distance
+//! to the shape's edge for every pixel, fading the edge out over exactly one pixel.
+//!
+//! This does not need MSAA, and since the edge width is computed from the screen space
+//! derivatives of the distance, the edges stay crisp however the projection scales them.
+
+extern crate glow; // OpenGL bindings
+extern crate sdl3; // SDL3 bindings
+
+use glow::*;
+use pyo3::prelude::*;
+
+use crate::engine::gl2d::path::LineCap;
+use crate::engine::gl2d::shapes::CornerRadii;
+use crate::engine::helpers::get_tctx;
+
+/// Name of the sdf shader program in the global shader programs.
+pub const SDF_SHADER: &str = "sdf-shader";
+
+/// Extra space(in pixels) around every shape's quad, so the faded edge is not clipped.
+const AA_MARGIN: f32 = 2.0;
+
+/// The shapes understood by sdf_frag.glsl, the values must match its `shapeType` uniform.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SdfShapeType {
+    Circle = 0,
+    RoundedBox = 1,
+    Ring = 2,
+    RoundLine = 3,
+    FlatLine = 4,
+}
+
+/// Uniform values describing a single shape for sdf_frag.glsl.
+#[derive(Clone, Copy, Debug)]
+struct SdfShape {
+    shape_type: SdfShapeType,
+    size: [f32; 2],
+    radii: [f32; 4],
+    radius: f32,
+    thickness: f32,
+}
+
+impl SdfShape {
+    fn new(shape_type: SdfShapeType) -> Self {
+        SdfShape {
+            shape_type,
+            size: [0.0, 0.0],
+            radii: [0.0; 4],
+            radius: 0.0,
+            thickness: 0.0,
+        }
+    }
+}
+
+/// Draw an anti-aliased circle with the given radius at given screen coordinates(x and y),
+/// of color [r: f32, g: f32, b: f32, a: f32].
+#[pyfunction]
+pub fn tengine_draw_smooth_circle(x: f32, y: f32, radius: f32, color: [f32; 4]) {
+    let extent = radius + AA_MARGIN;
+    let shape = SdfShape {
+        radius,
+        ..SdfShape::new(SdfShapeType::Circle)
+    };
+    draw_sdf_quad(axis_aligned_quad(x, y, extent, extent), [x, y], shape, color);
+}
+
+/// Draw an anti-aliased ring(circle outline) with the given radius and thickness at given
+/// screen coordinates(x and y), of color [r: f32, g: f32, b: f32, a: f32].
+///
+/// The ring is centered on the radius, so half of the thickness is inside of it.
+#[pyfunction]
+pub fn tengine_draw_smooth_ring(x: f32, y: f32, radius: f32, thickness: f32, color: [f32; 4]) {
+    let extent = radius + thickness / 2.0 + AA_MARGIN;
+    let shape = SdfShape {
+        radius,
+        thickness,
+        ..SdfShape::new(SdfShapeType::Ring)
+    };
+    draw_sdf_quad(axis_aligned_quad(x, y, extent, extent), [x, y], shape, color);
+}
+
+/// Draw an anti-aliased rectangle with rounded corners of the given width and height at given
+/// screen coordinates(x and y), of color [r: f32, g: f32, b: f32, a: f32].
+///
+/// `radius` is either a single radius for all the corners or a list of four radii
+/// [top_left, top_right, bottom_right, bottom_left].
+#[pyfunction]
+pub fn tengine_draw_smooth_rounded_rect(
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    color: [f32; 4],
+    radius: CornerRadii,
+) {
+    if width <= 0.0 || height <= 0.0 {
+        return;
+    }
+    let center = [x + width / 2.0, y + height / 2.0];
+    let shape = SdfShape {
+        size: [width / 2.0, height / 2.0],
+        radii: radius.resolve(width, height),
+        ..SdfShape::new(SdfShapeType::RoundedBox)
+    };
+    let quad = axis_aligned_quad(
+        center[0],
+        center[1],
+        width / 2.0 + AA_MARGIN,
+        height / 2.0 + AA_MARGIN,
+    );
+    draw_sdf_quad(quad, center, shape, color);
+}
+
+/// Draw an anti-aliased line of the given thickness from (x1, y1) to (x2, y2), of color
+/// [r: f32, g: f32, b: f32, a: f32], with the given line cap at both ends.
+#[pyfunction]
+#[pyo3(signature = (x1, y1, x2, y2, thickness, color, cap = LineCap::Butt))]
+pub fn tengine_draw_smooth_line(
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+    thickness: f32,
+    color: [f32; 4],
+    cap: LineCap,
+) {
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length < 1e-4 && cap == LineCap::Butt {
+        return;
+    }
+    // Direction of the line, a zero length line with caps is drawn as a dot.
+    let (dir_x, dir_y) = if length < 1e-4 { (1.0, 0.0) } else { (dx / length, dy / length) };
+    let half_thickness = thickness / 2.0;
+
+    // Square caps are flat lines extended by half the thickness at both ends.
+    let (start, end) = if cap == LineCap::Square {
+        (
+            [x1 - dir_x * half_thickness, y1 - dir_y * half_thickness],
+            [x2 + dir_x * half_thickness, y2 + dir_y * half_thickness],
+        )
+    } else {
+        ([x1, y1], [x2, y2])
+    };
+
+    let shape_type = match cap {
+        LineCap::Round => SdfShapeType::RoundLine,
+        LineCap::Butt | LineCap::Square => SdfShapeType::FlatLine,
+    };
+    let shape = SdfShape {
+        size: [end[0] - start[0], end[1] - start[1]],
+        thickness,
+        ..SdfShape::new(shape_type)
+    };
+
+    // A quad along the line, extended to make room for round caps and the faded edge.
+    let along = half_thickness + AA_MARGIN;
+    let (ax, ay) = (dir_x * along, dir_y * along);
+    let (nx, ny) = (-dir_y * along, dir_x * along);
+    let quad = [
+        [start[0] - ax + nx, start[1] - ay + ny],
+        [end[0] + ax + nx, end[1] + ay + ny],
+        [end[0] + ax - nx, end[1] + ay - ny],
+        [start[0] - ax - nx, start[1] - ay - ny],
+    ];
+    draw_sdf_quad(quad, start, shape, color);
+}
+
+/// Returns the corners of an axis aligned quad centered at (x, y).
+fn axis_aligned_quad(x: f32, y: f32, half_width: f32, half_height: f32) -> [[f32; 2]; 4] {
+    [
+        [x - half_width, y - half_height],
+        [x + half_width, y - half_height],
+        [x + half_width, y + half_height],
+        [x - half_width, y + half_height],
+    ]
+}
+
+/// Draws the quad with the given corners using the sdf shader program, with the
+/// shape's local coordinates measured from `origin`.
+fn draw_sdf_quad(quad: [[f32; 2]; 4], origin: [f32; 2], shape: SdfShape, color: [f32; 4]) {
+    // Get required global contexts
+    let ctx = get_tctx();
+    let gl = ctx.get_gl();
+    let (vao, vbo, default_program) = ctx.get_gl_parameters();
+    let program = ctx.get_shader_program(String::from(SDF_SHADER));
+
+    // Each vertex is its screen position followed by its position relative to the origin.
+    let mut vertices: Vec<f32> = Vec::with_capacity(24);
+    for index in [0, 1, 2, 0, 2, 3] {
+        let [x, y] = quad[index];
+        vertices.extend_from_slice(&[x, y, x - origin[0], y - origin[1]]);
+    }
+
+    ctx.apply_blend_mode(&gl, program);
+
+    unsafe {
+        gl.use_program(Some(program));
+
+        gl.bind_vertex_array(Some(vao));
+        gl.bind_buffer(ARRAY_BUFFER, Some(vbo));
+
+        gl.buffer_data_u8_slice(ARRAY_BUFFER, bytemuck::cast_slice(&vertices), DYNAMIC_DRAW);
+
+        let stride = 4 * std::mem::size_of::<f32>() as i32;
+        gl.vertex_attrib_pointer_f32(0, 2, FLOAT, false, stride, 0);
+        gl.enable_vertex_attrib_array(0);
+        gl.vertex_attrib_pointer_f32(1, 2, FLOAT, false, stride, 2 * std::mem::size_of::<f32>() as i32);
+        gl.enable_vertex_attrib_array(1);
+
+        let color_location = gl.get_uniform_location(program, "color");
+        gl.uniform_4_f32(color_location.as_ref(), color[0], color[1], color[2], color[3]);
+
+        let shape_type_location = gl.get_uniform_location(program, "shapeType");
+        gl.uniform_1_i32(shape_type_location.as_ref(), shape.shape_type as i32);
+
+        let size_location = gl.get_uniform_location(program, "shapeSize");
+        gl.uniform_2_f32(size_location.as_ref(), shape.size[0], shape.size[1]);
+
+        let radii_location = gl.get_uniform_location(program, "radii");
+        gl.uniform_4_f32_slice(radii_location.as_ref(), &shape.radii);
+
+        let radius_location = gl.get_uniform_location(program, "radius");
+        gl.uniform_1_f32(radius_location.as_ref(), shape.radius);
+
+        let thickness_location = gl.get_uniform_location(program, "thickness");
+        gl.uniform_1_f32(thickness_location.as_ref(), shape.thickness);
+
+        gl.draw_arrays(TRIANGLES, 0, 6);
+
+        gl.disable_vertex_attrib_array(1);
+        gl.bind_buffer(ARRAY_BUFFER, None);
+        gl.bind_vertex_array(None);
+
+        // Switch back to the default shader program for the other draw functions.
+        gl.use_program(Some(default_program));
+    }
+}
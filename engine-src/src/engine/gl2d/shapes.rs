@@ -15,8 +15,15 @@ extern crate glow; // OpenGL bindings
 extern crate sdl3; // SDL3 bindings
 
 use glow::*;
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 
+use crate::engine::gl2d::gradient::{Gradient, Paint, set_paint_uniforms};
+use crate::engine::gl2d::path::{FillRule, LineCap, LineJoin, Path};
+use crate::engine::gl2d::sdf::{
+    tengine_draw_smooth_circle, tengine_draw_smooth_line, tengine_draw_smooth_ring,
+    tengine_draw_smooth_rounded_rect,
+};
 use crate::engine::helpers::get_tctx;
 
 /// Declaration of the shapes submodule for the parent python module.
@@ -25,15 +32,27 @@ pub fn register_shapes_module(parent_module: &Bound<'_, PyModule>) -> PyResult<(
     let child_module = PyModule::new(parent_module.py(), "shapes")?;
     child_module.add_function(wrap_pyfunction!(tengine_draw_rect, &child_module)?)?;
     child_module.add_function(wrap_pyfunction!(tengine_draw_circle, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(tengine_draw_rounded_rect, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(tengine_draw_quarter_circle, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(tengine_draw_smooth_circle, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(tengine_draw_smooth_ring, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(tengine_draw_smooth_rounded_rect, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(tengine_draw_smooth_line, &child_module)?)?;
+    child_module.add_class::<Corner>()?;
+    child_module.add_class::<Gradient>()?;
+    child_module.add_class::<Path>()?;
+    child_module.add_class::<FillRule>()?;
+    child_module.add_class::<LineJoin>()?;
+    child_module.add_class::<LineCap>()?;
     parent_module.add_submodule(&child_module)
 }
 
 /// Draw a rectangle of the given width and height at given screen coordinates(x and y),
-/// of color [r: f32, g: f32, b: f32, a: f32].
+/// of color [r: f32, g: f32, b: f32, a: f32] or filled with a gradient.
 ///
 // TODO: Also add support for rectangle border color.
 #[pyfunction]
-pub fn tengine_draw_rect(x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+pub fn tengine_draw_rect(x: f32, y: f32, width: f32, height: f32, color: Paint) {
     // Get required global contexts
     let ctx = get_tctx();
     let gl = ctx.get_gl();
@@ -54,10 +73,9 @@ pub fn tengine_draw_rect(x: f32, y: f32, width: f32, height: f32, color: [f32; 4
         y + height,
     ];
 
-    unsafe {
-        gl.enable(BLEND);
-        gl.blend_func(SRC_ALPHA, ONE_MINUS_SRC_ALPHA);
+    ctx.apply_blend_mode(&gl, program);
 
+    unsafe {
         gl.bind_vertex_array(Some(vao));
         gl.bind_buffer(ARRAY_BUFFER, Some(vbo));
 
@@ -66,13 +84,7 @@ pub fn tengine_draw_rect(x: f32, y: f32, width: f32, height: f32, color: [f32; 4
         gl.vertex_attrib_pointer_f32(0, 2, FLOAT, false, 2 * std::mem::size_of::<f32>() as i32, 0);
         gl.enable_vertex_attrib_array(0);
 
-        let color_location = gl.get_uniform_location(program, "color");
-
-        if let Some(ref loc) = color_location {
-            gl.uniform_4_f32(Some(loc), color[0], color[1], color[2], color[3]);
-        } else {
-            eprintln!("Error: Uniform location for 'color' not found.");
-        }
+        set_paint_uniforms(&gl, program, &color, [x, y, x + width, y + height]);
 
         gl.draw_arrays(TRIANGLES, 0, 6);
 
@@ -81,40 +93,152 @@ pub fn tengine_draw_rect(x: f32, y: f32, width: f32, height: f32, color: [f32; 4
     }
 }
 
-// @d34d0s - implementation based off of engine.py implementation
-/**
- * Draw a rectangle with rounded corners using four quarter circles for each corner
- * :param x: The x-coordinate of the rectangle.
- * :param y: The y-coordinate of the rectangle.
- * :param color: The color (RGBA) of the rectangle.
- * :param radius: The radius of the quarter circle corners of the rectangle.
- */
+/// One of the four corners of a rectangle.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// The corner radii of a rounded rectangle, either one radius for all the corners
+/// or a list of radii [top_left, top_right, bottom_right, bottom_left].
+#[derive(FromPyObject, Clone, Copy, Debug)]
+pub enum CornerRadii {
+    Uniform(f32),
+    PerCorner([f32; 4]),
+}
+
+impl CornerRadii {
+    /// Returns the radii [top_left, top_right, bottom_right, bottom_left], scaled down
+    /// (the same way css does it) so that adjacent corners never overlap.
+    pub fn resolve(self, width: f32, height: f32) -> [f32; 4] {
+        let radii = match self {
+            CornerRadii::Uniform(radius) => [radius; 4],
+            CornerRadii::PerCorner(radii) => radii,
+        }
+        .map(|radius| radius.max(0.0));
+
+        let [top_left, top_right, bottom_right, bottom_left] = radii;
+        let factor = [
+            width / (top_left + top_right),
+            height / (top_right + bottom_right),
+            width / (bottom_right + bottom_left),
+            height / (bottom_left + top_left),
+        ]
+        .into_iter()
+        .filter(|f| f.is_finite())
+        .fold(1.0_f32, f32::min);
+
+        radii.map(|radius| radius * factor)
+    }
+}
+
+impl From<f32> for CornerRadii {
+    fn from(radius: f32) -> Self {
+        CornerRadii::Uniform(radius)
+    }
+}
+
+impl From<[f32; 4]> for CornerRadii {
+    fn from(radii: [f32; 4]) -> Self {
+        CornerRadii::PerCorner(radii)
+    }
+}
+
+/// Returns the number of segments needed for an arc of the given radius and angle(in radians),
+/// so that it does not deviate from the real arc by more than a quarter of a pixel.
+pub fn arc_segment_count(radius: f32, angle: f32) -> u32 {
+    if radius <= 0.0 {
+        return 0;
+    }
+    let max_step = 2.0 * (radius / (radius + 0.25)).acos();
+    (angle.abs() / max_step).ceil().clamp(1.0, 128.0) as u32
+}
+
+/// Returns the outline points(as x, y pairs) of a rounded rectangle, going clockwise
+/// around the rectangle starting from the top left corner.
+pub fn rounded_rect_outline(x: f32, y: f32, width: f32, height: f32, radii: [f32; 4]) -> Vec<f32> {
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    let [top_left, top_right, bottom_right, bottom_left] = radii;
+    // Center and starting angle of each corner arc. Every arc spans a quarter circle.
+    let corners = [
+        (x + top_left, y + top_left, top_left, PI),
+        (x + width - top_right, y + top_right, top_right, 1.5 * PI),
+        (x + width - bottom_right, y + height - bottom_right, bottom_right, 0.0),
+        (x + bottom_left, y + height - bottom_left, bottom_left, FRAC_PI_2),
+    ];
+
+    let mut outline = Vec::new();
+    for (center_x, center_y, radius, start_angle) in corners {
+        let segments = arc_segment_count(radius, FRAC_PI_2);
+        if segments == 0 {
+            // Sharp corner.
+            outline.push(center_x);
+            outline.push(center_y);
+            continue;
+        }
+        for i in 0..=segments {
+            let angle = start_angle + FRAC_PI_2 * (i as f32 / segments as f32);
+            outline.push(center_x + radius * angle.cos());
+            outline.push(center_y + radius * angle.sin());
+        }
+    }
+    outline
+}
+
+/// Draw a rectangle with rounded corners of the given width and height at given screen
+/// coordinates(x and y), of color [r: f32, g: f32, b: f32, a: f32] or filled with a gradient.
+///
+/// `radius` is either a single radius for all the corners or a list of four radii
+/// [top_left, top_right, bottom_right, bottom_left]. Radii that don't fit inside the
+/// rectangle are scaled down.
+///
+/// The rectangle is drawn as a single triangle fan from its center, so no pixel is
+/// drawn twice and translucent colors blend correctly.
 #[pyfunction]
-pub fn tengine_draw_rounded_rect(x: f32, y: f32, width: f32, height: f32, color: [f32; 4], radius: f32) {
+pub fn tengine_draw_rounded_rect(x: f32, y: f32, width: f32, height: f32, color: Paint, radius: CornerRadii) {
+    if width <= 0.0 || height <= 0.0 {
+        return;
+    }
+
+    // Get required global contexts
     let ctx = get_tctx();
     let gl = ctx.get_gl();
+    let (vao, vbo, program) = ctx.get_gl_parameters();
 
-    // enable blending once before drawing to handle semi-transparent colors
-    unsafe {
-        gl.enable(BLEND);
-        gl.blend_func(SRC_ALPHA, ONE_MINUS_SRC_ALPHA);
-    }
+    let radii = radius.resolve(width, height);
+    let outline = rounded_rect_outline(x, y, width, height, radii);
 
-    // draw the center rectangle
-    tengine_draw_rect(x + radius, y, width - 2.0 * radius, height, color);
-    
-    // draw the side rectangles
-    tengine_draw_rect(x, y + radius, width, height - 2.0 * radius, color);
-    tengine_draw_rect(x + width - radius, y + radius, radius, height - 2.0 * radius, color);
+    // The center of the rectangle is the first vertex of the fan, and the first
+    // outline point is repeated at the end to close it.
+    let mut vertices = Vec::with_capacity(outline.len() + 4);
+    vertices.push(x + width / 2.0);
+    vertices.push(y + height / 2.0);
+    vertices.extend_from_slice(&outline);
+    vertices.push(outline[0]);
+    vertices.push(outline[1]);
 
-    // draw the quarter circles for each corner
-    tengine_draw_quarter_circle(x + radius, y + radius, radius, color, 100, "top-left");
-    tengine_draw_quarter_circle(x + width - radius, y + radius, radius, color, 100, "top-right");
-    tengine_draw_quarter_circle(x + radius, y + height - radius, radius, color, 100, "bottom-left");
-    tengine_draw_quarter_circle(x + width - radius, y + height - radius, radius, color, 100, "bottom-right");
+    ctx.apply_blend_mode(&gl, program);
 
     unsafe {
-        gl.disable(BLEND);
+        gl.bind_vertex_array(Some(vao));
+        gl.bind_buffer(ARRAY_BUFFER, Some(vbo));
+
+        gl.buffer_data_u8_slice(ARRAY_BUFFER, bytemuck::cast_slice(&vertices), DYNAMIC_DRAW);
+
+        gl.vertex_attrib_pointer_f32(0, 2, FLOAT, false, 2 * std::mem::size_of::<f32>() as i32, 0);
+        gl.enable_vertex_attrib_array(0);
+
+        set_paint_uniforms(&gl, program, &color, [x, y, x + width, y + height]);
+
+        gl.draw_arrays(TRIANGLE_FAN, 0, (vertices.len() / 2) as i32);
+
+        gl.bind_buffer(ARRAY_BUFFER, None);
+        gl.bind_vertex_array(None);
     }
 }
 
@@ -125,30 +249,34 @@ pub fn tengine_draw_rounded_rect(x: f32, y: f32, width: f32, height: f32, color:
  * :param x: The x-coordinate of the corner of the quarter circle.
  * :param y: The y-coordinate of the corner of the quarter circle.
  * :param radius: The radius of the quarter circle.
- * :param color: The color (RGBA) of the quarter circle.
+ * :param color: The color (RGBA) or gradient of the quarter circle.
  * :param segments: The number of segments for drawing the curve.
- * :param corner: The corner for the quarter circle ('top-left', 'top-right', 'bottom-left', 'bottom-right').
- * :param roundedness: The factor of rounding (0=sharp, 1=circle).
+ * :param corner: The corner of the circle to draw (Corner.TopLeft, Corner.TopRight, Corner.BottomLeft or Corner.BottomRight).
  */
 #[pyfunction]
-pub fn tengine_draw_quarter_circle( x: f32, y: f32, radius: f32, color: [f32; 4], segments: i32, corner: &str) {
+pub fn tengine_draw_quarter_circle( x: f32, y: f32, radius: f32, color: Paint, segments: i32, corner: Corner) -> PyResult<()> {
+    if segments < 1 {
+        return Err(PyValueError::new_err(format!(
+            "A quarter circle needs at least 1 segment, got {}",
+            segments
+        )));
+    }
     let ctx = get_tctx();
     let gl = ctx.get_gl();
     let (vao, vbo, program) = ctx.get_gl_parameters();
 
     let (start_angle, end_angle, sign_x, sign_y) = match corner {
-        "top-left" => (std::f32::consts::PI, 1.5 * std::f32::consts::PI, 1.0, 1.0),
-        "top-right" => (1.5 * std::f32::consts::PI, 2.0 * std::f32::consts::PI, -1.0, 1.0),
-        "bottom-left" => (0.5 * std::f32::consts::PI, std::f32::consts::PI, 1.0, -1.0),
-        "bottom-right" => (0.0, 0.5 * std::f32::consts::PI, -1.0, -1.0),
-        _ => return,
+        Corner::TopLeft => (std::f32::consts::PI, 1.5 * std::f32::consts::PI, 1.0, 1.0),
+        Corner::TopRight => (1.5 * std::f32::consts::PI, 2.0 * std::f32::consts::PI, -1.0, 1.0),
+        Corner::BottomLeft => (0.5 * std::f32::consts::PI, std::f32::consts::PI, 1.0, -1.0),
+        Corner::BottomRight => (0.0, 0.5 * std::f32::consts::PI, -1.0, -1.0),
     };
 
     let center_x = x + sign_x * radius;
     let center_y = y + sign_y * radius;
 
     // create a buffer for storing vertices (quarter circle + center)
-    let mut vertices = Vec::with_capacity((segments + 2) as usize);
+    let mut vertices = Vec::with_capacity((segments as usize + 2) * 2);
 
     // the first vertex is the center
     vertices.push(x);
@@ -167,10 +295,9 @@ pub fn tengine_draw_quarter_circle( x: f32, y: f32, radius: f32, color: [f32; 4]
         vertices.push(cur_y);
     }
 
-    unsafe {
-        gl.enable(BLEND);
-        gl.blend_func(SRC_ALPHA, ONE_MINUS_SRC_ALPHA);
+    ctx.apply_blend_mode(&gl, program);
 
+    unsafe {
         gl.bind_vertex_array(Some(vao));
         gl.bind_buffer(ARRAY_BUFFER, Some(vbo));
 
@@ -179,23 +306,19 @@ pub fn tengine_draw_quarter_circle( x: f32, y: f32, radius: f32, color: [f32; 4]
         gl.vertex_attrib_pointer_f32(0, 2, FLOAT, false, 2 * std::mem::size_of::<f32>() as i32, 0);
         gl.enable_vertex_attrib_array(0);
 
-        let color_location = gl.get_uniform_location(program, "color");
-
-        if let Some(ref loc) = color_location {
-            gl.uniform_4_f32(Some(loc), color[0], color[1], color[2], color[3]);
-        } else {
-            eprintln!("Error: Uniform location for 'color' not found.");
-        }
+        set_paint_uniforms(&gl, program, &color, [x - radius, y - radius, x + radius, y + radius]);
 
         gl.draw_arrays(TRIANGLE_FAN, 0, segments + 2);
 
         gl.bind_buffer(ARRAY_BUFFER, None);
         gl.bind_vertex_array(None);
     }
+    Ok(())
 }
 
 
-/// Draw a circle with the given radius at given screen coordinates(x and y).
+/// Draw a circle with the given radius at given screen coordinates(x and y), of color
+/// [r: f32, g: f32, b: f32, a: f32] or filled with a gradient.
 ///
 /// The circle is approximated using a polygon with many segments.
 /// And since it is being approximated( that is made up of n number of polygons) then
@@ -207,7 +330,7 @@ pub fn tengine_draw_quarter_circle( x: f32, y: f32, radius: f32, color: [f32; 4]
 // remains the same as polygon, the only difference is that segments are determined by
 // the following formula: let segments = (radius * std::f32::consts::PI).max(20.0).min(500.0) as u32;
 #[pyfunction]
-pub fn tengine_draw_circle(x: f32, y: f32, radius: f32, segments: u32, color: [f32; 4]) {
+pub fn tengine_draw_circle(x: f32, y: f32, radius: f32, segments: u32, color: Paint) {
     // Get required global contexts
     let ctx = get_tctx();
     let gl = ctx.get_gl();
@@ -227,10 +350,9 @@ pub fn tengine_draw_circle(x: f32, y: f32, radius: f32, segments: u32, color: [f
         vertices.push(y + radius * angle.sin());
     }
 
-    unsafe {
-        gl.enable(BLEND);
-        gl.blend_func(SRC_ALPHA, ONE_MINUS_SRC_ALPHA);
+    ctx.apply_blend_mode(&gl, program);
 
+    unsafe {
         gl.bind_vertex_array(Some(vao));
         gl.bind_buffer(ARRAY_BUFFER, Some(vbo));
 
@@ -239,13 +361,7 @@ pub fn tengine_draw_circle(x: f32, y: f32, radius: f32, segments: u32, color: [f
         gl.vertex_attrib_pointer_f32(0, 2, FLOAT, false, 2 * std::mem::size_of::<f32>() as i32, 0);
         gl.enable_vertex_attrib_array(0);
 
-        let color_location = gl.get_uniform_location(program, "color");
-
-        if let Some(ref loc) = color_location {
-            gl.uniform_4_f32(Some(loc), color[0], color[1], color[2], color[3]);
-        } else {
-            eprintln!("Error: Uniform location for 'color' not found.");
-        }
+        set_paint_uniforms(&gl, program, &color, [x - radius, y - radius, x + radius, y + radius]);
 
         gl.draw_arrays(TRIANGLE_FAN, 0, (segments + 2) as i32);
 
@@ -0,0 +1,170 @@
+//! # Glyph Atlas
+//!
+//! A single channel texture that glyph bitmaps are packed into as they are needed, so
+//! that a whole string of text can be drawn from one texture with one draw call.
+//!
+//! Glyphs are packed in rows("shelves"), every glyph goes into the first shelf that
+//! is tall enough and still has room, or into a new shelf below the last one.
+
+extern crate glow; // OpenGL bindings
+
+use glow::*;
+
+/// Empty pixels left around every glyph, so that linear filtering does not bleed
+/// neighbouring glyphs into each other.
+const GLYPH_PADDING: u32 = 1;
+
+#[derive(Clone, Copy, Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    next_x: u32,
+}
+
+pub struct GlyphAtlas {
+    texture: NativeTexture,
+    width: u32,
+    height: u32,
+    shelves: Vec<Shelf>,
+}
+
+impl GlyphAtlas {
+    /// Creates an empty(transparent) atlas texture of the given size.
+    pub fn new(gl: &Context, width: u32, height: u32) -> Result<GlyphAtlas, String> {
+        let empty = vec![0u8; (width * height) as usize];
+
+        unsafe {
+            let texture = gl
+                .create_texture()
+                .map_err(|e| format!("Failed to create glyph atlas texture: {}", e))?;
+            gl.bind_texture(TEXTURE_2D, Some(texture));
+
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MIN_FILTER, LINEAR as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MAG_FILTER, LINEAR as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_WRAP_S, CLAMP_TO_EDGE as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_WRAP_T, CLAMP_TO_EDGE as i32);
+
+            // Glyph bitmaps are tightly packed single bytes per pixel.
+            gl.pixel_store_i32(UNPACK_ALIGNMENT, 1);
+            gl.tex_image_2d(
+                TEXTURE_2D,
+                0,
+                R8 as i32,
+                width as i32,
+                height as i32,
+                0,
+                RED,
+                UNSIGNED_BYTE,
+                PixelUnpackData::Slice(Some(&empty)),
+            );
+
+            gl.bind_texture(TEXTURE_2D, None);
+
+            Ok(GlyphAtlas {
+                texture,
+                width,
+                height,
+                shelves: Vec::new(),
+            })
+        }
+    }
+
+    pub fn texture(&self) -> NativeTexture {
+        self.texture
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Sets the minifying and magnifying filter of the atlas texture(LINEAR by default).
+    pub fn set_filter(&self, gl: &Context, filter: u32) {
+        unsafe {
+            gl.bind_texture(TEXTURE_2D, Some(self.texture));
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MIN_FILTER, filter as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MAG_FILTER, filter as i32);
+            gl.bind_texture(TEXTURE_2D, None);
+        }
+    }
+
+    /// Finds room for a bitmap of the given size, and returns the position of its top
+    /// left corner in the atlas, or None if the atlas is full.
+    pub fn allocate(&mut self, width: u32, height: u32) -> Option<[u32; 2]> {
+        let padded_width = width + GLYPH_PADDING;
+        let padded_height = height + GLYPH_PADDING;
+        if padded_width > self.width {
+            return None;
+        }
+
+        // Use the shelf which wastes the least height.
+        let best_shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= padded_height && shelf.next_x + padded_width <= self.width)
+            .min_by_key(|shelf| shelf.height - padded_height);
+
+        if let Some(shelf) = best_shelf {
+            let position = [shelf.next_x, shelf.y];
+            shelf.next_x += padded_width;
+            return Some(position);
+        }
+
+        let next_y = self.shelves.last().map_or(GLYPH_PADDING, |shelf| shelf.y + shelf.height);
+        if next_y + padded_height > self.height {
+            return None;
+        }
+        self.shelves.push(Shelf {
+            y: next_y,
+            height: padded_height,
+            next_x: GLYPH_PADDING + padded_width,
+        });
+        Some([GLYPH_PADDING, next_y])
+    }
+
+    /// Copies a single channel bitmap into the atlas at the given position.
+    pub fn upload(&self, gl: &Context, position: [u32; 2], width: u32, height: u32, pixels: &[u8]) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        unsafe {
+            gl.bind_texture(TEXTURE_2D, Some(self.texture));
+            gl.pixel_store_i32(UNPACK_ALIGNMENT, 1);
+            gl.tex_sub_image_2d(
+                TEXTURE_2D,
+                0,
+                position[0] as i32,
+                position[1] as i32,
+                width as i32,
+                height as i32,
+                RED,
+                UNSIGNED_BYTE,
+                PixelUnpackData::Slice(Some(pixels)),
+            );
+            gl.bind_texture(TEXTURE_2D, None);
+        }
+    }
+
+    /// Removes every glyph from the atlas, making all of it available again.
+    pub fn clear(&mut self, gl: &Context) {
+        self.shelves.clear();
+        // Padding pixels must stay empty, so the whole texture is zeroed.
+        let empty = vec![0u8; (self.width * self.height) as usize];
+        self.upload(gl, [0, 0], self.width, self.height, &empty);
+    }
+
+    /// Returns the texture coordinates [u_min, v_min, u_max, v_max] of the given area of the atlas.
+    pub fn uv_rect(&self, position: [u32; 2], width: u32, height: u32) -> [f32; 4] {
+        [
+            position[0] as f32 / self.width as f32,
+            position[1] as f32 / self.height as f32,
+            (position[0] + width) as f32 / self.width as f32,
+            (position[1] + height) as f32 / self.height as f32,
+        ]
+    }
+
+    pub fn delete(&self, gl: &Context) {
+        unsafe {
+            gl.delete_texture(self.texture);
+        }
+    }
+}
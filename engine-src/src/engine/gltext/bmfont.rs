@@ -0,0 +1,408 @@
+//! # BMFont
+//!
+//! Parser of AngelCode BMFont font descriptions(.fnt), in both the text and the binary
+//! format. The glyph images are in separate page images, which are loaded by `Font::load_bmfont`.
+//! Reference: https://www.angelcode.com/products/bmfont/doc/file_format.html
+
+use std::collections::HashMap;
+
+/// Number of pages a font can have, the binary format stores the page of a glyph in a byte.
+const MAX_PAGES: usize = 256;
+/// Number of glyphs a font can have, glyph indices are u16 and index 0 is the missing glyph.
+pub const MAX_GLYPHS: usize = u16::MAX as usize;
+
+/// A glyph of a bitmap font, all the sizes are in pixels.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BmChar {
+    pub id: u32,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Offset from the pen position on the top of the line to the top left of the glyph.
+    pub x_offset: i32,
+    pub y_offset: i32,
+    pub x_advance: i32,
+    pub page: u32,
+    /// The color channels the glyph is stored in(1 = blue, 2 = green, 4 = red, 8 = alpha, 15 = all).
+    pub channel: u8,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BmFontDescription {
+    pub size: f32,
+    pub line_height: f32,
+    /// Distance from the top of a line to the baseline.
+    pub base: f32,
+    /// File names of the page images, relative to the .fnt file.
+    pub pages: Vec<String>,
+    pub chars: Vec<BmChar>,
+    /// Kerning amount between two characters, by their ids.
+    pub kernings: HashMap<(u32, u32), i32>,
+}
+
+/// Parses a .fnt file, detecting if it is in the binary or the text format.
+pub fn parse_bmfont(bytes: &[u8]) -> Result<BmFontDescription, String> {
+    if bytes.starts_with(b"BMF") {
+        parse_binary(bytes)
+    } else {
+        let text = std::str::from_utf8(bytes).map_err(|e| format!("BMFont file is not valid text: {}", e))?;
+        parse_text(text)
+    }
+}
+
+/// Parses the text format, made of lines of a tag followed by key=value pairs:
+/// `char id=65 x=0 y=0 width=10 height=12 xoffset=0 yoffset=4 xadvance=11 page=0 chnl=15`
+fn parse_text(text: &str) -> Result<BmFontDescription, String> {
+    let mut description = BmFontDescription::default();
+    let mut page_count = 0;
+
+    for (line_number, line) in text.lines().enumerate() {
+        let mut tokens = tokenize_line(line).into_iter();
+        let Some(tag) = tokens.next() else {
+            continue;
+        };
+        let values: HashMap<String, String> = tokens
+            .filter_map(|token| {
+                let (key, value) = token.split_once('=')?;
+                Some((key.to_string(), value.to_string()))
+            })
+            .collect();
+
+        let number = |key: &str| -> Result<i32, String> {
+            match values.get(key) {
+                Some(value) => value
+                    .split(',')
+                    .next()
+                    .unwrap_or_default()
+                    .parse::<i32>()
+                    .map_err(|_| format!("Invalid value '{}' of {} on line {}", value, key, line_number + 1)),
+                None => Ok(0),
+            }
+        };
+
+        match tag.as_str() {
+            "info" => description.size = number("size")?.unsigned_abs() as f32,
+            "common" => {
+                description.line_height = number("lineHeight")? as f32;
+                description.base = number("base")? as f32;
+                page_count = (number("pages")?.max(0) as usize).min(MAX_PAGES);
+            }
+            "page" => {
+                let id = number("id")?;
+                let id = usize::try_from(id)
+                    .ok()
+                    .filter(|id| *id < page_count)
+                    .ok_or(format!(
+                        "Page {} on line {} is not one of the {} pages of the common line",
+                        id,
+                        line_number + 1,
+                        page_count
+                    ))?;
+                let file = values
+                    .get("file")
+                    .ok_or(format!("Page without a file on line {}", line_number + 1))?;
+                if description.pages.len() <= id {
+                    description.pages.resize(id + 1, String::new());
+                }
+                description.pages[id] = file.clone();
+            }
+            "char" => description.chars.push(BmChar {
+                id: number("id")? as u32,
+                x: number("x")? as u32,
+                y: number("y")? as u32,
+                width: number("width")? as u32,
+                height: number("height")? as u32,
+                x_offset: number("xoffset")?,
+                y_offset: number("yoffset")?,
+                x_advance: number("xadvance")?,
+                page: number("page")? as u32,
+                channel: values.get("chnl").map_or(Ok(15), |_| number("chnl"))? as u8,
+            }),
+            "kerning" => {
+                let pair = (number("first")? as u32, number("second")? as u32);
+                description.kernings.insert(pair, number("amount")?);
+            }
+            _ => {}
+        }
+    }
+
+    validate(description)
+}
+
+/// Splits a line of the text format at spaces which are not inside quotes, and removes
+/// the quotes around values.
+fn tokenize_line(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+
+    for character in line.chars() {
+        match character {
+            '"' => quoted = !quoted,
+            ' ' | '\t' if !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            _ => token.push(character),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Reads little endian values from the blocks of the binary format.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .position
+            .checked_add(count)
+            .ok_or("BMFont file has a block bigger than the file")?;
+        let slice = self
+            .bytes
+            .get(self.position..end)
+            .ok_or("BMFont file ends in the middle of a block")?;
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn i16(&mut self) -> Result<i16, String> {
+        Ok(i16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+/// Parses the binary format(version 3): "BMF", the version byte, then blocks made of a
+/// type byte, a 32 bit size and the block data.
+fn parse_binary(bytes: &[u8]) -> Result<BmFontDescription, String> {
+    let version = bytes.get(3).copied().unwrap_or(0);
+    if version != 3 {
+        return Err(format!("Unsupported binary BMFont version {}, only version 3 is supported", version));
+    }
+
+    let mut description = BmFontDescription::default();
+    let mut reader = Reader { bytes, position: 4 };
+
+    while reader.position < bytes.len() {
+        let block_type = reader.u8()?;
+        let block_size = reader.u32()? as usize;
+        let mut block = Reader {
+            bytes: reader.take(block_size)?,
+            position: 0,
+        };
+
+        match block_type {
+            // Info: font size, then flags and paddings we don't need.
+            1 => description.size = block.i16()?.unsigned_abs() as f32,
+            // Common
+            2 => {
+                description.line_height = block.u16()? as f32;
+                description.base = block.u16()? as f32;
+            }
+            // Pages: null terminated file names.
+            3 => {
+                description.pages = block
+                    .bytes
+                    .split(|&byte| byte == 0)
+                    .filter(|name| !name.is_empty())
+                    .map(|name| String::from_utf8_lossy(name).into_owned())
+                    .collect();
+            }
+            // Chars: 20 bytes each.
+            4 => {
+                for _ in 0..block_size / 20 {
+                    description.chars.push(BmChar {
+                        id: block.u32()?,
+                        x: block.u16()? as u32,
+                        y: block.u16()? as u32,
+                        width: block.u16()? as u32,
+                        height: block.u16()? as u32,
+                        x_offset: block.i16()? as i32,
+                        y_offset: block.i16()? as i32,
+                        x_advance: block.i16()? as i32,
+                        page: block.u8()? as u32,
+                        channel: block.u8()?,
+                    });
+                }
+            }
+            // Kerning pairs: 10 bytes each.
+            5 => {
+                for _ in 0..block_size / 10 {
+                    let pair = (block.u32()?, block.u32()?);
+                    description.kernings.insert(pair, block.i16()? as i32);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    validate(description)
+}
+
+fn validate(description: BmFontDescription) -> Result<BmFontDescription, String> {
+    if description.line_height <= 0.0 {
+        return Err(String::from("BMFont file has no line height(missing common block)"));
+    }
+    if description.chars.len() >= MAX_GLYPHS {
+        return Err(format!(
+            "BMFont file has {} characters, fonts can have up to {}",
+            description.chars.len(),
+            MAX_GLYPHS - 1
+        ));
+    }
+    if description.pages.is_empty() || description.pages.iter().any(|page| page.is_empty()) {
+        return Err(String::from("BMFont file has missing page images"));
+    }
+    if let Some(bm_char) = description
+        .chars
+        .iter()
+        .find(|bm_char| bm_char.page as usize >= description.pages.len())
+    {
+        return Err(format!("Character {} is on page {} which does not exist", bm_char.id, bm_char.page));
+    }
+    Ok(description)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT_FONT: &str = "info face=\"Pixel Sans\" size=-16 bold=0
+common lineHeight=18 base=14 scaleW=256 scaleH=256 pages=2 packed=0
+page id=0 file=\"pixel_0.png\"
+page id=1 file=\"pixel 1.png\"
+chars count=2
+char id=65 x=1 y=2 width=8 height=10 xoffset=-1 yoffset=4 xadvance=9 page=0 chnl=15
+char id=66 x=10 y=2 width=7 height=10 xoffset=0 yoffset=4 xadvance=8 page=1 chnl=4
+kernings count=1
+kerning first=65 second=66 amount=-2
+";
+
+    fn block(block_type: u8, data: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![block_type];
+        bytes.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    fn binary_char(id: u32, page: u8) -> Vec<u8> {
+        let mut bytes = id.to_le_bytes().to_vec();
+        for value in [3u16, 4, 8, 10] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [-1i16, 4, 9] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[page, 15]);
+        bytes
+    }
+
+    fn binary_font(chars: &[u8]) -> Vec<u8> {
+        let mut bytes = b"BMF\x03".to_vec();
+        bytes.extend(block(1, &(-16i16).to_le_bytes()));
+        let mut common = Vec::new();
+        for value in [18u16, 14, 256, 256, 1] {
+            common.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend(block(2, &common));
+        bytes.extend(block(3, b"pixel_0.png\0"));
+        bytes.extend(block(4, chars));
+        let mut kerning = 65u32.to_le_bytes().to_vec();
+        kerning.extend_from_slice(&66u32.to_le_bytes());
+        kerning.extend_from_slice(&(-2i16).to_le_bytes());
+        bytes.extend(block(5, &kerning));
+        bytes
+    }
+
+    #[test]
+    fn parses_text_format() {
+        let font = parse_bmfont(TEXT_FONT.as_bytes()).unwrap();
+        assert_eq!(font.size, 16.0);
+        assert_eq!(font.line_height, 18.0);
+        assert_eq!(font.base, 14.0);
+        assert_eq!(font.pages, ["pixel_0.png", "pixel 1.png"]);
+        assert_eq!(font.chars.len(), 2);
+        let b = font.chars[1];
+        assert_eq!((b.id, b.x, b.y, b.width, b.height), (66, 10, 2, 7, 10));
+        assert_eq!((b.x_offset, b.y_offset, b.x_advance, b.page, b.channel), (0, 4, 8, 1, 4));
+        assert_eq!(font.chars[0].x_offset, -1);
+        assert_eq!(font.kernings.get(&(65, 66)), Some(&-2));
+    }
+
+    #[test]
+    fn rejects_text_pages_beyond_page_count() {
+        let huge_page = TEXT_FONT.replace("page id=1", "page id=4000000000");
+        assert!(parse_bmfont(huge_page.as_bytes()).is_err());
+        let extra_page = TEXT_FONT.replace("page id=1", "page id=2");
+        assert!(parse_bmfont(extra_page.as_bytes()).is_err());
+        let negative_page = TEXT_FONT.replace("page id=1", "page id=-1");
+        assert!(parse_bmfont(negative_page.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn rejects_chars_on_missing_pages() {
+        let font = TEXT_FONT.replace("page=1", "page=2");
+        assert!(parse_bmfont(font.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn parses_binary_format() {
+        let mut chars = binary_char(65, 0);
+        chars.extend(binary_char(66, 0));
+        let font = parse_bmfont(&binary_font(&chars)).unwrap();
+        assert_eq!(font.size, 16.0);
+        assert_eq!((font.line_height, font.base), (18.0, 14.0));
+        assert_eq!(font.pages, ["pixel_0.png"]);
+        assert_eq!(font.chars.len(), 2);
+        let a = font.chars[0];
+        assert_eq!((a.id, a.x, a.y, a.width, a.height), (65, 3, 4, 8, 10));
+        assert_eq!((a.x_offset, a.y_offset, a.x_advance, a.page, a.channel), (-1, 4, 9, 0, 15));
+        assert_eq!(font.kernings.get(&(65, 66)), Some(&-2));
+    }
+
+    #[test]
+    fn rejects_truncated_and_unknown_binary_files() {
+        let font = binary_font(&binary_char(65, 0));
+        assert!(parse_bmfont(&font[..font.len() - 3]).is_err());
+        assert!(parse_bmfont(b"BMF\x02").is_err());
+
+        // A block size which would go past the end of the file.
+        let mut huge_block = b"BMF\x03".to_vec();
+        huge_block.push(4);
+        huge_block.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse_bmfont(&huge_block).is_err());
+    }
+
+    #[test]
+    fn rejects_binary_chars_on_missing_pages() {
+        assert!(parse_bmfont(&binary_font(&binary_char(65, 1))).is_err());
+    }
+
+    #[test]
+    fn rejects_more_glyphs_than_indices() {
+        let chars: Vec<u8> = (0..MAX_GLYPHS as u32).flat_map(|id| binary_char(id, 0)).collect();
+        assert!(parse_bmfont(&binary_font(&chars)).is_err());
+
+        let chars: Vec<u8> = (0..MAX_GLYPHS as u32 - 1).flat_map(|id| binary_char(id, 0)).collect();
+        assert_eq!(parse_bmfont(&binary_font(&chars)).unwrap().chars.len(), MAX_GLYPHS - 1);
+    }
+}
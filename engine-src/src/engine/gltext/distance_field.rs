@@ -0,0 +1,134 @@
+//! # Distance Field
+//!
+//! Turns glyph coverage bitmaps into signed distance fields, so that SDF fonts can be
+//! drawn crisp at any size and with outlines, shadows and glows.
+//!
+//! The distances are computed with the exact euclidean distance transform of Felzenszwalb
+//! and Huttenlocher, using the coverage of the edge pixels for sub pixel accuracy the same
+//! way Mapbox's TinySDF does.
+//! Reference: https://cs.brown.edu/people/pfelzens/papers/dt-final.pdf
+//! Reference: https://github.com/mapbox/tiny-sdf
+
+/// How many pixels of distance the field stores on each side of a glyph's edge. Glyph
+/// bitmaps get this much padding, so outlines and glows can be at most this wide(in atlas
+/// pixels, which is the font's pixel size scaled by the size the text is drawn at).
+pub const SDF_SPREAD: u32 = 8;
+
+const INF: f64 = 1e20;
+
+/// Converts a coverage bitmap of the given size into a distance field, which is
+/// SDF_SPREAD pixels larger on every side.
+///
+/// Every byte of the distance field is 0.5(127.5) on the glyph's edge, going up to 1.0
+/// SDF_SPREAD pixels inside the glyph and down to 0.0 SDF_SPREAD pixels outside of it.
+pub fn coverage_to_sdf(coverage: &[u8], width: u32, height: u32) -> (Vec<u8>, u32, u32) {
+    let spread = SDF_SPREAD as usize;
+    let (width, height) = (width as usize, height as usize);
+    let (sdf_width, sdf_height) = (width + 2 * spread, height + 2 * spread);
+    let length = sdf_width * sdf_height;
+
+    // Squared distances to the nearest pixel outside(inner) and inside(outer) of the glyph.
+    let mut outer = vec![INF; length];
+    let mut inner = vec![0.0; length];
+
+    for y in 0..height {
+        for x in 0..width {
+            let alpha = coverage[y * width + x] as f64 / 255.0;
+            if alpha == 0.0 {
+                continue;
+            }
+            let index = (y + spread) * sdf_width + x + spread;
+            if alpha == 1.0 {
+                outer[index] = 0.0;
+                inner[index] = INF;
+            } else {
+                // Edge pixels, the edge is estimated to be 0.5 - alpha pixels away.
+                let distance = 0.5 - alpha;
+                outer[index] = if distance > 0.0 { distance * distance } else { 0.0 };
+                inner[index] = if distance < 0.0 { distance * distance } else { 0.0 };
+            }
+        }
+    }
+
+    let longest = sdf_width.max(sdf_height);
+    let mut buffers = EdtBuffers {
+        f: vec![0.0; longest],
+        z: vec![0.0; longest + 1],
+        v: vec![0; longest],
+    };
+    edt(&mut outer, sdf_width, sdf_height, &mut buffers);
+    edt(&mut inner, sdf_width, sdf_height, &mut buffers);
+
+    let field = outer
+        .iter()
+        .zip(inner.iter())
+        .map(|(outer, inner)| {
+            // Positive outside of the glyph.
+            let distance = outer.sqrt() - inner.sqrt();
+            let value = 0.5 - distance / (2.0 * SDF_SPREAD as f64);
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect();
+
+    (field, sdf_width as u32, sdf_height as u32)
+}
+
+/// Scratch buffers of the 1D distance transform, reused for every row and column.
+struct EdtBuffers {
+    f: Vec<f64>,
+    z: Vec<f64>,
+    v: Vec<usize>,
+}
+
+/// 2D squared euclidean distance transform of the grid, in place.
+fn edt(grid: &mut [f64], width: usize, height: usize, buffers: &mut EdtBuffers) {
+    for x in 0..width {
+        edt_1d(grid, x, width, height, buffers);
+    }
+    for y in 0..height {
+        edt_1d(grid, y * width, 1, width, buffers);
+    }
+}
+
+/// 1D squared distance transform of `length` values of the grid, starting at `offset`
+/// and `stride` values apart. The lower envelope of the parabolas rooted at every value
+/// is found first, then sampled at every position.
+fn edt_1d(grid: &mut [f64], offset: usize, stride: usize, length: usize, buffers: &mut EdtBuffers) {
+    let EdtBuffers { f, z, v } = buffers;
+    v[0] = 0;
+    z[0] = -INF;
+    z[1] = INF;
+    f[0] = grid[offset];
+
+    let mut k: usize = 0;
+    for q in 1..length {
+        f[q] = grid[offset + q * stride];
+        let q2 = (q * q) as f64;
+        // Drop the parabolas which are hidden by the new one. The first one never is, as
+        // z[0] is lower than any intersection of the (finite) values.
+        let mut s;
+        loop {
+            let r = v[k];
+            s = (f[q] - f[r] + q2 - (r * r) as f64) / (q - r) as f64 / 2.0;
+            if s <= z[k] && k > 0 {
+                k -= 1;
+            } else {
+                break;
+            }
+        }
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = INF;
+    }
+
+    k = 0;
+    for q in 0..length {
+        while z[k + 1] < q as f64 {
+            k += 1;
+        }
+        let r = v[k];
+        let qr = q as f64 - r as f64;
+        grid[offset + q * stride] = f[r] + qr * qr;
+    }
+}
@@ -0,0 +1,585 @@
+//! # Font
+//!
+//! Loads TrueType/OpenType fonts and rasterizes their glyphs into a glyph atlas the
+//! first time each glyph is drawn.
+//!
+//! Bitmap fonts(BMFont) are loaded into the same `Font` type, their glyphs are copied from
+//! the page images into the atlas when the font is loaded.
+//!
+//! SDF fonts store a signed distance field of every glyph in the atlas instead of its
+//! coverage, they stay sharp when drawn at other sizes than the one they were loaded at
+//! and can be drawn with outlines, shadows and glows(see `TextEffects`).
+//!
+//! The atlas of an outline font is made of pages, a new page is added when the others are
+//! full. Once there are MAX_ATLAS_PAGES pages, the least recently used page is cleared and
+//! its glyphs are rasterized again the next time they are drawn, so fonts with large
+//! character sets(like CJK fonts) only keep the glyphs which are being used.
+
+extern crate glow; // OpenGL bindings
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use glow::*;
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+
+use crate::T_CONTEXT;
+use crate::engine::gltext::atlas::GlyphAtlas;
+use crate::engine::gltext::bmfont::{BmChar, parse_bmfont};
+use crate::engine::gltext::distance_field::{SDF_SPREAD, coverage_to_sdf};
+use crate::engine::gltext::layout::{LayoutItem, StyleMetrics};
+use crate::engine::helpers::get_tctx;
+
+/// Size of the glyph atlas texture(or of every page of it) of every font.
+const ATLAS_SIZE: u32 = 1024;
+/// Maximum number of atlas pages of an outline font.
+const MAX_ATLAS_PAGES: usize = 4;
+/// Largest atlas a bitmap font's glyphs are copied into, if they don't fit in ATLAS_SIZE.
+const MAX_BITMAP_ATLAS_SIZE: u32 = 4096;
+
+/// Where a rasterized glyph is in the atlas, and how to place it relative to the pen position.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GlyphInfo {
+    /// Texture coordinates [u_min, v_min, u_max, v_max] of the glyph in the atlas.
+    pub uv: [f32; 4],
+    /// Size of the glyph bitmap in pixels.
+    pub size: [f32; 2],
+    /// Offset from the pen position on the baseline to the top left corner of the bitmap.
+    pub offset: [f32; 2],
+    /// How far the pen moves to the right after this glyph.
+    pub advance: f32,
+    /// The atlas page the glyph is in.
+    pub page: usize,
+}
+
+/// Where the glyphs of a font come from.
+enum FontSource {
+    /// Outlines of a TrueType/OpenType font, rasterized as glyphs are needed. The font
+    /// file is kept parsed to shape text with.
+    Outline { font: fontdue::Font, face: Box<ShapingFace> },
+    /// A bitmap font whose glyphs are all in the atlas. Glyph indices are given to the
+    /// characters in the order of the font file, starting at 1(0 is the missing glyph).
+    Bitmap {
+        characters: HashMap<char, u16>,
+        kerning: HashMap<(u16, u16), f32>,
+    },
+}
+
+/// The rustybuzz face of a font file, parsed once instead of for every shaped run.
+struct ShapingFace {
+    /// Borrows `_data`, so it is declared first to be dropped before it.
+    face: rustybuzz::Face<'static>,
+    /// The font file, never changed while the face is alive.
+    _data: Vec<u8>,
+}
+
+impl ShapingFace {
+    fn parse(data: Vec<u8>) -> Option<Self> {
+        // SAFETY: the bytes are on the heap and stay where they are as long as `data` is
+        // not changed, which it never is, and the face is dropped before them.
+        let bytes: &'static [u8] = unsafe { std::slice::from_raw_parts(data.as_ptr(), data.len()) };
+        let face = rustybuzz::Face::from_slice(bytes, 0)?;
+        Some(ShapingFace { face, _data: data })
+    }
+}
+
+/// A font loaded at a fixed pixel size, together with the atlas of its rasterized glyphs.
+///
+/// Fonts are created with `load_font` or `load_bmfont` and can be passed to any of the
+/// text functions.
+#[pyclass(unsendable)]
+pub struct Font {
+    source: FontSource,
+    size: f32,
+    ascent: f32,
+    descent: f32,
+    line_height: f32,
+    sdf: bool,
+    glyphs: HashMap<u16, GlyphInfo>,
+    pages: Vec<AtlasPage>,
+    /// Increased every time text is drawn with the font, pages used since are never evicted.
+    draw_stamp: u64,
+    /// Fonts to draw the characters this font does not have with, in order.
+    fallbacks: Vec<Py<Font>>,
+}
+
+struct AtlasPage {
+    atlas: GlyphAtlas,
+    /// The draw_stamp of the last draw which used a glyph of the page.
+    last_used: u64,
+}
+
+#[pymethods]
+impl Font {
+    /// The pixel size the font was loaded at.
+    #[getter]
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    /// Distance from the top of a line to its baseline.
+    #[getter]
+    pub fn ascent(&self) -> f32 {
+        self.ascent
+    }
+
+    /// Distance from the baseline to the bottom of a line(negative, as it is below the baseline).
+    #[getter]
+    pub fn descent(&self) -> f32 {
+        self.descent
+    }
+
+    /// Distance between the baselines of two consecutive lines.
+    #[getter]
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /// Whether the glyph atlas of the font is a signed distance field.
+    #[getter]
+    pub fn is_sdf(&self) -> bool {
+        self.sdf
+    }
+
+    /// Sets the fonts which characters missing from this font are drawn with, the first
+    /// font which has a character is used.
+    ///
+    /// _Example(python)_:
+    /// ```python
+    /// ui_font.set_fallbacks([tge.text.load_font("NotoSansArabic.ttf", 18.0), tge.text.load_font("NotoSansSC.otf", 18.0)])
+    /// ```
+    pub fn set_fallbacks(&mut self, fallbacks: Vec<Py<Font>>) {
+        self.fallbacks = fallbacks;
+    }
+
+    /// Number of atlas pages the font's glyphs currently use.
+    #[getter]
+    pub fn atlas_pages(&self) -> usize {
+        self.pages.len()
+    }
+}
+
+impl Font {
+    /// Loads the font file at the given path to be rendered at the given pixel size, with
+    /// a distance field atlas if `sdf` is set.
+    pub fn load(gl: &Context, path: &str, size: f32, sdf: bool) -> Result<Font, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Failed to read font file {}: {}", path, e))?;
+        let font = fontdue::Font::from_bytes(
+            bytes.as_slice(),
+            fontdue::FontSettings {
+                scale: size,
+                ..fontdue::FontSettings::default()
+            },
+        )
+        .map_err(|e| format!("Failed to parse font file {}: {}", path, e))?;
+        let face = ShapingFace::parse(bytes).ok_or_else(|| format!("Failed to parse font file {}", path))?;
+
+        // Fonts without horizontal metrics are rare, fall back to the usual proportions.
+        let (ascent, descent, line_height) = match font.horizontal_line_metrics(size) {
+            Some(metrics) => (metrics.ascent, metrics.descent, metrics.new_line_size),
+            None => (size * 0.8, -size * 0.2, size * 1.2),
+        };
+
+        Ok(Font {
+            source: FontSource::Outline { font, face: Box::new(face) },
+            size,
+            ascent,
+            descent,
+            line_height,
+            sdf,
+            glyphs: HashMap::new(),
+            pages: vec![AtlasPage {
+                atlas: GlyphAtlas::new(gl, ATLAS_SIZE, ATLAS_SIZE)?,
+                last_used: 0,
+            }],
+            draw_stamp: 0,
+            fallbacks: Vec::new(),
+        })
+    }
+
+    /// Loads a BMFont font description(.fnt, text or binary) and the page images next to
+    /// it. With `smooth` unset the glyphs are drawn with nearest filtering, for pixel fonts.
+    pub fn load_bmfont(gl: &Context, path: &str, smooth: bool) -> Result<Font, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("Failed to read font file {}: {}", path, e))?;
+        let description = parse_bmfont(&bytes).map_err(|e| format!("Failed to parse font file {}: {}", path, e))?;
+
+        let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+        let mut pages = Vec::with_capacity(description.pages.len());
+        for page in description.pages.iter() {
+            let page_path = directory.join(page);
+            let image = image::open(&page_path)
+                .map_err(|e| format!("Failed to open font page {}: {}", page_path.display(), e))?;
+            // Pages without alpha have their glyphs in the color channels.
+            pages.push((image.color().has_alpha(), image.to_rgba8()));
+        }
+
+        let mut atlas_size = ATLAS_SIZE;
+        let (atlas, glyphs) = loop {
+            let mut atlas = GlyphAtlas::new(gl, atlas_size, atlas_size)?;
+            match pack_bitmap_glyphs(gl, &mut atlas, &description.chars, &pages, description.base) {
+                Some(glyphs) => break (atlas, glyphs),
+                None if atlas_size < MAX_BITMAP_ATLAS_SIZE => {
+                    atlas.delete(gl);
+                    atlas_size *= 2;
+                }
+                None => {
+                    atlas.delete(gl);
+                    return Err(format!("The glyphs of font {} do not fit in a glyph atlas", path));
+                }
+            }
+        };
+        if !smooth {
+            atlas.set_filter(gl, NEAREST);
+        }
+
+        // Index 0 is the missing glyph, like in TrueType fonts.
+        let mut characters = HashMap::new();
+        // parse_bmfont rejects fonts with more glyphs than u16 indices.
+        for (bm_char, glyph) in description.chars.iter().zip(1..=u16::MAX) {
+            if let Some(character) = char::from_u32(bm_char.id) {
+                characters.insert(character, glyph);
+            }
+        }
+        let mut kerning = HashMap::new();
+        for (&(first, second), &amount) in description.kernings.iter() {
+            let first = char::from_u32(first).and_then(|c| characters.get(&c));
+            let second = char::from_u32(second).and_then(|c| characters.get(&c));
+            if let (Some(&first), Some(&second)) = (first, second) {
+                kerning.insert((first, second), amount as f32);
+            }
+        }
+
+        Ok(Font {
+            source: FontSource::Bitmap { characters, kerning },
+            size: description.size,
+            ascent: description.base,
+            descent: description.base - description.line_height,
+            line_height: description.line_height,
+            sdf: false,
+            glyphs,
+            pages: vec![AtlasPage { atlas, last_used: 0 }],
+            draw_stamp: 0,
+            fallbacks: Vec::new(),
+        })
+    }
+
+    /// Returns the texture of the given atlas page.
+    pub fn atlas_texture(&self, page: usize) -> NativeTexture {
+        self.pages[page].atlas.texture()
+    }
+
+    pub fn fallbacks(&self) -> &[Py<Font>] {
+        &self.fallbacks
+    }
+
+    /// Starts drawing text with the font, the atlas pages of the glyphs used from now on
+    /// are not evicted until the next draw.
+    pub fn begin_draw(&mut self) {
+        self.draw_stamp += 1;
+    }
+
+    /// Returns the index of the glyph for the given character(0 if the font does not have it).
+    pub fn glyph_index(&self, character: char) -> u16 {
+        match &self.source {
+            FontSource::Outline { font, .. } => font.lookup_glyph_index(character),
+            FontSource::Bitmap { characters, .. } => characters.get(&character).copied().unwrap_or(0),
+        }
+    }
+
+    /// Returns the kerning adjustment(in pixels) between two glyphs.
+    pub fn kerning(&self, left: u16, right: u16) -> f32 {
+        match &self.source {
+            FontSource::Outline { font, .. } => font.horizontal_kern_indexed(left, right, self.size).unwrap_or(0.0),
+            FontSource::Bitmap { kerning, .. } => kerning.get(&(left, right)).copied().unwrap_or(0.0),
+        }
+    }
+
+    /// Returns how far the pen moves after the glyph with the given index, without rasterizing it.
+    pub fn advance(&self, index: u16) -> f32 {
+        if let Some(glyph) = self.glyphs.get(&index) {
+            return glyph.advance;
+        }
+        match &self.source {
+            FontSource::Outline { font, .. } => font.metrics_indexed(index, self.size).advance_width,
+            FontSource::Bitmap { .. } => 0.0,
+        }
+    }
+
+    /// Returns the line metrics of the font drawn at `scale` times its size.
+    pub fn style_metrics(&self, scale: f32) -> StyleMetrics {
+        StyleMetrics {
+            ascent: self.ascent * scale,
+            descent: self.descent * scale,
+            line_height: self.line_height * scale,
+        }
+    }
+
+    /// Turns the text into layout items drawn with this font at `scale` times its size, all
+    /// with the given style index.
+    pub fn layout_items(&self, text: &str, style: usize, scale: f32) -> Vec<LayoutItem> {
+        let mut items = Vec::with_capacity(text.len());
+        let mut previous: Option<u16> = None;
+
+        for character in text.chars() {
+            if character == '\n' {
+                items.push(LayoutItem {
+                    character,
+                    glyph: 0,
+                    advance: 0.0,
+                    kerning: 0.0,
+                    offset: [0.0; 2],
+                    level: 0,
+                    style,
+                });
+                previous = None;
+                continue;
+            }
+
+            let glyph = self.glyph_index(character);
+            items.push(LayoutItem {
+                character,
+                glyph,
+                advance: self.advance(glyph) * scale,
+                kerning: previous.map_or(0.0, |previous| self.kerning(previous, glyph)) * scale,
+                offset: [0.0; 2],
+                level: 0,
+                style,
+            });
+            previous = Some(glyph);
+        }
+        items
+    }
+
+    /// Returns the placement of the glyph with the given index, rasterizing it into the
+    /// atlas if this is the first time it is used.
+    pub fn glyph(&mut self, gl: &Context, index: u16) -> GlyphInfo {
+        if let Some(glyph) = self.glyphs.get(&index) {
+            let glyph = *glyph;
+            self.pages[glyph.page].last_used = self.draw_stamp;
+            return glyph;
+        }
+        // All the glyphs of bitmap fonts are loaded with the font.
+        let FontSource::Outline { font, .. } = &self.source else {
+            return GlyphInfo::default();
+        };
+
+        let (metrics, mut bitmap) = font.rasterize_indexed(index, self.size);
+        let (mut width, mut height) = (metrics.width as u32, metrics.height as u32);
+        let mut offset = [metrics.xmin as f32, -(metrics.ymin as f32 + height as f32)];
+
+        if self.sdf && width > 0 && height > 0 {
+            (bitmap, width, height) = coverage_to_sdf(&bitmap, width, height);
+            offset = [offset[0] - SDF_SPREAD as f32, offset[1] - SDF_SPREAD as f32];
+        }
+
+        let mut glyph = GlyphInfo {
+            uv: [0.0; 4],
+            size: [width as f32, height as f32],
+            offset,
+            advance: metrics.advance_width,
+            page: 0,
+        };
+
+        if width > 0 && height > 0 {
+            match self.allocate(gl, width, height) {
+                Some((page, position)) => {
+                    let atlas = &self.pages[page].atlas;
+                    atlas.upload(gl, position, width, height, &bitmap);
+                    glyph.uv = atlas.uv_rect(position, width, height);
+                    glyph.page = page;
+                }
+                None => {
+                    // Not cached, so that the next draw tries again once pages are free.
+                    eprintln!("Error: Glyph atlas is full, glyph {} will not be drawn.", index);
+                    glyph.size = [0.0, 0.0];
+                    return glyph;
+                }
+            }
+        }
+
+        self.glyphs.insert(index, glyph);
+        glyph
+    }
+}
+
+impl Font {
+    /// Finds room for a glyph bitmap in the atlas pages, adding a page or evicting the least
+    /// recently used one if they are all full. Returns the page and the position in it.
+    fn allocate(&mut self, gl: &Context, width: u32, height: u32) -> Option<(usize, [u32; 2])> {
+        for (index, page) in self.pages.iter_mut().enumerate() {
+            if let Some(position) = page.atlas.allocate(width, height) {
+                page.last_used = self.draw_stamp;
+                return Some((index, position));
+            }
+        }
+
+        let index = if self.pages.len() < MAX_ATLAS_PAGES {
+            match GlyphAtlas::new(gl, ATLAS_SIZE, ATLAS_SIZE) {
+                Ok(atlas) => self.pages.push(AtlasPage { atlas, last_used: 0 }),
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return None;
+                }
+            }
+            self.pages.len() - 1
+        } else {
+            // Pages used by the text being drawn can't be evicted, as its glyphs point into them.
+            let (evicted, _) = self
+                .pages
+                .iter()
+                .enumerate()
+                .filter(|(_, page)| page.last_used != self.draw_stamp)
+                .min_by_key(|(_, page)| page.last_used)?;
+            // Glyphs without a bitmap(like spaces) are not in any page.
+            self.glyphs
+                .retain(|_, glyph| glyph.page != evicted || glyph.size == [0.0, 0.0]);
+            self.pages[evicted].atlas.clear(gl);
+            evicted
+        };
+
+        let page = &mut self.pages[index];
+        let position = page.atlas.allocate(width, height)?;
+        page.last_used = self.draw_stamp;
+        Some((index, position))
+    }
+
+    /// Shapes a run of text in one direction with rustybuzz, returning its items in logical
+    /// order at `scale` times the font's size. Returns None for bitmap fonts.
+    pub fn shape(&self, text: &str, right_to_left: bool, scale: f32) -> Option<Vec<LayoutItem>> {
+        let FontSource::Outline { face, .. } = &self.source else {
+            return None;
+        };
+        let face = &face.face;
+        let units_scale = self.size * scale / face.units_per_em() as f32;
+
+        let mut buffer = rustybuzz::UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.set_direction(if right_to_left {
+            rustybuzz::Direction::RightToLeft
+        } else {
+            rustybuzz::Direction::LeftToRight
+        });
+        buffer.guess_segment_properties();
+        let shaped = rustybuzz::shape(face, &[], buffer);
+
+        let mut items: Vec<LayoutItem> = shaped
+            .glyph_infos()
+            .iter()
+            .zip(shaped.glyph_positions())
+            .map(|(info, position)| LayoutItem {
+                // Clusters are byte offsets into the text.
+                character: text[info.cluster as usize..].chars().next().unwrap_or(' '),
+                glyph: info.glyph_id as u16,
+                advance: position.x_advance as f32 * units_scale,
+                kerning: 0.0,
+                // Font units go up, screen coordinates down.
+                offset: [
+                    position.x_offset as f32 * units_scale,
+                    -position.y_offset as f32 * units_scale,
+                ],
+                level: 0,
+                style: 0,
+            })
+            .collect();
+
+        // Right to left glyphs come out in display order, the layout wants logical order.
+        if right_to_left {
+            items.reverse();
+        }
+        Some(items)
+    }
+}
+
+/// Copies the glyphs of a bitmap font from their pages into the atlas, and returns their
+/// placements by glyph index(their index in `chars` + 1), or None if the atlas is too small.
+fn pack_bitmap_glyphs(
+    gl: &Context,
+    atlas: &mut GlyphAtlas,
+    chars: &[BmChar],
+    pages: &[(bool, image::RgbaImage)],
+    base: f32,
+) -> Option<HashMap<u16, GlyphInfo>> {
+    let mut glyphs = HashMap::with_capacity(chars.len());
+
+    for (bm_char, glyph_index) in chars.iter().zip(1..=u16::MAX) {
+        let (has_alpha, page) = &pages[bm_char.page as usize];
+        // Glyphs outside of their page are cut to it.
+        let width = bm_char.width.min(page.width().saturating_sub(bm_char.x));
+        let height = bm_char.height.min(page.height().saturating_sub(bm_char.y));
+
+        let mut glyph = GlyphInfo {
+            uv: [0.0; 4],
+            size: [width as f32, height as f32],
+            // BMFont offsets are from the top of the line, ours from the baseline.
+            offset: [bm_char.x_offset as f32, bm_char.y_offset as f32 - base],
+            advance: bm_char.x_advance as f32,
+            page: 0,
+        };
+
+        if width > 0 && height > 0 {
+            let channel = match bm_char.channel {
+                1 => 2,
+                2 => 1,
+                4 => 0,
+                _ if *has_alpha => 3,
+                // White glyphs on black without alpha.
+                _ => 0,
+            };
+            let mut coverage = Vec::with_capacity((width * height) as usize);
+            for y in bm_char.y..bm_char.y + height {
+                for x in bm_char.x..bm_char.x + width {
+                    coverage.push(page.get_pixel(x, y).0[channel]);
+                }
+            }
+
+            let position = atlas.allocate(width, height)?;
+            atlas.upload(gl, position, width, height, &coverage);
+            glyph.uv = atlas.uv_rect(position, width, height);
+        }
+
+        glyphs.insert(glyph_index, glyph);
+    }
+    Some(glyphs)
+}
+
+impl Drop for Font {
+    fn drop(&mut self) {
+        // The engine context may already be gone when python shuts down, in which case
+        // the texture was destroyed along with the gl context.
+        let _ = T_CONTEXT.try_with(|context| {
+            if let Some(ctx) = context.get() {
+                let gl = ctx.get_gl();
+                for page in self.pages.iter() {
+                    page.atlas.delete(&gl);
+                }
+            }
+        });
+    }
+}
+
+/// Loads a TrueType(.ttf) or OpenType(.otf) font to be rendered at the given pixel size.
+///
+/// With `sdf` set the glyphs are stored as signed distance fields, use this for text that
+/// is drawn at many sizes(like labels on a zoomable map) or with `TextEffects`. Around 32
+/// to 64 pixels is a good size for SDF fonts, the size they are drawn at is given to the
+/// draw functions.
+#[pyfunction]
+#[pyo3(signature = (path, size, sdf=false))]
+pub fn load_font(path: &str, size: f32, sdf: bool) -> PyResult<Font> {
+    let ctx = get_tctx();
+    let gl = ctx.get_gl();
+    Font::load(&gl, path, size, sdf).map_err(PyIOError::new_err)
+}
+
+/// Loads an AngelCode BMFont bitmap font(a text or binary .fnt file and its page images).
+///
+/// Pixel fonts are drawn with nearest filtering, set `smooth` for fonts made to be filtered.
+/// Bitmap fonts are best drawn at the size they were made for, or at whole multiples of it.
+#[pyfunction]
+#[pyo3(signature = (path, smooth=false))]
+pub fn load_bmfont(path: &str, smooth: bool) -> PyResult<Font> {
+    let ctx = get_tctx();
+    let gl = ctx.get_gl();
+    Font::load_bmfont(&gl, path, smooth).map_err(PyIOError::new_err)
+}
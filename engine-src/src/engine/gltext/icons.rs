@@ -0,0 +1,104 @@
+//! # Icons
+//!
+//! Named images in a texture atlas, which rich text can show inline with `[icon=name]`.
+
+extern crate glow; // OpenGL bindings
+
+use std::collections::HashMap;
+
+use glow::*;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::T_CONTEXT;
+use crate::engine::gltext::textures::load_texture;
+use crate::engine::helpers::get_tctx;
+
+/// A texture with named rectangles [x, y, width, height](in pixels) of icons in it.
+///
+/// _Example(python)_:
+/// ```python
+/// icons = tge.text.load_icon_atlas("assets/resources.png", {"wood": (0, 0, 32, 32), "stone": (32, 0, 32, 32)})
+/// ```
+#[pyclass(unsendable)]
+pub struct IconAtlas {
+    texture: NativeTexture,
+    width: u32,
+    height: u32,
+    icons: HashMap<String, [u32; 4]>,
+}
+
+#[pymethods]
+impl IconAtlas {
+    /// Adds an icon, or moves an existing one, to the given rectangle of the atlas.
+    pub fn add_icon(&mut self, name: String, rect: [u32; 4]) -> PyResult<()> {
+        let [x, y, width, height] = rect;
+        let right = x.checked_add(width).filter(|right| *right <= self.width);
+        let bottom = y.checked_add(height).filter(|bottom| *bottom <= self.height);
+        if width == 0 || height == 0 || right.is_none() || bottom.is_none() {
+            return Err(PyValueError::new_err(format!(
+                "Icon '{}' at {:?} is outside of the {}x{} atlas",
+                name, rect, self.width, self.height
+            )));
+        }
+        self.icons.insert(name, rect);
+        Ok(())
+    }
+
+    pub fn has_icon(&self, name: &str) -> bool {
+        self.icons.contains_key(name)
+    }
+}
+
+impl IconAtlas {
+    pub fn texture(&self) -> NativeTexture {
+        self.texture
+    }
+
+    /// Returns the texture coordinates [u_min, v_min, u_max, v_max] and the size(in pixels)
+    /// of the icon with the given name.
+    pub fn icon(&self, name: &str) -> Option<([f32; 4], [f32; 2])> {
+        let [x, y, width, height] = *self.icons.get(name)?;
+        let uv = [
+            x as f32 / self.width as f32,
+            y as f32 / self.height as f32,
+            (x + width) as f32 / self.width as f32,
+            (y + height) as f32 / self.height as f32,
+        ];
+        Some((uv, [width as f32, height as f32]))
+    }
+}
+
+impl Drop for IconAtlas {
+    fn drop(&mut self) {
+        let _ = T_CONTEXT.try_with(|context| {
+            if let Some(ctx) = context.get() {
+                unsafe {
+                    ctx.get_gl().delete_texture(self.texture);
+                }
+            }
+        });
+    }
+}
+
+/// Loads the image at the given path as an icon atlas, with the given icons in it.
+#[pyfunction]
+#[pyo3(signature = (path, icons=HashMap::new()))]
+pub fn load_icon_atlas(path: &str, icons: HashMap<String, [u32; 4]>) -> PyResult<IconAtlas> {
+    let ctx = get_tctx();
+    let gl = ctx.get_gl();
+
+    let (width, height) = image::image_dimensions(path).map_err(|e| PyIOError::new_err(format!("{}", e)))?;
+    let texture = load_texture(&gl, path).map_err(PyIOError::new_err)?;
+
+    let mut atlas = IconAtlas {
+        texture,
+        width,
+        height,
+        icons: HashMap::new(),
+    };
+    for (name, rect) in icons {
+        atlas.add_icon(name, rect)?;
+    }
+    Ok(atlas)
+}
@@ -0,0 +1,446 @@
+//! # Text Layout
+//!
+//! Breaks text into lines and positions every glyph on its line. The layout only works
+//! with glyph advances and line metrics, so it does not need a gl context and can be
+//! used to measure text before drawing it.
+//!
+//! Text is laid out from a list of `LayoutItem`s(one per glyph, in logical order) instead
+//! of a string, each item refers to a style(font) by index, so the same line breaking and
+//! alignment works for text which mixes several fonts and sizes. Lines with right to left
+//! items are reordered for display after they are broken.
+
+use pyo3::prelude::*;
+
+/// Horizontal alignment of the lines of text inside their box.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+    /// Stretches the spaces of every wrapped line so it fills the whole width of the box,
+    /// the last line of each paragraph stays left aligned.
+    Justify,
+}
+
+/// Vertical alignment of a block of text inside its box.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VerticalAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// A single glyph of the text to lay out.
+#[derive(Clone, Copy, Debug)]
+pub struct LayoutItem {
+    /// The(first) character the glyph is drawn for.
+    pub character: char,
+    pub glyph: u16,
+    /// How far the pen moves after this item.
+    pub advance: f32,
+    /// Kerning between the previous item and this one, only applied when both are on the same line.
+    pub kerning: f32,
+    /// Offset of the glyph from the pen position(y going down), set by shaping for marks.
+    pub offset: [f32; 2],
+    /// Bidi embedding level, odd levels are right to left.
+    pub level: u8,
+    /// Index of the style(font) the item is drawn with.
+    pub style: usize,
+}
+
+impl LayoutItem {
+    fn is_whitespace(&self) -> bool {
+        self.character.is_whitespace()
+    }
+}
+
+/// The vertical metrics of a style, used to compute the height of the lines it is on.
+#[derive(Clone, Copy, Debug)]
+pub struct StyleMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_height: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LayoutOptions {
+    /// Width of the box the text is laid out in, lines wrap at this width if `wrap` is set.
+    pub max_width: Option<f32>,
+    /// Height of the box the text is laid out in, lines which don't fit are dropped.
+    pub max_height: Option<f32>,
+    pub wrap: bool,
+    pub align: TextAlign,
+    /// Multiplier of the line height.
+    pub line_spacing: f32,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        LayoutOptions {
+            max_width: None,
+            max_height: None,
+            wrap: false,
+            align: TextAlign::Left,
+            line_spacing: 1.0,
+        }
+    }
+}
+
+/// A glyph and where its pen position is, relative to the top left of the layout.
+#[derive(Clone, Copy, Debug)]
+pub struct PositionedGlyph {
+    pub character: char,
+    pub glyph: u16,
+    pub style: usize,
+    pub x: f32,
+    pub baseline: f32,
+    /// Offset of the glyph from its pen position.
+    pub offset: [f32; 2],
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct LayoutLine {
+    pub glyphs: Vec<PositionedGlyph>,
+    /// Width of the line without its trailing whitespace.
+    pub width: f32,
+    pub top: f32,
+    pub baseline: f32,
+    pub height: f32,
+    pub ascent: f32,
+    pub descent: f32,
+    /// Whether the end of the line was cut off and replaced with an ellipsis.
+    pub ellipsized: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TextLayout {
+    pub lines: Vec<LayoutLine>,
+    /// Width of the widest line.
+    pub width: f32,
+    /// Total height of all the lines.
+    pub height: f32,
+    /// Whether some text did not fit and was cut off.
+    pub truncated: bool,
+}
+
+/// A range of items that make up one line.
+#[derive(Clone, Debug)]
+struct LineBreak {
+    start: usize,
+    end: usize,
+    // Lines ending in a newline or at the end of the text are not justified.
+    ends_paragraph: bool,
+}
+
+/// Lays out the given items, `styles` giving the metrics of every style index used by them.
+///
+/// If `ellipsis` is given, lines which are cut off(because they are too wide and can't wrap,
+/// or because the lines after them don't fit in the box) end with the ellipsis items it
+/// returns for the style of the line's last glyph.
+pub fn layout_text(
+    items: &[LayoutItem],
+    styles: &[StyleMetrics],
+    options: &LayoutOptions,
+    ellipsis: Option<&dyn Fn(usize) -> Vec<LayoutItem>>,
+) -> TextLayout {
+    let wrap_width = if options.wrap { options.max_width } else { None };
+    let breaks = break_lines(items, wrap_width);
+
+    let mut layout = TextLayout::default();
+    let mut top = 0.0;
+
+    for (line_number, line_break) in breaks.iter().enumerate() {
+        let line_items = &items[line_break.start..line_break.end];
+        let (ascent, descent, height) = line_metrics(line_items, items, line_break.start, styles, options.line_spacing);
+
+        // Drop the lines which don't fit, but always keep the first one.
+        if let Some(max_height) = options.max_height
+            && line_number > 0
+            && top + height > max_height + 0.5
+        {
+            layout.truncated = true;
+            // The line may already end with an ellipsis if it was too wide.
+            if let (Some(last), Some(ellipsis)) = (layout.lines.last_mut(), ellipsis)
+                && !last.ellipsized
+            {
+                apply_ellipsis(last, options.max_width, ellipsis);
+            }
+            break;
+        }
+
+        let mut line = position_line(line_items);
+        line.top = top;
+        line.ascent = ascent;
+        line.descent = descent;
+        line.height = height;
+        line.baseline = top + ascent;
+        for glyph in line.glyphs.iter_mut() {
+            glyph.baseline = line.baseline;
+        }
+
+        // Lines that can't wrap and are too wide get cut off.
+        if let (Some(max_width), Some(ellipsis)) = (options.max_width, ellipsis)
+            && line.width > max_width + 0.5
+        {
+            layout.truncated = true;
+            apply_ellipsis(&mut line, Some(max_width), ellipsis);
+        }
+
+        if options.align == TextAlign::Justify
+            && !line_break.ends_paragraph
+            && let Some(max_width) = options.max_width
+        {
+            justify_line(&mut line, max_width);
+        }
+
+        top += height;
+        layout.lines.push(line);
+    }
+
+    layout.width = layout.lines.iter().fold(0.0, |width, line| line.width.max(width));
+    layout.height = top;
+
+    // Align every line inside the box, or inside the widest line if there is no box.
+    let align_width = options.max_width.unwrap_or(layout.width);
+    for line in layout.lines.iter_mut() {
+        let shift = match options.align {
+            TextAlign::Left | TextAlign::Justify => 0.0,
+            TextAlign::Center => (align_width - line.width) / 2.0,
+            TextAlign::Right => align_width - line.width,
+        };
+        if shift != 0.0 {
+            for glyph in line.glyphs.iter_mut() {
+                glyph.x += shift;
+            }
+        }
+    }
+
+    layout
+}
+
+impl TextLayout {
+    /// Returns how far down the layout has to be moved to be aligned inside a box of
+    /// the given height.
+    pub fn vertical_offset(&self, box_height: f32, valign: VerticalAlign) -> f32 {
+        match valign {
+            VerticalAlign::Top => 0.0,
+            VerticalAlign::Middle => (box_height - self.height) / 2.0,
+            VerticalAlign::Bottom => box_height - self.height,
+        }
+    }
+}
+
+/// Splits the items into lines at newlines and, if max_width is given, at the last
+/// whitespace before the line gets too wide. Words longer than a whole line are split
+/// between characters.
+fn break_lines(items: &[LayoutItem], max_width: Option<f32>) -> Vec<LineBreak> {
+    let mut breaks = Vec::new();
+    let mut line_start = 0;
+    let mut width = 0.0;
+    // Index of the first item after the last whitespace on the current line.
+    let mut last_break: Option<usize> = None;
+
+    for (i, item) in items.iter().enumerate() {
+        if item.character == '\n' {
+            breaks.push(LineBreak {
+                start: line_start,
+                end: i,
+                ends_paragraph: true,
+            });
+            line_start = i + 1;
+            width = 0.0;
+            last_break = None;
+            continue;
+        }
+
+        let kerning = if i > line_start { item.kerning } else { 0.0 };
+        let new_width = width + kerning + item.advance;
+
+        match max_width {
+            Some(max_width) if !item.is_whitespace() && new_width > max_width && i > line_start => {
+                let next_start = last_break.filter(|&b| b > line_start).unwrap_or(i);
+                breaks.push(LineBreak {
+                    start: line_start,
+                    end: next_start,
+                    ends_paragraph: false,
+                });
+                line_start = next_start;
+                width = items[line_start..=i]
+                    .iter()
+                    .enumerate()
+                    .map(|(j, item)| if j > 0 { item.kerning + item.advance } else { item.advance })
+                    .sum();
+                last_break = None;
+            }
+            _ => width = new_width,
+        }
+
+        if item.is_whitespace() {
+            last_break = Some(i + 1);
+        }
+    }
+
+    breaks.push(LineBreak {
+        start: line_start,
+        end: items.len(),
+        ends_paragraph: true,
+    });
+    breaks
+}
+
+/// Returns the ascent, descent and height of a line from the styles used on it. Empty
+/// lines use the style of the item before them, or the first style.
+fn line_metrics(
+    line_items: &[LayoutItem],
+    items: &[LayoutItem],
+    line_start: usize,
+    styles: &[StyleMetrics],
+    line_spacing: f32,
+) -> (f32, f32, f32) {
+    let fallback_style = if line_start > 0 { items[line_start - 1].style } else { 0 };
+    let mut used_styles: Vec<usize> = line_items.iter().map(|item| item.style).collect();
+    if used_styles.is_empty() {
+        used_styles.push(fallback_style);
+    }
+
+    let mut ascent: f32 = 0.0;
+    let mut descent: f32 = 0.0;
+    let mut line_height: f32 = 0.0;
+    for style in used_styles {
+        if let Some(metrics) = styles.get(style) {
+            ascent = ascent.max(metrics.ascent);
+            descent = descent.min(metrics.descent);
+            line_height = line_height.max(metrics.line_height);
+        }
+    }
+    (ascent, descent, line_height * line_spacing)
+}
+
+/// Positions the items of a line one after another starting at x = 0, in display order.
+fn position_line(line_items: &[LayoutItem]) -> LayoutLine {
+    let mut line = LayoutLine::default();
+    let mut pen_x = 0.0;
+    let mut previous: Option<usize> = None;
+
+    for i in visual_order(line_items) {
+        let item = &line_items[i];
+        // Kerning is only between items which are next to each other in the text too.
+        if i > 0 && previous == Some(i - 1) {
+            pen_x += item.kerning;
+        }
+        line.glyphs.push(PositionedGlyph {
+            character: item.character,
+            glyph: item.glyph,
+            style: item.style,
+            x: pen_x,
+            baseline: 0.0,
+            offset: item.offset,
+        });
+        pen_x += item.advance;
+        if !item.is_whitespace() {
+            line.width = pen_x;
+        }
+        previous = Some(i);
+    }
+    line
+}
+
+/// Returns the indices of the items of a line in the order they are displayed in, from
+/// left to right. From the highest level down to the lowest odd level, every sequence of
+/// items at that level or higher is reversed(rule L2 of the bidi algorithm). Whitespace at
+/// the end of the line stays at the end.
+fn visual_order(line_items: &[LayoutItem]) -> Vec<usize> {
+    let visible = line_items
+        .iter()
+        .rposition(|item| !item.is_whitespace())
+        .map_or(0, |last| last + 1);
+    let mut order: Vec<usize> = (0..line_items.len()).collect();
+
+    let highest = line_items[..visible].iter().map(|item| item.level).max().unwrap_or(0);
+    let lowest_odd = line_items[..visible]
+        .iter()
+        .map(|item| item.level)
+        .filter(|level| level % 2 == 1)
+        .min();
+    let Some(lowest_odd) = lowest_odd else {
+        return order;
+    };
+
+    for level in (lowest_odd..=highest).rev() {
+        let mut start = 0;
+        while start < visible {
+            if line_items[order[start]].level < level {
+                start += 1;
+                continue;
+            }
+            let mut end = start;
+            while end < visible && line_items[order[end]].level >= level {
+                end += 1;
+            }
+            order[start..end].reverse();
+            start = end;
+        }
+    }
+    order
+}
+
+/// Removes glyphs from the end of the line until the ellipsis fits, then appends the ellipsis.
+fn apply_ellipsis(line: &mut LayoutLine, max_width: Option<f32>, ellipsis: &dyn Fn(usize) -> Vec<LayoutItem>) {
+    let style = line.glyphs.last().map_or(0, |glyph| glyph.style);
+    let ellipsis_items = ellipsis(style);
+    let ellipsis_width: f32 = ellipsis_items.iter().map(|item| item.advance).sum();
+    let max_width = max_width.unwrap_or(f32::MAX);
+
+    // Glyph x positions are pen positions, so after removing a glyph the line ends where it started.
+    while let Some(last) = line.glyphs.last() {
+        if !last.character.is_whitespace() && line.width + ellipsis_width <= max_width {
+            break;
+        }
+        line.width = last.x;
+        line.glyphs.pop();
+    }
+
+    for item in ellipsis_items {
+        line.glyphs.push(PositionedGlyph {
+            character: item.character,
+            glyph: item.glyph,
+            style: item.style,
+            x: line.width,
+            baseline: line.baseline,
+            offset: item.offset,
+        });
+        line.width += item.advance;
+    }
+    line.ellipsized = true;
+}
+
+/// Spreads the space left on the line evenly between its(non trailing) whitespace.
+fn justify_line(line: &mut LayoutLine, max_width: f32) {
+    let extra = max_width - line.width;
+    let visible = line
+        .glyphs
+        .iter()
+        .rposition(|glyph| !glyph.character.is_whitespace())
+        .map_or(0, |last| last + 1);
+    let gaps = line.glyphs[..visible]
+        .iter()
+        .filter(|glyph| glyph.character.is_whitespace())
+        .count();
+    if extra <= 0.0 || gaps == 0 {
+        return;
+    }
+
+    let gap_extra = extra / gaps as f32;
+    let mut shift = 0.0;
+    for glyph in line.glyphs[..visible].iter_mut() {
+        glyph.x += shift;
+        if glyph.character.is_whitespace() {
+            shift += gap_extra;
+        }
+    }
+    line.width = max_width;
+}
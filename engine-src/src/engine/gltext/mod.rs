@@ -1 +1,34 @@
-pub mod textures;
\ No newline at end of file
+use pyo3::prelude::*;
+use pyo3::wrap_pyfunction;
+
+pub mod atlas;
+pub mod bmfont;
+pub mod distance_field;
+pub mod font;
+pub mod icons;
+pub mod layout;
+pub mod rich_text;
+pub mod shaping;
+pub mod text;
+pub mod textures;
+
+/// Declaration of the text submodule for the parent python module.
+/// Reference: https://pyo3.rs/v0.23.4/module.html
+pub fn register_text_module(parent_module: &Bound<'_, PyModule>) -> PyResult<()> {
+    let child_module = PyModule::new(parent_module.py(), "text")?;
+    child_module.add_class::<font::Font>()?;
+    child_module.add_function(wrap_pyfunction!(font::load_font, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(font::load_bmfont, &child_module)?)?;
+    child_module.add_class::<layout::TextAlign>()?;
+    child_module.add_class::<layout::VerticalAlign>()?;
+    child_module.add_class::<text::TextMetrics>()?;
+    child_module.add_class::<text::TextEffects>()?;
+    child_module.add_class::<rich_text::FontFamily>()?;
+    child_module.add_class::<icons::IconAtlas>()?;
+    child_module.add_function(wrap_pyfunction!(icons::load_icon_atlas, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(text::draw_text, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(text::draw_text_box, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(text::measure_text, &child_module)?)?;
+    child_module.add_function(wrap_pyfunction!(rich_text::draw_rich_text, &child_module)?)?;
+    parent_module.add_submodule(&child_module)
+}
@@ -0,0 +1,342 @@
+//! # Rich Text
+//!
+//! Draws text with inline markup, for tooltips and messages mixing colors, font faces,
+//! sizes and icons. The markup tags are:
+//!
+//! - `[color=#rgb]...[/color]`(also #rgba, #rrggbb and #rrggbbaa)
+//! - `[b]...[/b]` and `[i]...[/i]`, drawn with the bold and italic faces of the `FontFamily`
+//! - `[size=24]...[/size]`, the pixel size of the text
+//! - `[icon=name]`, an icon of the `IconAtlas` as high as the line's text
+//!
+//! `[[` is a literal `[`, and anything in brackets which is not a known tag is drawn as is.
+//! The spans are laid out with the same layout as plain text, so they wrap and align together.
+
+extern crate glow; // OpenGL bindings
+
+use glow::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::engine::gltext::font::Font;
+use crate::engine::gltext::icons::IconAtlas;
+use crate::engine::gltext::layout::{LayoutItem, LayoutOptions, TextAlign, VerticalAlign};
+use crate::engine::gltext::shaping::bidi_levels;
+use crate::engine::gltext::text::{TextBuilder, draw_quads_with};
+use crate::engine::gltext::textures::TEXTURE_SHADER;
+use crate::engine::helpers::get_tctx;
+
+/// The character icons are laid out as, icons are the only thing drawn for it.
+pub const ICON_CHARACTER: char = '\u{FFFC}';
+
+/// The regular, bold, italic and bold italic faces of a font, for rich text.
+///
+/// Missing faces fall back to the closest face there is(bold italic to bold, then italic,
+/// then regular).
+///
+/// _Example(python)_:
+/// ```python
+/// family = tge.text.FontFamily(tge.text.load_font("NotoSans-Regular.ttf", 18.0),
+///                              bold=tge.text.load_font("NotoSans-Bold.ttf", 18.0))
+/// ```
+#[pyclass(unsendable)]
+pub struct FontFamily {
+    regular: Py<Font>,
+    bold: Option<Py<Font>>,
+    italic: Option<Py<Font>>,
+    bold_italic: Option<Py<Font>>,
+}
+
+#[pymethods]
+impl FontFamily {
+    #[new]
+    #[pyo3(signature = (regular, bold=None, italic=None, bold_italic=None))]
+    pub fn new(regular: Py<Font>, bold: Option<Py<Font>>, italic: Option<Py<Font>>, bold_italic: Option<Py<Font>>) -> Self {
+        FontFamily {
+            regular,
+            bold,
+            italic,
+            bold_italic,
+        }
+    }
+}
+
+impl FontFamily {
+    fn face(&self, bold: bool, italic: bool) -> &Py<Font> {
+        let faces = match (bold, italic) {
+            (true, true) => [&self.bold_italic, &self.bold, &self.italic],
+            (true, false) => [&self.bold, &None, &None],
+            (false, true) => [&self.italic, &None, &None],
+            (false, false) => [&None, &None, &None],
+        };
+        faces.into_iter().flatten().next().unwrap_or(&self.regular)
+    }
+}
+
+/// The formatting of a span of text, set by the tags around it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct SpanFormat {
+    bold: bool,
+    italic: bool,
+    color: Option<[f32; 4]>,
+    size: Option<f32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum MarkupNode {
+    Text(String, SpanFormat),
+    Icon(String, SpanFormat),
+}
+
+/// Splits the markup into spans of text with the same format, and icons.
+fn parse_markup(markup: &str) -> Result<Vec<MarkupNode>, String> {
+    let mut nodes = Vec::new();
+    let mut text = String::new();
+    let mut format = SpanFormat::default();
+    // Open tags, with the format from before they were opened.
+    let mut open_tags: Vec<(String, SpanFormat)> = Vec::new();
+
+    let mut rest = markup;
+    while let Some(character) = rest.chars().next() {
+        if rest.starts_with("[[") {
+            text.push('[');
+            rest = &rest[2..];
+            continue;
+        }
+        // A tag ends at the first ']', unless another '[' comes first.
+        let tag = match character {
+            '[' => rest[1..]
+                .find(['[', ']'])
+                .filter(|&end| rest[1 + end..].starts_with(']'))
+                .map(|end| &rest[1..1 + end]),
+            _ => None,
+        };
+        let Some(tag) = tag else {
+            // Icons are laid out as this character, it can't be in the text itself.
+            if character != ICON_CHARACTER {
+                text.push(character);
+            }
+            rest = &rest[character.len_utf8()..];
+            continue;
+        };
+
+        let (name, value) = match tag.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim())),
+            None => (tag.trim(), None),
+        };
+        let previous = format;
+        let known = match (name, value) {
+            ("b", None) => {
+                format.bold = true;
+                true
+            }
+            ("i", None) => {
+                format.italic = true;
+                true
+            }
+            ("color", Some(value)) => {
+                format.color = Some(parse_hex_color(value)?);
+                true
+            }
+            ("size", Some(value)) => {
+                let size = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|size| *size > 0.0)
+                    .ok_or(format!("Invalid text size '{}'", value))?;
+                format.size = Some(size);
+                true
+            }
+            ("icon", Some(value)) => {
+                flush_text(&mut nodes, &mut text, previous);
+                nodes.push(MarkupNode::Icon(value.to_string(), format));
+                true
+            }
+            _ => match name.strip_prefix('/') {
+                // Closing a tag also closes the tags opened inside it.
+                Some(closed) if value.is_none() && open_tags.iter().any(|(open, _)| open == closed) => {
+                    while let Some((open, before)) = open_tags.pop() {
+                        format = before;
+                        if open == closed {
+                            break;
+                        }
+                    }
+                    true
+                }
+                _ => false,
+            },
+        };
+
+        if known {
+            if format != previous {
+                flush_text(&mut nodes, &mut text, previous);
+            }
+            if name != "icon" && !name.starts_with('/') {
+                open_tags.push((name.to_string(), previous));
+            }
+        } else {
+            text.push('[');
+            text.push_str(tag);
+            text.push(']');
+        }
+        rest = &rest[tag.len() + 2..];
+    }
+
+    flush_text(&mut nodes, &mut text, format);
+    Ok(nodes)
+}
+
+fn flush_text(nodes: &mut Vec<MarkupNode>, text: &mut String, format: SpanFormat) {
+    if !text.is_empty() {
+        nodes.push(MarkupNode::Text(std::mem::take(text), format));
+    }
+}
+
+/// Parses a #rgb, #rgba, #rrggbb or #rrggbbaa color.
+fn parse_hex_color(value: &str) -> Result<[f32; 4], String> {
+    let invalid = || format!("Invalid color '{}', colors are written as #rgb, #rgba, #rrggbb or #rrggbbaa", value);
+    let hex = value.strip_prefix('#').ok_or_else(invalid)?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid());
+    }
+
+    let digits: Vec<u8> = hex.bytes().map(|c| (c as char).to_digit(16).unwrap() as u8).collect();
+    let channels: Vec<f32> = match digits.len() {
+        3 | 4 => digits.iter().map(|d| (d * 17) as f32 / 255.0).collect(),
+        6 | 8 => digits.chunks(2).map(|d| (d[0] * 16 + d[1]) as f32 / 255.0).collect(),
+        _ => return Err(invalid()),
+    };
+    Ok([channels[0], channels[1], channels[2], channels.get(3).copied().unwrap_or(1.0)])
+}
+
+/// Draw text with markup(see the rich text module docs) inside the given box
+/// [x, y, width, height], with `color` being the color of text outside of color tags.
+///
+/// The text is wrapped and aligned like `draw_text_box` does, icons come from `icons`.
+///
+/// _Example(python)_:
+/// ```python
+/// tge.text.draw_rich_text(family, "Costs [icon=wood] [b]12[/b] [color=#8b5a2b]wood[/color]",
+///                         (x, y, 240.0, 80.0), [1.0, 1.0, 1.0, 1.0], icons=resource_icons)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    family,
+    text,
+    rect,
+    color,
+    align=TextAlign::Left,
+    valign=VerticalAlign::Top,
+    wrap=true,
+    line_spacing=1.0,
+    icons=None
+))]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_rich_text(
+    py: Python<'_>,
+    family: PyRef<'_, FontFamily>,
+    text: &str,
+    rect: [f32; 4],
+    color: [f32; 4],
+    align: TextAlign,
+    valign: VerticalAlign,
+    wrap: bool,
+    line_spacing: f32,
+    icons: Option<PyRef<'_, IconAtlas>>,
+) -> PyResult<()> {
+    let nodes = parse_markup(text).map_err(PyValueError::new_err)?;
+
+    // The bidi levels come from the plain text of all the nodes, icons being U+FFFC.
+    let plain_text: String = nodes
+        .iter()
+        .map(|node| match node {
+            MarkupNode::Text(text, _) => text.as_str(),
+            MarkupNode::Icon(..) => "\u{FFFC}",
+        })
+        .collect();
+    let levels = bidi_levels(&plain_text);
+    let mut node_start = 0;
+
+    let mut builder = TextBuilder::default();
+    // Texture coordinates and sizes of the icons, by the glyph index of their layout item.
+    let mut icon_quads: Vec<([f32; 4], [f32; 2])> = Vec::new();
+
+    for node in nodes.iter() {
+        let (MarkupNode::Text(_, format) | MarkupNode::Icon(_, format)) = node;
+        let face = family.face(format.bold, format.italic);
+        let scale = format.size.map_or(1.0, |size| size / face.borrow(py).size());
+        let node_color = format.color.unwrap_or(color);
+
+        match node {
+            MarkupNode::Text(text, _) => {
+                let node_levels = &levels[node_start..node_start + text.len()];
+                builder.push_text(py, face, text, node_levels, scale, node_color);
+                node_start += text.len();
+            }
+            MarkupNode::Icon(name, _) => {
+                let icon = icons.as_ref().and_then(|icons| icons.icon(name));
+                let Some((uv, size)) = icon else {
+                    return Err(PyValueError::new_err(format!("Unknown icon '{}'", name)));
+                };
+                // Icons are as high as the text around them.
+                let style = builder.style(py, face, scale, node_color);
+                let metrics = builder.style_metrics(style);
+                let height = metrics.ascent - metrics.descent;
+                let width = size[0] * height / size[1];
+                icon_quads.push((uv, [width, height]));
+                builder.items.push(LayoutItem {
+                    character: ICON_CHARACTER,
+                    glyph: (icon_quads.len() - 1) as u16,
+                    advance: width,
+                    kerning: 0.0,
+                    offset: [0.0; 2],
+                    level: levels[node_start],
+                    style,
+                });
+                node_start += ICON_CHARACTER.len_utf8();
+            }
+        }
+    }
+
+    let [x, y, width, height] = rect;
+    let options = LayoutOptions {
+        max_width: Some(width),
+        max_height: Some(height),
+        wrap,
+        align,
+        line_spacing,
+    };
+    let layout = builder.layout(py, &options, true);
+    let offset_y = y + layout.vertical_offset(height, valign);
+    builder.draw(py, &layout, x, offset_y, None);
+
+    let ctx = get_tctx();
+    let gl = ctx.get_gl();
+
+    if let Some(icons) = icons {
+        let mut vertices: Vec<f32> = Vec::new();
+        for line in layout.lines.iter() {
+            for positioned in line.glyphs.iter().filter(|glyph| glyph.character == ICON_CHARACTER) {
+                let ([u0, v0, u1, v1], [icon_width, icon_height]) = icon_quads[positioned.glyph as usize];
+                let left = x + positioned.x;
+                let top = offset_y + positioned.baseline - builder.style_metrics(positioned.style).ascent;
+                let (right, bottom) = (left + icon_width, top + icon_height);
+                #[rustfmt::skip]
+                vertices.extend_from_slice(&[
+                    left, top, u0, v0,
+                    right, top, u1, v0,
+                    left, bottom, u0, v1,
+                    right, top, u1, v0,
+                    right, bottom, u1, v1,
+                    left, bottom, u0, v1,
+                ]);
+            }
+        }
+        // Icons keep their own colors.
+        draw_quads_with(&ctx, &gl, TEXTURE_SHADER, icons.texture(), "image", &vertices, |gl, program| unsafe {
+            let color_location = gl.get_uniform_location(program, "color");
+            gl.uniform_4_f32(color_location.as_ref(), 1.0, 1.0, 1.0, 1.0);
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+//! # Shaping
+//!
+//! Turns text into the glyphs of a font and its fallback fonts, ready to be laid out.
+//!
+//! The text is split into runs of characters which have the same font and direction.
+//! Every character uses the first font of the chain which has a glyph for it, except for
+//! whitespace and combining marks, which stay in the font of the character before them.
+//! Runs of outline fonts are shaped with rustybuzz(a port of HarfBuzz), which gives
+//! ligatures, mark positioning and the glyphs of complex scripts like Arabic and Devanagari.
+//! Bitmap fonts are not shaped, they only map characters to glyphs and apply kerning.
+//!
+//! Right to left text(Arabic, Hebrew) gets its direction from the Unicode bidirectional
+//! algorithm. Glyphs stay in logical order, and the layout reorders each line for display.
+//! Reference: https://www.unicode.org/reports/tr9/
+
+use pyo3::prelude::*;
+use unicode_bidi::{BidiClass, BidiInfo, bidi_class};
+
+use crate::engine::gltext::font::Font;
+use crate::engine::gltext::layout::LayoutItem;
+
+/// Returns the font followed by its fallback fonts, without duplicates.
+pub fn font_chain(py: Python<'_>, font: &Py<Font>) -> Vec<Py<Font>> {
+    let mut chain = vec![font.clone_ref(py)];
+    for fallback in font.borrow(py).fallbacks() {
+        if !chain.iter().any(|other| other.is(fallback)) {
+            chain.push(fallback.clone_ref(py));
+        }
+    }
+    chain
+}
+
+/// Returns the bidi embedding level of every byte of the text. Even levels are left to
+/// right, odd levels right to left.
+pub fn bidi_levels(text: &str) -> Vec<u8> {
+    BidiInfo::new(text, None).levels.iter().map(|level| level.number()).collect()
+}
+
+/// Shapes the text with the chain of fonts(see `font_chain`) at the given pixel size.
+///
+/// `levels` are the bidi levels of the bytes of the text(see `bidi_levels`), and `styles`
+/// the style index given to the items of each font of the chain.
+pub fn shape_text(chain: &[PyRef<'_, Font>], text: &str, levels: &[u8], pixel_size: f32, styles: &[usize]) -> Vec<LayoutItem> {
+    let mut items = Vec::with_capacity(text.len());
+
+    for run in split_runs(chain, text, levels) {
+        let run_text = &text[run.start..run.end];
+        if run_text == "\n" {
+            items.push(LayoutItem {
+                character: '\n',
+                glyph: 0,
+                advance: 0.0,
+                kerning: 0.0,
+                offset: [0.0; 2],
+                level: run.level,
+                style: styles[run.font],
+            });
+            continue;
+        }
+
+        let font = &chain[run.font];
+        let scale = pixel_size / font.size();
+        let mut run_items = match font.shape(run_text, run.level % 2 == 1, scale) {
+            Some(shaped) => shaped,
+            None => font.layout_items(run_text, 0, scale),
+        };
+        for item in run_items.iter_mut() {
+            item.style = styles[run.font];
+            item.level = run.level;
+        }
+        items.extend(run_items);
+    }
+    items
+}
+
+/// A range of bytes of the text with the same font(index in the chain) and bidi level.
+#[derive(Clone, Copy, Debug)]
+struct Run {
+    start: usize,
+    end: usize,
+    font: usize,
+    level: u8,
+}
+
+/// Splits the text into runs, newlines always being runs of their own.
+fn split_runs(chain: &[PyRef<'_, Font>], text: &str, levels: &[u8]) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+
+    for (start, character) in text.char_indices() {
+        let end = start + character.len_utf8();
+        let level = levels.get(start).copied().unwrap_or(0);
+        // Newlines are never part of a run with other characters.
+        let previous = runs
+            .last()
+            .filter(|run| character != '\n' && !text[run.start..run.end].ends_with('\n'))
+            .map(|run| (run.font, run.level));
+
+        let font = match previous {
+            // Marks and whitespace are shaped together with the character before them.
+            Some((previous_font, _)) if sticks_to_previous(character) => previous_font,
+            _ => chain
+                .iter()
+                .position(|font| font.glyph_index(character) != 0)
+                .unwrap_or(0),
+        };
+
+        if previous == Some((font, level)) {
+            if let Some(run) = runs.last_mut() {
+                run.end = end;
+            }
+        } else {
+            runs.push(Run {
+                start,
+                end,
+                font,
+                level,
+            });
+        }
+    }
+    runs
+}
+
+/// Whether a character is drawn with the font of the character before it.
+fn sticks_to_previous(character: char) -> bool {
+    character.is_whitespace() || matches!(bidi_class(character), BidiClass::NSM | BidiClass::BN)
+}
@@ -0,0 +1,603 @@
+//! # Text
+//!
+//! Draws and measures strings of text with a `Font`. The text is laid out by the layout
+//! module, then all the glyphs are put into one vertex buffer and drawn from the font's
+//! glyph atlas in a single draw call(or one per effect for SDF fonts). Glyphs from
+//! fallback fonts or other atlas pages add a draw call each.
+
+extern crate glow; // OpenGL bindings
+
+use glow::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::TContext;
+use crate::engine::gltext::distance_field::SDF_SPREAD;
+use crate::engine::gltext::font::{Font, GlyphInfo};
+use crate::engine::gltext::layout::{
+    LayoutItem, LayoutOptions, StyleMetrics, TextAlign, TextLayout, VerticalAlign, layout_text,
+};
+use crate::engine::gltext::rich_text::ICON_CHARACTER;
+use crate::engine::gltext::shaping::{bidi_levels, font_chain, shape_text};
+use crate::engine::helpers::get_tctx;
+
+/// Name of the text shader program in the global shader programs.
+pub const TEXT_SHADER: &str = "text-shader";
+/// Name of the shader program for SDF fonts in the global shader programs.
+pub const TEXT_SDF_SHADER: &str = "text-sdf-shader";
+
+/// Outline, drop shadow and glow of text drawn with an SDF font. All the sizes are in
+/// screen pixels.
+///
+/// Effects reach at most 8 pixels of the font's loaded size away from the glyphs(so 16
+/// screen pixels for a font loaded at 32 pixels drawn at 64), anything wider is cut off.
+///
+/// _Example(python)_:
+/// ```python
+/// label = tge.text.TextEffects(outline_width=2.0, shadow_offset=(2.0, 2.0), shadow_softness=3.0)
+/// tge.text.draw_text(font, "Rivendell", 400.0, 300.0, [1.0, 1.0, 1.0, 1.0], size=24.0, effects=label)
+/// ```
+#[pyclass(get_all, set_all)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextEffects {
+    pub outline_width: f32,
+    pub outline_color: [f32; 4],
+    /// Offset of the shadow from the text, no shadow is drawn if both this and the
+    /// shadow's softness are zero.
+    pub shadow_offset: (f32, f32),
+    pub shadow_color: [f32; 4],
+    pub shadow_softness: f32,
+    /// How far the glow fades out around the text, no glow is drawn if this is zero.
+    pub glow_width: f32,
+    pub glow_color: [f32; 4],
+}
+
+#[pymethods]
+impl TextEffects {
+    #[new]
+    #[pyo3(signature = (
+        outline_width=0.0,
+        outline_color=[0.0, 0.0, 0.0, 1.0],
+        shadow_offset=(0.0, 0.0),
+        shadow_color=[0.0, 0.0, 0.0, 0.5],
+        shadow_softness=0.0,
+        glow_width=0.0,
+        glow_color=[1.0, 1.0, 1.0, 0.5]
+    ))]
+    pub fn new(
+        outline_width: f32,
+        outline_color: [f32; 4],
+        shadow_offset: (f32, f32),
+        shadow_color: [f32; 4],
+        shadow_softness: f32,
+        glow_width: f32,
+        glow_color: [f32; 4],
+    ) -> Self {
+        TextEffects {
+            outline_width,
+            outline_color,
+            shadow_offset,
+            shadow_color,
+            shadow_softness,
+            glow_width,
+            glow_color,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// One pass of drawing SDF glyphs, every effect is drawn as its own pass below the text.
+#[derive(Clone, Copy, Debug)]
+pub struct SdfPass {
+    color: [f32; 4],
+    offset: (f32, f32),
+    /// How far(in screen pixels) the glyphs are grown outwards.
+    dilate: f32,
+    softness: f32,
+    outline_width: f32,
+    outline_color: [f32; 4],
+}
+
+impl SdfPass {
+    fn plain(color: [f32; 4]) -> Self {
+        SdfPass {
+            color,
+            offset: (0.0, 0.0),
+            dilate: 0.0,
+            softness: 0.0,
+            outline_width: 0.0,
+            outline_color: [0.0; 4],
+        }
+    }
+}
+
+/// Returns the draw passes of text with the given color and effects, from bottom to top.
+pub fn sdf_passes(color: [f32; 4], effects: Option<&TextEffects>) -> Vec<SdfPass> {
+    let Some(effects) = effects else {
+        return vec![SdfPass::plain(color)];
+    };
+
+    let mut passes = Vec::with_capacity(3);
+    if effects.shadow_offset != (0.0, 0.0) || effects.shadow_softness > 0.0 {
+        // The shadow of the text together with its outline.
+        passes.push(SdfPass {
+            offset: effects.shadow_offset,
+            dilate: effects.outline_width,
+            softness: effects.shadow_softness,
+            ..SdfPass::plain(effects.shadow_color)
+        });
+    }
+    if effects.glow_width > 0.0 {
+        passes.push(SdfPass {
+            dilate: effects.outline_width + effects.glow_width / 2.0,
+            softness: effects.glow_width,
+            ..SdfPass::plain(effects.glow_color)
+        });
+    }
+    passes.push(SdfPass {
+        outline_width: effects.outline_width,
+        outline_color: effects.outline_color,
+        ..SdfPass::plain(color)
+    });
+    passes
+}
+
+/// Returns how much the font has to be scaled to be drawn at the given size.
+fn font_scale(font: &Font, size: Option<f32>) -> f32 {
+    size.map_or(1.0, |size| size / font.size())
+}
+
+fn check_effects(font: &Font, effects: &Option<TextEffects>) -> PyResult<()> {
+    if effects.is_some() && !font.is_sdf() {
+        return Err(PyValueError::new_err(
+            "Text effects need an SDF font, load the font with load_font(path, size, sdf=True)",
+        ));
+    }
+    Ok(())
+}
+
+/// Draw the given text with the given font and color [r: f32, g: f32, b: f32, a: f32],
+/// with the top left corner of the first line at the given screen coordinates(x and y).
+///
+/// Every line's glyphs sit on the line's baseline, which is the font's ascent below the
+/// top of the line. Newlines('\n') start a new line below the previous one.
+///
+/// The text is drawn at the pixel size the font was loaded at, or at `size` if given. Only
+/// SDF fonts stay sharp when drawn at other sizes, and only they can be drawn with `effects`.
+///
+/// The text is shaped(see the shaping module), characters the font does not have are drawn
+/// with its fallback fonts, and right to left text is drawn right to left.
+#[pyfunction]
+#[pyo3(signature = (font, text, x, y, color, size=None, effects=None))]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text(
+    py: Python<'_>,
+    font: Py<Font>,
+    text: &str,
+    x: f32,
+    y: f32,
+    color: [f32; 4],
+    size: Option<f32>,
+    effects: Option<TextEffects>,
+) -> PyResult<()> {
+    check_effects(&font.borrow(py), &effects)?;
+    let scale = font_scale(&font.borrow(py), size);
+
+    let mut builder = TextBuilder::default();
+    builder.push_text(py, &font, text, &bidi_levels(text), scale, color);
+    let layout = builder.layout(py, &LayoutOptions::default(), false);
+    builder.draw(py, &layout, x, y, effects.as_ref());
+    Ok(())
+}
+
+/// Draw the given text inside the given box [x, y, width, height].
+///
+/// Lines are aligned inside the box with `align` and the whole block of text with
+/// `valign`. With `wrap` set, lines are broken between words to fit the width of the
+/// box, otherwise only at newlines('\n'). Text that does not fit in the box is cut off
+/// and ends with an ellipsis("...").
+///
+/// `line_spacing` multiplies the font's line height, `size` and `effects` work like they
+/// do for `draw_text`.
+///
+/// _Example(python)_:
+/// ```python
+/// tge.text.draw_text_box(font, description, (20.0, 500.0, 300.0, 120.0), [1.0, 1.0, 1.0, 1.0],
+///                        align=tge.text.TextAlign.Justify, valign=tge.text.VerticalAlign.Middle)
+/// ```
+#[pyfunction]
+#[pyo3(signature = (
+    font,
+    text,
+    rect,
+    color,
+    align=TextAlign::Left,
+    valign=VerticalAlign::Top,
+    wrap=true,
+    line_spacing=1.0,
+    size=None,
+    effects=None
+))]
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_box(
+    py: Python<'_>,
+    font: Py<Font>,
+    text: &str,
+    rect: [f32; 4],
+    color: [f32; 4],
+    align: TextAlign,
+    valign: VerticalAlign,
+    wrap: bool,
+    line_spacing: f32,
+    size: Option<f32>,
+    effects: Option<TextEffects>,
+) -> PyResult<()> {
+    check_effects(&font.borrow(py), &effects)?;
+    let scale = font_scale(&font.borrow(py), size);
+
+    let [x, y, width, height] = rect;
+    let options = LayoutOptions {
+        max_width: Some(width),
+        max_height: Some(height),
+        wrap,
+        align,
+        line_spacing,
+    };
+
+    let mut builder = TextBuilder::default();
+    builder.push_text(py, &font, text, &bidi_levels(text), scale, color);
+    let layout = builder.layout(py, &options, true);
+
+    let offset_y = layout.vertical_offset(height, valign);
+    builder.draw(py, &layout, x, y + offset_y, effects.as_ref());
+    Ok(())
+}
+
+/// The size of a block of text, as returned by `measure_text`.
+#[pyclass(get_all)]
+#[derive(Clone, Debug)]
+pub struct TextMetrics {
+    /// Width of the widest line.
+    pub width: f32,
+    /// Height of all the lines together.
+    pub height: f32,
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_height: f32,
+    /// Width of every line.
+    pub line_widths: Vec<f32>,
+}
+
+#[pymethods]
+impl TextMetrics {
+    #[getter]
+    pub fn line_count(&self) -> usize {
+        self.line_widths.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "TextMetrics(width={}, height={}, line_count={})",
+            self.width,
+            self.height,
+            self.line_widths.len()
+        )
+    }
+}
+
+/// Measures the given text as `draw_text` would draw it, or as `draw_text_box` would
+/// wrap it if `max_width` is given. The ascent, descent and line height are the font's.
+#[pyfunction]
+#[pyo3(signature = (font, text, max_width=None, line_spacing=1.0, size=None))]
+pub fn measure_text(
+    py: Python<'_>,
+    font: Py<Font>,
+    text: &str,
+    max_width: Option<f32>,
+    line_spacing: f32,
+    size: Option<f32>,
+) -> TextMetrics {
+    let scale = font_scale(&font.borrow(py), size);
+    let options = LayoutOptions {
+        max_width,
+        wrap: max_width.is_some(),
+        line_spacing,
+        ..LayoutOptions::default()
+    };
+
+    let mut builder = TextBuilder::default();
+    builder.push_text(py, &font, text, &bidi_levels(text), scale, [1.0; 4]);
+    let layout = builder.layout(py, &options, false);
+    let metrics = font.borrow(py).style_metrics(scale);
+
+    TextMetrics {
+        width: layout.width,
+        height: layout.height,
+        ascent: metrics.ascent,
+        descent: metrics.descent,
+        line_height: metrics.line_height * line_spacing,
+        line_widths: layout.lines.iter().map(|line| line.width).collect(),
+    }
+}
+
+/// How the glyphs of one style are drawn, the glyphs of a style are drawn together.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TextStyle {
+    /// Index of the font in the distinct fonts used by the text.
+    font: usize,
+    scale: f32,
+    color: [f32; 4],
+}
+
+/// Collects the layout items of text drawn with any number of fonts, sizes and colors,
+/// then lays them out and draws them with one draw call per style and atlas page.
+#[derive(Default)]
+pub struct TextBuilder {
+    fonts: Vec<Py<Font>>,
+    styles: Vec<TextStyle>,
+    style_metrics: Vec<StyleMetrics>,
+    pub items: Vec<LayoutItem>,
+}
+
+impl TextBuilder {
+    /// Returns the index of the style, adding it if the text does not use it yet.
+    pub fn style(&mut self, py: Python<'_>, font: &Py<Font>, scale: f32, color: [f32; 4]) -> usize {
+        let font_index = match self.fonts.iter().position(|other| other.is(font)) {
+            Some(index) => index,
+            None => {
+                self.fonts.push(font.clone_ref(py));
+                self.fonts.len() - 1
+            }
+        };
+        let style = TextStyle {
+            font: font_index,
+            scale,
+            color,
+        };
+        match self.styles.iter().position(|other| *other == style) {
+            Some(index) => index,
+            None => {
+                self.styles.push(style);
+                self.style_metrics.push(font.borrow(py).style_metrics(scale));
+                self.styles.len() - 1
+            }
+        }
+    }
+
+    pub fn style_metrics(&self, style: usize) -> StyleMetrics {
+        self.style_metrics[style]
+    }
+
+    /// Shapes the text with the font and its fallbacks at `scale` times the font's size,
+    /// `levels` being the bidi levels of its bytes(see `bidi_levels`).
+    pub fn push_text(&mut self, py: Python<'_>, font: &Py<Font>, text: &str, levels: &[u8], scale: f32, color: [f32; 4]) {
+        let pixel_size = font.borrow(py).size() * scale;
+        let chain = font_chain(py, font);
+        // Fallback fonts are scaled to the same pixel size as the font.
+        let styles: Vec<usize> = chain
+            .iter()
+            .map(|fallback| {
+                let fallback_scale = pixel_size / fallback.borrow(py).size();
+                self.style(py, fallback, fallback_scale, color)
+            })
+            .collect();
+
+        let borrowed: Vec<PyRef<'_, Font>> = chain.iter().map(|font| font.borrow(py)).collect();
+        self.items.extend(shape_text(&borrowed, text, levels, pixel_size, &styles));
+    }
+
+    /// Lays out the items, ending text cut off by the height of the box with an ellipsis
+    /// if `ellipsis` is set.
+    pub fn layout(&self, py: Python<'_>, options: &LayoutOptions, ellipsis: bool) -> TextLayout {
+        let ellipsis_of_style = |style_index: usize| {
+            let style = self.styles[style_index];
+            ellipsis_items(&self.fonts[style.font].borrow(py), style_index, style.scale)
+        };
+        let ellipsis: Option<&dyn Fn(usize) -> Vec<LayoutItem>> = match ellipsis {
+            true => Some(&ellipsis_of_style),
+            false => None,
+        };
+        layout_text(&self.items, &self.style_metrics, options, ellipsis)
+    }
+
+    /// Draws the glyphs of the layout with its top left corner at (x, y). Effects are only
+    /// drawn for the styles of SDF fonts.
+    pub fn draw(&self, py: Python<'_>, layout: &TextLayout, x: f32, y: f32, effects: Option<&TextEffects>) {
+        let ctx = get_tctx();
+        let gl = ctx.get_gl();
+
+        for (style_index, style) in self.styles.iter().enumerate() {
+            let mut font = self.fonts[style.font].borrow_mut(py);
+            font.begin_draw();
+
+            // The vertices of the glyphs in every atlas page.
+            let mut page_vertices: Vec<Vec<f32>> = Vec::new();
+            for line in layout.lines.iter() {
+                for positioned in line.glyphs.iter() {
+                    if positioned.style != style_index
+                        || positioned.character == ICON_CHARACTER
+                        || positioned.character.is_whitespace()
+                    {
+                        continue;
+                    }
+                    let glyph = font.glyph(&gl, positioned.glyph);
+                    if page_vertices.len() <= glyph.page {
+                        page_vertices.resize(glyph.page + 1, Vec::new());
+                    }
+                    let pen_x = x + positioned.x + positioned.offset[0];
+                    let baseline = y + positioned.baseline + positioned.offset[1];
+                    push_glyph_quad(&mut page_vertices[glyph.page], &glyph, pen_x, baseline, style.scale);
+                }
+            }
+
+            for (page, vertices) in page_vertices.iter().enumerate() {
+                if font.is_sdf() {
+                    let passes = sdf_passes(style.color, effects);
+                    draw_sdf_glyph_quads(&ctx, &gl, font.atlas_texture(page), vertices, style.scale, &passes);
+                } else {
+                    draw_glyph_quads(&ctx, &gl, font.atlas_texture(page), vertices, style.color);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the layout items of the ellipsis character, or of three dots if the font does
+/// not have it.
+fn ellipsis_items(font: &Font, style: usize, scale: f32) -> Vec<LayoutItem> {
+    let (character, count) = match font.glyph_index('\u{2026}') {
+        0 => ('.', 3),
+        _ => ('\u{2026}', 1),
+    };
+    let glyph = font.glyph_index(character);
+    let item = LayoutItem {
+        character,
+        glyph,
+        advance: font.advance(glyph) * scale,
+        kerning: 0.0,
+        offset: [0.0; 2],
+        level: 0,
+        style,
+    };
+    vec![item; count]
+}
+
+/// Pushes the two triangles(as x, y, u, v vertices) of a glyph drawn at `scale` times its
+/// size with its pen position at (pen_x, baseline).
+///
+/// Unscaled quads are snapped to whole pixels so that the glyph bitmap is not blurred.
+pub fn push_glyph_quad(vertices: &mut Vec<f32>, glyph: &GlyphInfo, pen_x: f32, baseline: f32, scale: f32) {
+    if glyph.size[0] <= 0.0 || glyph.size[1] <= 0.0 {
+        return;
+    }
+    let mut left = pen_x + glyph.offset[0] * scale;
+    let mut top = baseline + glyph.offset[1] * scale;
+    if scale == 1.0 {
+        left = left.round();
+        top = top.round();
+    }
+    let right = left + glyph.size[0] * scale;
+    let bottom = top + glyph.size[1] * scale;
+    let [u0, v0, u1, v1] = glyph.uv;
+
+    #[rustfmt::skip]
+    vertices.extend_from_slice(&[
+        left, top, u0, v0,
+        right, top, u1, v0,
+        left, bottom, u0, v1,
+        right, top, u1, v0,
+        right, bottom, u1, v1,
+        left, bottom, u0, v1,
+    ]);
+}
+
+/// Draws glyph quads(made by push_glyph_quad) from the given atlas texture with the text shader.
+pub fn draw_glyph_quads(ctx: &TContext, gl: &Context, atlas: NativeTexture, vertices: &[f32], color: [f32; 4]) {
+    draw_quads_with(ctx, gl, TEXT_SHADER, atlas, "glyphAtlas", vertices, |gl, program| unsafe {
+        let color_location = gl.get_uniform_location(program, "color");
+        gl.uniform_4_f32(color_location.as_ref(), color[0], color[1], color[2], color[3]);
+    });
+}
+
+/// Draws glyph quads(made by push_glyph_quad, at the given scale) from the given distance
+/// field atlas with the SDF text shader, once for every pass.
+pub fn draw_sdf_glyph_quads(
+    ctx: &TContext,
+    gl: &Context,
+    atlas: NativeTexture,
+    vertices: &[f32],
+    scale: f32,
+    passes: &[SdfPass],
+) {
+    for pass in passes {
+        let shifted: Vec<f32>;
+        let pass_vertices = if pass.offset == (0.0, 0.0) {
+            vertices
+        } else {
+            shifted = vertices
+                .chunks_exact(4)
+                .flat_map(|vertex| [vertex[0] + pass.offset.0, vertex[1] + pass.offset.1, vertex[2], vertex[3]])
+                .collect();
+            &shifted
+        };
+
+        draw_quads_with(ctx, gl, TEXT_SDF_SHADER, atlas, "glyphAtlas", pass_vertices, |gl, program| unsafe {
+            let [r, g, b, a] = pass.color;
+            let color_location = gl.get_uniform_location(program, "color");
+            gl.uniform_4_f32(color_location.as_ref(), r, g, b, a);
+
+            let [r, g, b, a] = pass.outline_color;
+            let outline_color_location = gl.get_uniform_location(program, "outlineColor");
+            gl.uniform_4_f32(outline_color_location.as_ref(), r, g, b, a);
+
+            let uniforms = [
+                ("distanceRange", SDF_SPREAD as f32),
+                ("pixelScale", scale),
+                ("outlineWidth", pass.outline_width),
+                ("dilate", pass.dilate),
+                ("softness", pass.softness),
+            ];
+            for (name, value) in uniforms {
+                let location = gl.get_uniform_location(program, name);
+                gl.uniform_1_f32(location.as_ref(), value);
+            }
+        });
+    }
+}
+
+/// Draws textured quads(x, y, u, v vertices) from the atlas with the given shader program,
+/// the atlas being bound to its `sampler` uniform. `set_uniforms` sets the other uniforms
+/// of the program.
+pub fn draw_quads_with(
+    ctx: &TContext,
+    gl: &Context,
+    program_name: &str,
+    atlas: NativeTexture,
+    sampler: &str,
+    vertices: &[f32],
+    set_uniforms: impl FnOnce(&Context, NativeProgram),
+) {
+    if vertices.is_empty() {
+        return;
+    }
+    let (vao, vbo, default_program) = ctx.get_gl_parameters();
+    let program = ctx.get_shader_program(String::from(program_name));
+
+    ctx.apply_blend_mode(gl, program);
+
+    unsafe {
+        gl.use_program(Some(program));
+
+        gl.bind_vertex_array(Some(vao));
+        gl.bind_buffer(ARRAY_BUFFER, Some(vbo));
+
+        gl.buffer_data_u8_slice(ARRAY_BUFFER, bytemuck::cast_slice(vertices), DYNAMIC_DRAW);
+
+        let stride = 4 * std::mem::size_of::<f32>() as i32;
+        gl.vertex_attrib_pointer_f32(0, 2, FLOAT, false, stride, 0);
+        gl.enable_vertex_attrib_array(0);
+        gl.vertex_attrib_pointer_f32(1, 2, FLOAT, false, stride, 2 * std::mem::size_of::<f32>() as i32);
+        gl.enable_vertex_attrib_array(1);
+
+        gl.active_texture(TEXTURE0);
+        gl.bind_texture(TEXTURE_2D, Some(atlas));
+
+        let atlas_location = gl.get_uniform_location(program, sampler);
+        gl.uniform_1_i32(atlas_location.as_ref(), 0);
+
+        set_uniforms(gl, program);
+
+        gl.draw_arrays(TRIANGLES, 0, (vertices.len() / 4) as i32);
+
+        gl.bind_texture(TEXTURE_2D, None);
+        gl.disable_vertex_attrib_array(1);
+        gl.bind_buffer(ARRAY_BUFFER, None);
+        gl.bind_vertex_array(None);
+
+        // Switch back to the default shader program for the other draw functions.
+        gl.use_program(Some(default_program));
+    }
+}
@@ -7,6 +7,9 @@ use pyo3::prelude::*;
 
 use crate::engine::helpers::get_tctx;
 
+/// Name of the shader program drawing tinted RGBA textures in the global shader programs.
+pub const TEXTURE_SHADER: &str = "texture-shader";
+
 pub fn load_texture(gl: &Context, filename: &str) -> Result<NativeTexture, String> {
     let img = image::open(filename).map_err(|e| format!("Failed to open image: {}", e))?;
     let img = img.to_rgba8();
@@ -1,8 +1,10 @@
+pub mod events;
 pub mod gl2d;
 pub mod gltext;
 pub mod helpers;
 pub mod objects;
 pub mod sound;
 pub mod test;
+pub mod tilemap;
 pub mod video;
 pub mod window;
@@ -4,6 +4,7 @@ extern crate sdl3; // SDL3 bindings
 use glow::*;
 use pyo3::prelude::*;
 
+use crate::engine::gl2d::gradient::{Paint, set_paint_uniforms};
 use crate::engine::gltext::textures::load_texture;
 use crate::engine::helpers::get_tctx;
 
@@ -25,10 +26,15 @@ pub fn tengine_place_img(x: f32, y: f32, width: f32, height: f32, image_path: &s
 
     let texture = load_texture(&gl, image_path);
 
-    unsafe {
-        gl.enable(glow::BLEND);
-        gl.blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
+    let program = ctx
+        .global_program
+        .read()
+        .unwrap_or_else(|err| panic!("Failed to acquire read lock for global_program: {:?}", err))
+        .unwrap_or_else(|| panic!("global_program is None"));
+
+    ctx.apply_blend_mode(&gl, program);
 
+    unsafe {
         let vertices: [f32; 24] = [
             x,
             y,
@@ -85,14 +91,6 @@ pub fn tengine_place_img(x: f32, y: f32, width: f32, height: f32, image_path: &s
         );
         gl.enable_vertex_attrib_array(1);
 
-        let program = ctx
-            .global_program
-            .read()
-            .unwrap_or_else(|err| {
-                panic!("Failed to acquire read lock for global_program: {:?}", err)
-            })
-            .unwrap_or_else(|| panic!("global_program is None"));
-
         gl.active_texture(glow::TEXTURE0);
         let texture = match load_texture(&gl, image_path) {
             Ok(tex) => tex,
@@ -101,8 +99,7 @@ pub fn tengine_place_img(x: f32, y: f32, width: f32, height: f32, image_path: &s
 
         gl.bind_texture(glow::TEXTURE_2D, Some(texture));
 
-        let color_location = gl.get_uniform_location(program, "color");
-        gl.uniform_4_f32(color_location.as_ref(), 1.0, 1.0, 1.0, 1.0);
+        set_paint_uniforms(&gl, program, &Paint::Color([1.0; 4]), [x, y, x + width, y + height]);
 
         let image_location = gl.get_uniform_location(program, "image");
         gl.uniform_1_i32(image_location.as_ref(), 0);
@@ -1,9 +1,55 @@
 #version 330 core
 
+#define MAX_GRADIENT_STOPS 16
+
+in vec2 FragPos;
+
 uniform vec4 color;
 
+// Gradient fill uniforms, see gl2d/gradient.rs
+uniform int paintType;        // 0 = solid color, 1 = linear gradient, 2 = radial gradient
+uniform vec2 gradientStart;   // Linear gradient start point, or radial gradient center
+uniform vec2 gradientEnd;     // Linear gradient end point, or (radius, 0) for radial gradients
+uniform int stopCount;
+uniform float stopOffsets[MAX_GRADIENT_STOPS];
+uniform vec4 stopColors[MAX_GRADIENT_STOPS];
+
 out vec4 fragColor;
 
+// Set when the blend mode needs the color premultiplied by its alpha(see gl2d/blend.rs).
+uniform int premultiplyAlpha;
+
+vec4 gradientColor(float t) {
+    if (t <= stopOffsets[0]) {
+        return stopColors[0];
+    }
+    for (int i = 1; i < stopCount; i++) {
+        if (t <= stopOffsets[i]) {
+            float span = max(stopOffsets[i] - stopOffsets[i - 1], 1e-6);
+            float f = (t - stopOffsets[i - 1]) / span;
+            // Interpolate with premultiplied alpha, so fading to a transparent stop
+            // does not darken the colors in between.
+            vec4 a = vec4(stopColors[i - 1].rgb * stopColors[i - 1].a, stopColors[i - 1].a);
+            vec4 b = vec4(stopColors[i].rgb * stopColors[i].a, stopColors[i].a);
+            vec4 c = mix(a, b, f);
+            return c.a > 0.0 ? vec4(c.rgb / c.a, c.a) : vec4(0.0);
+        }
+    }
+    return stopColors[stopCount - 1];
+}
+
 void main() {
-    fragColor = color;
+    if (paintType == 1) {
+        vec2 direction = gradientEnd - gradientStart;
+        float t = dot(FragPos - gradientStart, direction) / max(dot(direction, direction), 1e-6);
+        fragColor = gradientColor(t) * color;
+    } else if (paintType == 2) {
+        float t = length(FragPos - gradientStart) / max(gradientEnd.x, 1e-6);
+        fragColor = gradientColor(t) * color;
+    } else {
+        fragColor = color;
+    }
+    if (premultiplyAlpha != 0) {
+        fragColor.rgb *= fragColor.a;
+    }
 }
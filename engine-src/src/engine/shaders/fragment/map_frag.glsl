@@ -1,6 +1,9 @@
 #version 330 core
 out vec4 FragColor;
 
+// Set when the blend mode needs the color premultiplied by its alpha(see gl2d/blend.rs).
+uniform int premultiplyAlpha;
+
 in vec2 TexCoord;
 flat in float TexIndex;
 in float Highlight;
@@ -17,74 +20,94 @@ in float TextureDimensions;
 in float TextureIndex;
 in vec2 FragPos;
 in vec3 Normal;
+in vec2 FogCoord;
 
 // Texture atlas uniform
 uniform sampler2D textureatlas;
 
-// New lighting uniforms
-uniform float gameTime;  // Time in seconds (0-4500 for full day cycle)
-uniform float dayDuration;  // Total duration of day+night cycle in seconds
+// Lighting uniforms, set from the day night cycle every draw
+uniform vec3 sunDirection;  // Normalized direction the sun light comes from
+uniform vec3 ambientLight;  // Ambient light color, scaled by its strength
+uniform vec3 sunColor;      // Color of the sun light
+uniform float lightIntensity;  // Multiplier of the whole light
 uniform vec2 screenDimensions;  // Screen width and height
 
 //Texture Related Uniforms
 uniform float textureSize; //Defines size of the tile
 uniform vec2 textureAtlasSize; // Size of the texture atlas
-
-uniform float ticks;
-
-vec3 calculateSunPosition() {
-     // Calculate day and night portions
-     float nightPortion = 0.2;  // 20% of total time is night
-     float dayLength = dayDuration * (1.0 - nightPortion);    // 80% of total time
-     float nightLength = dayDuration * nightPortion;          // 20% of total time
-     
-     // Offset the sun angle to start at 10 AM (approximately 1/3 into the day)
-     float morningOffset = 1.047;  // PI/3 radians
-     
-     // Calculate sun angle based on time of day
-     float sunAngle;
-     if (mod(gameTime, dayDuration) < dayLength) {
-          // During day (80% of total time)
-          sunAngle = (mod(gameTime, dayLength) / dayLength) * 3.14159 + morningOffset;
-     } else {
-          // During night (20% of total time)
-          sunAngle = 3.14159 + ((mod(gameTime, dayLength) / nightLength) * 3.14159) + morningOffset;
+uniform vec2 tileDimensions; // Size of the tile quad in pixels
+uniform int tileShape; // Shape the tile quad is cut to, one of the TILE_SHAPE constants
+
+uniform float ticks;  // Time in milliseconds, advanced by the map every draw
+
+// Fog of war, a texel per tile: 0.0 unexplored, 0.5 explored and 1.0 visible.
+uniform sampler2D fogTexture;
+uniform int fogEnabled;
+uniform vec2 mapSize;  // Size of the map in tiles
+uniform float fogExploredBrightness;
+uniform float fogExploredSaturation;
+
+// Overlay lookup table, every texel is (atlas cell x, atlas cell y, frame count, ticks per frame),
+// or (animation id, -1, 1, 0) for an entry drawn with an animation.
+// Row 0 has the overlay of every structure id, row 1 the other overlays and row 2 the ground textures.
+uniform sampler2D overlayTable;
+uniform int overlayTableWidth;
+
+// Animation table, a row per animation: the header (frame count, total duration, loop mode,
+// start ticks) then the frames (atlas cell x, atlas cell y, ticks the frame ends at, 0).
+uniform sampler2D animationTable;
+
+const int STRUCTURE_ROW = 0;
+const int OVERLAY_ROW = 1;
+const int TEXTURE_ROW = 2;
+const int OVERLAY_WORKER = 0;
+const int OVERLAY_TROOPS = 1;
+const int OVERLAY_SELECTED = 2;
+const int OVERLAY_HIGHLIGHT = 3;
+const int OVERLAY_RESOURCE_HIGHLIGHT = 4;
+
+const int LOOP_MODE_LOOP = 0;
+const int LOOP_MODE_ONCE = 1;
+const int LOOP_MODE_PING_PONG = 2;
+
+const int TILE_SHAPE_SQUARE = 0;
+const int TILE_SHAPE_DIAMOND = 1;
+const int TILE_SHAPE_HEX_POINTY = 2;
+const int TILE_SHAPE_HEX_FLAT = 3;
+
+// Distance (in pixels) from the fragment to the border of the tile's shape, negative outside of it.
+float distanceToBorder() {
+     vec2 halfSize = tileDimensions * 0.5;
+     vec2 p = abs(FragPos - halfSize);
+
+     if (tileShape == TILE_SHAPE_DIAMOND) {
+          vec2 inverse = 1.0 / halfSize;
+          return (1.0 - dot(p, inverse)) / length(inverse);
+     } else if (tileShape == TILE_SHAPE_HEX_POINTY) {
+          // The apothem is half the width, the edges face 0, 60 and 120 degrees.
+          return halfSize.x - max(p.x, dot(p, vec2(0.5, 0.8660254)));
+     } else if (tileShape == TILE_SHAPE_HEX_FLAT) {
+          return halfSize.y - max(p.y, dot(p, vec2(0.8660254, 0.5)));
      }
-     
-     float sunHeight = sin(sunAngle);
-     float sunX = cos(sunAngle);
-     
-     return normalize(vec3(sunX, sunHeight, 0.5));
+     return min(halfSize.x - p.x, halfSize.y - p.y);
 }
 
 vec3 calculateLighting(vec4 baseColor) {
-     vec3 sunPosition = calculateSunPosition();
-     
-     float ambientStrength = 0.2;
-     vec3 ambient = ambientStrength * vec3(1.0);
-     
-     float diff = max(dot(Normal, sunPosition), 0.0);
-     vec3 diffuse = diff * vec3(1.0);
-     
-     float nightPortion = 0.2;
-     float dayLength = dayDuration * (1.0 - nightPortion);
-     float timeInCycle = mod(gameTime, dayDuration);
-     
-     float intensityMultiplier;
-     if (timeInCycle < dayLength) {
-          // During day
-          float dayProgress = (timeInCycle / dayLength + 1.0/3.0);
-          if (dayProgress > 1.0) dayProgress -= 1.0;
-          
-          float noonBoost = 1.25;
-          float noonFocus = 3.0;
-          intensityMultiplier = max(pow(sin(dayProgress * 3.14159), noonFocus) * noonBoost + 0.3, 0.3);
-     } else {
-          // During night
-          intensityMultiplier = 0.3;
-     }
-     
-     return (ambient + diffuse) * intensityMultiplier;
+     float diff = max(dot(Normal, sunDirection), 0.0);
+     vec3 diffuse = diff * sunColor;
+
+     return (ambientLight + diffuse) * lightIntensity;
+}
+
+// Blackens unexplored tiles and dims explored ones, the fog being blended between tiles.
+vec3 applyFog(vec3 color) {
+     float fog = texture(fogTexture, (FogCoord + 0.5) / mapSize).r;
+     float explored = clamp(fog * 2.0, 0.0, 1.0);
+     float visible = clamp(fog * 2.0 - 1.0, 0.0, 1.0);
+
+     float gray = dot(color, vec3(0.299, 0.587, 0.114));
+     vec3 dimmed = mix(vec3(gray), color, fogExploredSaturation) * fogExploredBrightness;
+     return mix(dimmed, color, visible) * explored;
 }
 
 void highlight_Frag(float minDist, vec4 highlightColor, float borderThickness, vec4 texColor) {
@@ -155,6 +178,63 @@ vec2 getCoordsFromAtlas(int xIndex, int yIndex, float fDimensions, float fSubInd
      }
 }
 
+// Finds the atlas cell of the current frame of an animation, false if it has no frames.
+bool animationCell(int id, out ivec2 cell) {
+     vec4 header = texelFetch(animationTable, ivec2(0, id), 0);
+     int frames = int(header.x);
+     if (frames < 1) {
+          return false;
+     }
+     float duration = header.y;
+     int loopMode = int(header.z);
+     float time = max(ticks - header.w, 0.0);
+     if (loopMode == LOOP_MODE_LOOP) {
+          time = mod(time, duration);
+     } else if (loopMode == LOOP_MODE_ONCE) {
+          time = min(time, duration);
+     } else {
+          time = mod(time, duration * 2.0);
+          time = time > duration ? duration * 2.0 - time : time;
+     }
+
+     for (int i = 0; i < frames; i++) {
+          vec4 frame = texelFetch(animationTable, ivec2(i + 1, id), 0);
+          cell = ivec2(frame.xy);
+          if (time < frame.z) {
+               break;
+          }
+     }
+     return true;
+}
+
+// Finds the atlas cell of the current frame of an entry of the overlay table, false if it is not drawn.
+bool entryCell(int row, int id, out ivec2 cell) {
+     if (id < 0 || id >= overlayTableWidth) {
+          return false;
+     }
+     vec4 entry = texelFetch(overlayTable, ivec2(id, row), 0);
+     if (entry.y < 0.0) {
+          return animationCell(int(entry.x), cell);
+     }
+     int frames = int(entry.z);
+     if (frames < 1) {
+          return false;
+     }
+     int frame = entry.w > 0.0 ? int(ticks / entry.w) % frames : 0;
+     cell = ivec2(int(entry.x) + frame, int(entry.y));
+     return true;
+}
+
+// Draws the current frame of the overlay at the given entry of the overlay table.
+void drawOverlay(int row, int id) {
+     ivec2 cell;
+     if (!entryCell(row, id, cell)) {
+          return;
+     }
+     vec4 overlayColor = texture(textureatlas, getCoordsFromAtlas(cell.x, cell.y, TextureDimensions, TextureIndex));
+     overlayTexture(FragColor, overlayColor, 1.0);
+}
+
 void main()
 {
      vec4 texColor;
@@ -164,22 +244,19 @@ void main()
      vec4 resourceHighlightColor = vec4(1.0, 0.0, 0.0, 1.0);
 
      float borderThickness = max(2.0, textureSize / 16.0);
-     vec2 distFromEdge = min(FragPos, vec2(textureSize) - FragPos);
-     float minDist = min(distFromEdge.x, distFromEdge.y);
-
-     float distFromCenter = length(FragPos - vec2(textureSize / 2.0, textureSize / 2.0)) / (textureSize / 8.0);
-
-     // Select base texture
-     if (TexIndex == 0.0) {
-          texColor = texture(textureatlas, getCoordsFromAtlas(0, 0, 1, 1));
-     } else if (TexIndex == 1.0) {
-          texColor = texture(textureatlas, getCoordsFromAtlas(1, 0, 1, 1));
-     } else if (TexIndex == 2.0) {
-          texColor = texture(textureatlas, getCoordsFromAtlas(2, 0, 1, 1));
-     } else if (TexIndex == 3.0) {
-          texColor = texture(textureatlas, getCoordsFromAtlas(3, 0, 1, 1));
+     float minDist = distanceToBorder();
+     if (minDist < 0.0) {
+          discard;
+     }
+
+     float distFromCenter = length(FragPos - tileDimensions / 2.0) / (textureSize / 8.0);
+
+     // Select base texture, textures past the table are drawn like the last one
+     ivec2 groundCell;
+     if (entryCell(TEXTURE_ROW, min(int(TexIndex), overlayTableWidth - 1), groundCell)) {
+          texColor = texture(textureatlas, getCoordsFromAtlas(groundCell.x, groundCell.y, 1, 1));
      } else {
-          texColor = texture(textureatlas, getCoordsFromAtlas(4, 0, 1, 1));
+          texColor = vec4(0.0);
      }
 
      // Start with base color and apply lighting
@@ -199,226 +276,42 @@ void main()
      
      // Apply structure overlays
      if (Structure > 0.0) {
-          if (Structure == 1.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(5, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 2.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(6, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 3.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(7, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 4.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(11, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 5.0) {
-               if (int(ticks / 200) % 3 == 0) {
-                    vec4 fortColor = texture(textureatlas, getCoordsFromAtlas(17, 1, TextureDimensions, TextureIndex));
-                    overlayTexture(FragColor, fortColor, Structure);
-               } else if (int(ticks / 200) % 3 == 1) {
-                    vec4 fortColor = texture(textureatlas, getCoordsFromAtlas(18, 1, TextureDimensions, TextureIndex));
-                    overlayTexture(FragColor, fortColor, Structure);
-               } else {
-                    vec4 fortColor = texture(textureatlas, getCoordsFromAtlas(19, 1, TextureDimensions, TextureIndex));
-                    overlayTexture(FragColor, fortColor, Structure);
-               }
-          }
-          else if (Structure == 6.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(10, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 7.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(9, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 8.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(12, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 9.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(13, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 10.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(14, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 11.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(15, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 12.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(16, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 13.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(17, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 14.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(18, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 15.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(19, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 16.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(20, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 17.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(21, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 18.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(22, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 19.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(23, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 20.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(24, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 21.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(25, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 22.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(26, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 23.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(27, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 24.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(58, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 25.0) {
-               vec4 structureColor = texture(textureatlas, getCoordsFromAtlas(0, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, structureColor, Structure);
-          }
-          else if (Structure == 26.0) {
-               vec4 roadAllSidesColor = texture(textureatlas, getCoordsFromAtlas(1, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadAllSidesColor, Structure);
-          }
-          else if (Structure == 27.0) {
-               vec4 roadBottomLeftColor = texture(textureatlas, getCoordsFromAtlas(2, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadBottomLeftColor, Structure);
-          }
-          else if (Structure == 28.0) {
-               vec4 roadBottomColor = texture(textureatlas, getCoordsFromAtlas(3, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadBottomColor, Structure);
-          }
-          else if (Structure == 29.0) {
-               vec4 roadHorizontalColor = texture(textureatlas, getCoordsFromAtlas(4, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadHorizontalColor, Structure);
-          }
-          else if (Structure == 30.0) {
-               vec4 roadLeftColor = texture(textureatlas, getCoordsFromAtlas(5, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadLeftColor, Structure);
-          }
-          else if (Structure == 31.0) {
-               vec4 roadNoConnectionColor = texture(textureatlas, getCoordsFromAtlas(6, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadNoConnectionColor, Structure);
-          }
-          else if (Structure == 32.0) {
-               vec4 roadRightBottomLeftColor = texture(textureatlas, getCoordsFromAtlas(7, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadRightBottomLeftColor, Structure);
-          }
-          else if (Structure == 33.0) {
-               vec4 roadRightBottomColor = texture(textureatlas, getCoordsFromAtlas(8, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadRightBottomColor, Structure);
-          }
-          else if (Structure == 34.0) {
-               vec4 roadRightColor = texture(textureatlas, getCoordsFromAtlas(9, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadRightColor, Structure);
-          }
-          else if (Structure == 35.0) {
-               vec4 roadTopBottomLeftColor = texture(textureatlas, getCoordsFromAtlas(10, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadTopBottomLeftColor, Structure);
-          }
-          else if (Structure == 36.0) {
-                    vec4 roadTopLeftColor = texture(textureatlas, getCoordsFromAtlas(11, 1, TextureDimensions, TextureIndex));
-                    overlayTexture(FragColor, roadTopLeftColor, Structure);
-          }
-          else if (Structure == 37.0) {
-               vec4 roadTopColor = texture(textureatlas, getCoordsFromAtlas(12, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadTopColor, Structure);
-          }
-          else if (Structure == 38.0) {
-               vec4 roadTopRightBottomColor = texture(textureatlas, getCoordsFromAtlas(13, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadTopRightBottomColor, Structure);
-          }
-          else if (Structure == 39.0) {
-               vec4 roadTopRightLeftColor = texture(textureatlas, getCoordsFromAtlas(14, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadTopRightLeftColor, Structure);
-          }
-          else if (Structure == 40.0) {
-               vec4 roadTopRightColor = texture(textureatlas, getCoordsFromAtlas(15, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadTopRightColor, Structure);
-          }
-          else if (Structure == 41.0) {
-               vec4 roadVerticalColor = texture(textureatlas, getCoordsFromAtlas(16, 1, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, roadVerticalColor, Structure);
-          }
+          drawOverlay(STRUCTURE_ROW, int(Structure));
      }
      
      // Apply worker overlay
-     if (Worker == 1.0) {
-          vec4 workerColor = texture(textureatlas, getCoordsFromAtlas(28, 0, TextureDimensions, TextureIndex));
-          overlayTexture(FragColor, workerColor, Worker);
+     if (Worker > 0.5) {
+          drawOverlay(OVERLAY_ROW, OVERLAY_WORKER);
      }
 
-     if (Troops == 1.0) {
-          if (int(ticks / 200) % 3 == 0) {
-               vec4 troopColor = texture(textureatlas, getCoordsFromAtlas(36, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, troopColor, Troops);
-          } else if (int(ticks / 200) % 3 == 1) {
-               vec4 troopColor = texture(textureatlas, getCoordsFromAtlas(37, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, troopColor, Troops);
-          } else {
-               vec4 troopColor = texture(textureatlas, getCoordsFromAtlas(38, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, troopColor, Troops);
-          }
+     if (Troops > 0.5) {
+          drawOverlay(OVERLAY_ROW, OVERLAY_TROOPS);
      }
 
      // Apply highlights
-     if ((SelectedTile > 0.0) || (Highlight > 0.0)) {
-          if ((SelectedTile > 0.0) && (Highlight > 0.0)) {
-               vec4 resourceColor = texture(textureatlas, getCoordsFromAtlas(35, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, resourceColor, 1.0);
-               // highlight_Frag(minDist, selectedColor, borderThickness, FragColor);
-          }
-          else if (SelectedTile > 0.0) {
-               vec4 resourceColor = texture(textureatlas, getCoordsFromAtlas(35, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, resourceColor, 1.0);
-               // highlight_Frag(minDist, selectedColor, borderThickness, FragColor);
-          }
-          else if (Highlight > 0.0) {
-               vec4 resourceColor = texture(textureatlas, getCoordsFromAtlas(30, 0, TextureDimensions, TextureIndex));
-               overlayTexture(FragColor, resourceColor, 1.0);
-               // highlight_Frag(minDist, highlightColor, borderThickness, FragColor);
-          }
+     if (SelectedTile > 0.0) {
+          drawOverlay(OVERLAY_ROW, OVERLAY_SELECTED);
+     }
+     else if (Highlight > 0.0) {
+          drawOverlay(OVERLAY_ROW, OVERLAY_HIGHLIGHT);
      }
 
      // Apply Resource Highlight
      if ((HighlightResources > 0.0) && (Resource > 0.0)) {
-          vec4 resourceColor = texture(textureatlas, getCoordsFromAtlas(34, 0, TextureDimensions, TextureIndex));
-          overlayTexture(FragColor, resourceColor, 1.0);
+          drawOverlay(OVERLAY_ROW, OVERLAY_RESOURCE_HIGHLIGHT);
      }
 
      // Apply Highlight Color
      if (HighlightColor.a > 0.0) {
           highlight_Frag(minDist, HighlightColor, borderThickness, FragColor);
      }
-}
\ No newline at end of file
+
+     // Apply fog of war over everything
+     if (fogEnabled != 0) {
+          FragColor.rgb = applyFog(FragColor.rgb);
+     }
+
+     if (premultiplyAlpha != 0) {
+          FragColor.rgb *= FragColor.a;
+     }
+}
@@ -0,0 +1,75 @@
+#version 330 core
+
+in vec2 LocalPos;
+
+uniform vec4 color;
+uniform int shapeType;    // 0 = circle, 1 = rounded box, 2 = ring, 3 = round line, 4 = flat line
+uniform vec2 shapeSize;   // Half size of the box, or the line end point
+uniform vec4 radii;       // Box corner radii (top left, top right, bottom right, bottom left)
+uniform float radius;     // Circle and ring radius
+uniform float thickness;  // Ring and line thickness
+
+out vec4 fragColor;
+
+// Set when the blend mode needs the color premultiplied by its alpha(see gl2d/blend.rs).
+uniform int premultiplyAlpha;
+
+// Signed distance functions, negative inside the shape and positive outside.
+// Reference: https://iquilezles.org/articles/distfunctions2d/
+
+float sdCircle(vec2 p, float r) {
+    return length(p) - r;
+}
+
+float sdRoundedBox(vec2 p, vec2 halfSize, vec4 r) {
+    // Pick the radius of the quadrant p is in (y points down on screen).
+    float cornerRadius = p.x > 0.0 ? (p.y > 0.0 ? r.z : r.y) : (p.y > 0.0 ? r.w : r.x);
+    vec2 q = abs(p) - halfSize + cornerRadius;
+    return min(max(q.x, q.y), 0.0) + length(max(q, 0.0)) - cornerRadius;
+}
+
+float sdSegment(vec2 p, vec2 a, vec2 b) {
+    vec2 pa = p - a;
+    vec2 ba = b - a;
+    // A zero length segment is a point, 0/0 would make the distance NaN.
+    float lengthSquared = dot(ba, ba);
+    float h = lengthSquared > 0.0 ? clamp(dot(pa, ba) / lengthSquared, 0.0, 1.0) : 0.0;
+    return length(pa - ba * h);
+}
+
+float sdOrientedBox(vec2 p, vec2 a, vec2 b, float th) {
+    float l = length(b - a);
+    vec2 d = (b - a) / l;
+    vec2 q = p - (a + b) * 0.5;
+    q = mat2(d.x, -d.y, d.y, d.x) * q;
+    q = abs(q) - vec2(l, th) * 0.5;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0);
+}
+
+void main() {
+    float dist;
+    if (shapeType == 0) {
+        dist = sdCircle(LocalPos, radius);
+    } else if (shapeType == 1) {
+        dist = sdRoundedBox(LocalPos, shapeSize, radii);
+    } else if (shapeType == 2) {
+        dist = abs(sdCircle(LocalPos, radius)) - thickness * 0.5;
+    } else if (shapeType == 3) {
+        dist = sdSegment(LocalPos, vec2(0.0), shapeSize) - thickness * 0.5;
+    } else {
+        dist = sdOrientedBox(LocalPos, vec2(0.0), shapeSize, thickness);
+    }
+
+    // fwidth gives the change of the distance across one screen pixel, so the
+    // edge is always one pixel wide no matter how the projection scales the shape.
+    float edgeWidth = max(fwidth(dist), 1e-4);
+    float coverage = clamp(0.5 - dist / edgeWidth, 0.0, 1.0);
+
+    if (coverage <= 0.0) {
+        discard;
+    }
+    fragColor = vec4(color.rgb, color.a * coverage);
+    if (premultiplyAlpha != 0) {
+        fragColor.rgb *= fragColor.a;
+    }
+}
@@ -0,0 +1,19 @@
+#version 330 core
+
+in vec2 TexCoords;
+
+uniform vec4 color;
+uniform sampler2D glyphAtlas; // Single channel glyph coverage
+
+out vec4 fragColor;
+
+// Set when the blend mode needs the color premultiplied by its alpha(see gl2d/blend.rs).
+uniform int premultiplyAlpha;
+
+void main() {
+    float coverage = texture(glyphAtlas, TexCoords).r;
+    fragColor = vec4(color.rgb, color.a * coverage);
+    if (premultiplyAlpha != 0) {
+        fragColor.rgb *= fragColor.a;
+    }
+}
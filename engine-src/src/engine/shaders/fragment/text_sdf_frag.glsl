@@ -0,0 +1,43 @@
+#version 330 core
+
+in vec2 TexCoords;
+
+uniform vec4 color;
+uniform sampler2D glyphAtlas; // Single channel signed distance field, 0.5 on the glyph edges
+uniform float distanceRange;  // Distance(in atlas pixels) from the edge to where the field reaches 0.0 or 1.0
+uniform float pixelScale;     // Screen pixels per atlas pixel
+uniform vec4 outlineColor;
+uniform float outlineWidth;   // In screen pixels, like dilate and softness
+uniform float dilate;         // Grows the glyphs outwards, used for shadows and glows
+uniform float softness;       // Width of the fade out of the edges, 0.0 for sharp anti-aliased edges
+
+out vec4 fragColor;
+
+// Set when the blend mode needs the color premultiplied by its alpha(see gl2d/blend.rs).
+uniform int premultiplyAlpha;
+
+// Coverage of a pixel at the given distance from an edge (positive inside).
+float coverage(float distance) {
+    return clamp(distance / (1.0 + softness) + 0.5, 0.0, 1.0);
+}
+
+void main() {
+    float value = texture(glyphAtlas, TexCoords).r;
+    float distance = (value - 0.5) * 2.0 * distanceRange * pixelScale + dilate;
+
+    float fill = coverage(distance);
+    float outline = outlineWidth > 0.0 ? coverage(distance + outlineWidth) : 0.0;
+
+    // The fill is drawn over the outline.
+    float fillAlpha = color.a * fill;
+    float outlineAlpha = outlineColor.a * outline * (1.0 - fillAlpha);
+    float alpha = fillAlpha + outlineAlpha;
+    if (alpha <= 0.0) {
+        discard;
+    }
+    vec3 rgb = (color.rgb * fillAlpha + outlineColor.rgb * outlineAlpha) / alpha;
+    fragColor = vec4(rgb, alpha);
+    if (premultiplyAlpha != 0) {
+        fragColor.rgb *= fragColor.a;
+    }
+}
@@ -7,6 +7,12 @@ uniform sampler2D image;
 
 out vec4 fragColor;
 
+// Set when the blend mode needs the color premultiplied by its alpha(see gl2d/blend.rs).
+uniform int premultiplyAlpha;
+
 void main() {
     fragColor = texture(image, TexCoords) * color;
+    if (premultiplyAlpha != 0) {
+        fragColor.rgb *= fragColor.a;
+    }
 }
@@ -3,10 +3,12 @@ layout (location = 0) in vec2 position;
 layout (location = 1) in vec2 texCoords;
 
 out vec2 TexCoords;
+out vec2 FragPos; // Screen position, used by the gradient fills
 
 uniform mat4 projection;
 
 void main() {
     gl_Position = projection * vec4(position, 0.0, 1.0);
     TexCoords = texCoords;
+    FragPos = position;
 }
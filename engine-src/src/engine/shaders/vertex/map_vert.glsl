@@ -3,7 +3,7 @@
 // Input vertex attributes
 layout (location = 0) in vec2 aPos;           // Vertex position
 layout (location = 1) in vec2 aTexCoord;       // Texture coordinates
-layout (location = 2) in vec2 aOffset;         // Instance offset
+layout (location = 2) in vec4 aOffset;         // Instance offset(xy), column and row of the tile(zw)
 layout (location = 3) in float aTexIndex;      // Texture index
 layout (location = 4) in float aHighlight;     // Highlight flag
 layout (location = 5) in float aSelectedTile;  // Selected tile flag
@@ -36,6 +36,7 @@ out float TextureIndex;
 out vec2 FragPos;
 out vec3 Normal;
 out float IsVisible;  // Visibility flag
+out vec2 FogCoord;    // Position in the grid, in tiles
 
 // Uniform variables
 uniform mat4 projection;       // Projection matrix
@@ -44,6 +45,7 @@ uniform vec2 cameraOffset;     // Camera's current position
 uniform float panningRotation; // Rotation angle for 3D-like effect
 uniform float gameTime;        // Game time (optional)
 uniform float textureSize;     //Defines size of the tile
+uniform int tileShape;         // Shape of the tiles, see the TILE_SHAPE constants of map_frag.glsl
 
 // Rotation matrix creation function
 mat2 rotationMatrix(float angle) {
@@ -56,7 +58,7 @@ void main()
 {
     // Calculate world position with 3D-like rotation
     vec2 screenCenter = screenDimensions * 0.5;
-    vec2 offsetFromCenter = aOffset - screenCenter;
+    vec2 offsetFromCenter = aOffset.xy - screenCenter;
     
     // Apply rotation matrix
     mat2 rotMat = rotationMatrix(panningRotation);
@@ -101,6 +103,14 @@ void main()
     TextureDimensions = aTextureDimensions;
     TextureIndex = aTextureIndex;
 
+    // Position in the grid, linear over the quad so it can be interpolated. The corners of a
+    // diamond are half a tile away along the columns or the rows.
+    vec2 local = aTexCoord - 0.5;
+    if (tileShape == 1) {
+        local = vec2(local.x + local.y, local.y - local.x);
+    }
+    FogCoord = aOffset.zw + local;
+
     FragPos = aPos;
     Normal = vec3(0.0, 0.0, 1.0);
 }
\ No newline at end of file
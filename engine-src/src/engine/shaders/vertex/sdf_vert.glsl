@@ -0,0 +1,12 @@
+#version 330 core
+layout (location = 0) in vec2 position;
+layout (location = 1) in vec2 localPos; // Position relative to the shape's origin
+
+out vec2 LocalPos;
+
+uniform mat4 projection;
+
+void main() {
+    gl_Position = projection * vec4(position, 0.0, 1.0);
+    LocalPos = localPos;
+}
@@ -9,8 +9,10 @@ extern crate sdl3; // SDL3 bindings
 
 // Import necessary types and traits
 use crate::engine::{
+    gl2d::gradient::Gradient,
+    gl2d::sdf::tengine_draw_smooth_ring,
     gl2d::shapes::{
-        tengine_draw_circle, tengine_draw_quarter_circle, tengine_draw_rect,
+        Corner, tengine_draw_circle, tengine_draw_quarter_circle, tengine_draw_rect,
         tengine_draw_rounded_rect,
     },
     helpers::get_tctx,
@@ -176,27 +178,34 @@ pub fn draw_color_changing_shapes() {
         let (width, height) = window.size();
 
         // draw a rounded rectangle with the current color (somewhere)
-        tengine_draw_rounded_rect(100.0, 100.0, 400.0, 200.0, [r, g, b, 1.0], 100.0);
+        tengine_draw_rounded_rect(100.0, 100.0, 400.0, 200.0, [r, g, b, 1.0].into(), 100.0.into());
 
         // draw a quarter circle with the current color (somewhere)
-        tengine_draw_quarter_circle(100.0, 500.0, 20.0, [r, g, b, 1.0], 100, "top-left");
-
-        // Draw the rectangle with the current color at right bottom
-        // corner of the screen.
+        tengine_draw_quarter_circle(100.0, 500.0, 20.0, [r, g, b, 1.0].into(), 100, Corner::TopLeft)
+            .expect("Failed to draw the quarter circle");
+
+        // Draw the rectangle at right bottom corner of the screen, with a gradient
+        // from the current color to its complementary color.
+        let [cr, cg, cb] = hsv_to_rgb((hue + 180.0) % 360.0, 1.0, 1.0);
+        let gradient = Gradient::linear(135.0, vec![(0.0, [r, g, b, 1.0]), (1.0, [cr, cg, cb, 1.0])])
+            .expect("Failed to create the rectangle gradient");
         tengine_draw_rect(
             width as f32 - 500.0,
             height as f32 - 500.0,
             500.0,
             500.0,
-            [r, g, b, 1.0],
+            gradient.into(),
         );
 
         // Draw a circle at 500.0, 500.0 of radius 50.0 with
         // 800 approximated segments.
-        tengine_draw_circle(400.0, 400.0, 50.0, 800, [r, g, b, 1.0]);
+        tengine_draw_circle(400.0, 400.0, 50.0, 800, [r, g, b, 1.0].into());
 
         // Draw a hexagon at 500.0, 500.0 of radius 50.0
-        tengine_draw_circle(500.0, 500.0, 50.0, 6, [r, g, b, 1.0]);
+        tengine_draw_circle(500.0, 500.0, 50.0, 6, [r, g, b, 1.0].into());
+
+        // Draw an anti-aliased ring around the hexagon.
+        tengine_draw_smooth_ring(500.0, 500.0, 70.0, 6.0, [r, g, b, 1.0]);
 
         // Swap the back buffer with the front buffer
         window.gl_swap_window();
@@ -0,0 +1,289 @@
+//! # Animations
+//!
+//! Sequences of atlas cells, each shown for its own duration, which the ground textures,
+//! structures and overlays of a map can be drawn with(see the overlays module).
+//!
+//! The animations of a map are kept in a float texture the map fragment shader reads with
+//! texelFetch, a row per animation. The first texel of a row is the header(frame count,
+//! total duration, loop mode, start) and the next ones are the frames(atlas cell x, atlas
+//! cell y, time the frame ends at). The shader picks the current frame from the map's
+//! `ticks`(in milliseconds), which the map advances itself every draw.
+
+extern crate glow; // OpenGL bindings
+
+use glow::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Number of animations of a map.
+pub const MAX_ANIMATIONS: usize = 256;
+/// Number of frames of an animation, a row of the table being its header and its frames.
+pub const MAX_FRAMES: usize = 31;
+
+/// What an animation does once its last frame is shown.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoopMode {
+    /// Starts again from the first frame.
+    #[default]
+    Loop = 0,
+    /// Stays on the last frame.
+    Once = 1,
+    /// Goes back to the first frame in reverse, then forward again.
+    PingPong = 2,
+}
+
+/// A sequence of atlas cells(x, y), each shown for its duration in ticks(milliseconds).
+///
+/// _Example(python)_:
+/// ```python
+/// water = tge.tilemap.Animation([((0, 4), 300.0), ((1, 4), 200.0), ((2, 4), 300.0)])
+/// flag = tge.tilemap.Animation.strip((20, 1), 4, 150.0, tge.tilemap.LoopMode.PingPong)
+///
+/// water_id = world.add_animation(water)
+/// world.set_texture_animation(3, water_id)
+/// world.set_structure_animation(42, world.add_animation(flag))
+/// ```
+#[pyclass(get_all)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animation {
+    frames: Vec<([u32; 2], f32)>,
+    loop_mode: LoopMode,
+}
+
+#[pymethods]
+impl Animation {
+    #[new]
+    #[pyo3(signature = (frames, loop_mode=LoopMode::Loop))]
+    pub fn new(frames: Vec<([u32; 2], f32)>, loop_mode: LoopMode) -> PyResult<Self> {
+        if frames.is_empty() || frames.len() > MAX_FRAMES {
+            return Err(PyValueError::new_err(format!(
+                "Animations have from 1 to {} frames, got {}",
+                MAX_FRAMES,
+                frames.len()
+            )));
+        }
+        if let Some((_, duration)) = frames
+            .iter()
+            .find(|(_, duration)| duration.is_nan() || *duration <= 0.0)
+        {
+            return Err(PyValueError::new_err(format!(
+                "Frame durations must be positive, got {}",
+                duration
+            )));
+        }
+        Ok(Animation { frames, loop_mode })
+    }
+
+    /// Creates an animation of `count` frames in the atlas cells going right from `cell`,
+    /// all shown for `frame_duration` ticks.
+    #[staticmethod]
+    #[pyo3(signature = (cell, count, frame_duration, loop_mode=LoopMode::Loop))]
+    pub fn strip(
+        cell: [u32; 2],
+        count: u32,
+        frame_duration: f32,
+        loop_mode: LoopMode,
+    ) -> PyResult<Self> {
+        let frames = (0..count)
+            .map(|frame| ([cell[0] + frame, cell[1]], frame_duration))
+            .collect();
+        Animation::new(frames, loop_mode)
+    }
+
+    /// Returns the time all the frames are shown for, once.
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|(_, duration)| duration).sum()
+    }
+
+    /// Returns the index of the frame shown `ticks` after the animation started, like the map
+    /// fragment shader picks it.
+    pub fn frame_at(&self, ticks: f32) -> usize {
+        let duration = self.duration();
+        let ticks = ticks.max(0.0);
+        let time = match self.loop_mode {
+            LoopMode::Loop => ticks % duration,
+            LoopMode::Once => ticks.min(duration),
+            LoopMode::PingPong => {
+                let time = ticks % (duration * 2.0);
+                if time > duration {
+                    duration * 2.0 - time
+                } else {
+                    time
+                }
+            }
+        };
+
+        let mut end = 0.0;
+        for (index, (_, frame_duration)) in self.frames.iter().enumerate() {
+            end += frame_duration;
+            if time < end {
+                return index;
+            }
+        }
+        self.frames.len() - 1
+    }
+
+    /// Returns the atlas cell shown `ticks` after the animation started.
+    pub fn cell_at(&self, ticks: f32) -> [u32; 2] {
+        self.frames[self.frame_at(ticks)].0
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+struct Slot {
+    animation: Animation,
+    /// Ticks the animation started at.
+    start: f32,
+}
+
+pub struct AnimationTable {
+    texture: NativeTexture,
+    slots: Vec<Option<Slot>>,
+    /// Whether the animations changed since the texture was last uploaded.
+    dirty: bool,
+}
+
+impl AnimationTable {
+    pub fn new(gl: &Context) -> Result<Self, String> {
+        let texture = unsafe {
+            let texture = gl
+                .create_texture()
+                .map_err(|e| format!("Failed to create animation table texture: {}", e))?;
+            gl.bind_texture(TEXTURE_2D, Some(texture));
+            // Frames are read with texelFetch, they must never be filtered.
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MIN_FILTER, NEAREST as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MAG_FILTER, NEAREST as i32);
+            gl.tex_image_2d(
+                TEXTURE_2D,
+                0,
+                RGBA32F as i32,
+                (MAX_FRAMES + 1) as i32,
+                MAX_ANIMATIONS as i32,
+                0,
+                RGBA,
+                FLOAT,
+                PixelUnpackData::Slice(None),
+            );
+            gl.bind_texture(TEXTURE_2D, None);
+            texture
+        };
+
+        Ok(AnimationTable {
+            texture,
+            slots: (0..MAX_ANIMATIONS).map(|_| None).collect(),
+            dirty: true,
+        })
+    }
+
+    pub fn texture(&self) -> NativeTexture {
+        self.texture
+    }
+
+    /// Adds an animation started at `start`, returns its id or None if the table is full.
+    pub fn add(&mut self, animation: Animation, start: f32) -> Option<u32> {
+        let id = self.slots.iter().position(Option::is_none)?;
+        self.slots[id] = Some(Slot { animation, start });
+        self.dirty = true;
+        Some(id as u32)
+    }
+
+    /// Replaces an animation, returns false if there is none with the given id.
+    pub fn set(&mut self, id: u32, animation: Animation) -> bool {
+        match self.slot_mut(id) {
+            Some(slot) => {
+                slot.animation = animation;
+                self.dirty = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Animation> {
+        let slot = self.slots.get(id as usize)?.as_ref()?;
+        Some(&slot.animation)
+    }
+
+    /// Removes an animation, the entries still using it are not drawn.
+    pub fn remove(&mut self, id: u32) -> bool {
+        let removed = self
+            .slots
+            .get_mut(id as usize)
+            .and_then(Option::take)
+            .is_some();
+        self.dirty |= removed;
+        removed
+    }
+
+    /// Starts an animation again from its first frame at `start`.
+    pub fn restart(&mut self, id: u32, start: f32) -> bool {
+        match self.slot_mut(id) {
+            Some(slot) => {
+                slot.start = start;
+                self.dirty = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Uploads the table if it changed.
+    pub fn upload(&mut self, gl: &Context) {
+        if !self.dirty {
+            return;
+        }
+        let row_length = (MAX_FRAMES + 1) * 4;
+        let mut texels: Vec<f32> = vec![0.0; row_length * MAX_ANIMATIONS];
+        for (row, slot) in texels.chunks_exact_mut(row_length).zip(self.slots.iter()) {
+            // Rows of removed animations have no frames.
+            let Some(Slot { animation, start }) = slot else {
+                continue;
+            };
+            let header = [
+                animation.frames.len() as f32,
+                animation.duration(),
+                animation.loop_mode as i32 as f32,
+                *start,
+            ];
+            row[..4].copy_from_slice(&header);
+            let mut end = 0.0;
+            for (texel, (cell, duration)) in
+                row[4..].chunks_exact_mut(4).zip(animation.frames.iter())
+            {
+                end += duration;
+                texel.copy_from_slice(&[cell[0] as f32, cell[1] as f32, end, 0.0]);
+            }
+        }
+
+        unsafe {
+            gl.bind_texture(TEXTURE_2D, Some(self.texture));
+            gl.tex_sub_image_2d(
+                TEXTURE_2D,
+                0,
+                0,
+                0,
+                (MAX_FRAMES + 1) as i32,
+                MAX_ANIMATIONS as i32,
+                RGBA,
+                FLOAT,
+                PixelUnpackData::Slice(Some(bytemuck::cast_slice(&texels))),
+            );
+            gl.bind_texture(TEXTURE_2D, None);
+        }
+        self.dirty = false;
+    }
+
+    pub fn delete(&self, gl: &Context) {
+        unsafe {
+            gl.delete_texture(self.texture);
+        }
+    }
+
+    fn slot_mut(&mut self, id: u32) -> Option<&mut Slot> {
+        self.slots.get_mut(id as usize)?.as_mut()
+    }
+}
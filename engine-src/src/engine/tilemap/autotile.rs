@@ -0,0 +1,213 @@
+//! # Autotiling
+//!
+//! Picks the structure of connecting tiles(roads, walls, rivers, ...) from which of their
+//! neighbours they connect to, so that the game only places or removes a road and every
+//! road piece around it follows.
+//!
+//! The neighbours a tile connects to are a mask of bits, one per direction. With 4
+//! neighbours only the sides count, which gives the 16 variants of a Wang tile set. With 8
+//! neighbours the corners count too, but only when both sides next to them connect(like
+//! the road going north and east, and to the north east tile), which gives the 47 variants
+//! of a blob tile set. A rule set tells which structure every mask is drawn with.
+//!
+//! Neighbours are the tiles around a tile in the columns and rows of the map, north being
+//! the row above whatever the layout.
+//! Reference: http://www.cr31.co.uk/stagecast/wang/blob.html
+
+use std::collections::{HashMap, HashSet};
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+pub const NORTH: u8 = 1;
+pub const EAST: u8 = 2;
+pub const SOUTH: u8 = 4;
+pub const WEST: u8 = 8;
+pub const NORTH_EAST: u8 = 16;
+pub const SOUTH_EAST: u8 = 32;
+pub const SOUTH_WEST: u8 = 64;
+pub const NORTH_WEST: u8 = 128;
+
+/// The offset(column, row) of every neighbour with its bit in the mask.
+pub const NEIGHBOURS: [(i64, i64, u8); 8] = [
+    (0, -1, NORTH),
+    (1, 0, EAST),
+    (0, 1, SOUTH),
+    (-1, 0, WEST),
+    (1, -1, NORTH_EAST),
+    (1, 1, SOUTH_EAST),
+    (-1, 1, SOUTH_WEST),
+    (-1, -1, NORTH_WEST),
+];
+
+/// The corners with the two sides which must connect for the corner to count.
+const CORNERS: [(u8, u8); 4] = [
+    (NORTH_EAST, NORTH | EAST),
+    (SOUTH_EAST, SOUTH | EAST),
+    (SOUTH_WEST, SOUTH | WEST),
+    (NORTH_WEST, NORTH | WEST),
+];
+
+/// The road structures of the TerraTactica atlas(26 to 41) by the sides they connect to.
+const ROADS: [(u8, u32); 16] = [
+    (NORTH | EAST | SOUTH | WEST, 26),
+    (SOUTH | WEST, 27),
+    (SOUTH, 28),
+    (EAST | WEST, 29),
+    (WEST, 30),
+    (0, 31),
+    (EAST | SOUTH | WEST, 32),
+    (EAST | SOUTH, 33),
+    (EAST, 34),
+    (NORTH | SOUTH | WEST, 35),
+    (NORTH | WEST, 36),
+    (NORTH, 37),
+    (NORTH | EAST | SOUTH, 38),
+    (NORTH | EAST | WEST, 39),
+    (NORTH | EAST, 40),
+    (NORTH | SOUTH, 41),
+];
+
+/// Which neighbours of a tile count for its variant.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Connectivity {
+    /// The 4 sides(north, east, south and west), 16 variants.
+    #[default]
+    Four,
+    /// The sides and the corners between two connected sides, 47 variants.
+    Eight,
+}
+
+/// The structures a set of connecting tiles is drawn with, by the mask of the neighbours
+/// they connect to.
+///
+/// Mask bits are NORTH(1), EAST(2), SOUTH(4), WEST(8), NORTH_EAST(16), SOUTH_EAST(32),
+/// SOUTH_WEST(64) and NORTH_WEST(128). Tiles connect to the tiles of the set and to the
+/// structures added with `connect_to`(like roads going into buildings).
+///
+/// _Example(python)_:
+/// ```python
+/// roads = tge.tilemap.AutotileRules.roads()
+/// roads.connect_to(3)  # the town hall
+///
+/// world.place_autotile(12, 7, roads)
+/// world.remove_autotile(12, 8, roads)
+///
+/// walls = tge.tilemap.AutotileRules(tge.tilemap.Connectivity.Eight)
+/// for mask, structure in wall_variants.items():
+///     walls.set_variant(mask, structure)
+/// ```
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct AutotileRules {
+    #[pyo3(get)]
+    connectivity: Connectivity,
+    variants: HashMap<u8, u32>,
+    connects_to: HashSet<u32>,
+}
+
+#[pymethods]
+impl AutotileRules {
+    #[new]
+    #[pyo3(signature = (connectivity=Connectivity::Four))]
+    pub fn new(connectivity: Connectivity) -> Self {
+        AutotileRules {
+            connectivity,
+            variants: HashMap::new(),
+            connects_to: HashSet::new(),
+        }
+    }
+
+    /// Returns the rules of the roads of the TerraTactica atlas(structures 26 to 41).
+    #[staticmethod]
+    pub fn roads() -> Self {
+        let mut rules = AutotileRules::new(Connectivity::Four);
+        rules.variants.extend(ROADS);
+        rules
+    }
+
+    /// Sets the structure of the tiles whose neighbours are the given mask.
+    pub fn set_variant(&mut self, mask: u8, structure: u32) -> PyResult<()> {
+        if structure == 0 {
+            return Err(PyValueError::new_err("Variants must be structures, not 0"));
+        }
+        if self.normalize(mask) != mask {
+            return Err(PyValueError::new_err(format!(
+                "Mask {} can not happen with {:?} connectivity, corners count only between two connected sides",
+                mask, self.connectivity
+            )));
+        }
+        self.variants.insert(mask, structure);
+        Ok(())
+    }
+
+    /// Returns the structure set for the given mask.
+    pub fn get_variant(&self, mask: u8) -> Option<u32> {
+        self.variants.get(&mask).copied()
+    }
+
+    /// Makes the tiles of the set connect to a structure which is not in it.
+    pub fn connect_to(&mut self, structure: u32) {
+        self.connects_to.insert(structure);
+    }
+
+    /// Returns whether a structure is one of the variants of the set.
+    pub fn is_variant(&self, structure: u32) -> bool {
+        structure != 0 && self.variants.values().any(|variant| *variant == structure)
+    }
+
+    /// Returns whether the tiles of the set connect to a structure.
+    pub fn connects(&self, structure: u32) -> bool {
+        self.is_variant(structure) || self.connects_to.contains(&structure)
+    }
+
+    /// Returns the structure a tile with the given neighbours is drawn with: the variant of
+    /// the mask, or of its sides only if it has none, or of no neighbours at all.
+    pub fn resolve(&self, mask: u8) -> Option<u32> {
+        let mask = self.normalize(mask);
+        self.get_variant(mask)
+            .or_else(|| self.get_variant(mask & (NORTH | EAST | SOUTH | WEST)))
+            .or_else(|| self.get_variant(0))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "AutotileRules({:?}, {} variants)",
+            self.connectivity,
+            self.variants.len()
+        )
+    }
+}
+
+impl AutotileRules {
+    /// Returns the mask of the neighbours of the tile at (x, y) which connect to it,
+    /// `structure_at` returning the structure of a tile or None outside of the map.
+    pub fn mask(&self, x: i64, y: i64, structure_at: impl Fn(i64, i64) -> Option<u32>) -> u8 {
+        let mask = self
+            .neighbours()
+            .iter()
+            .filter(|(dx, dy, _)| structure_at(x + dx, y + dy).is_some_and(|s| self.connects(s)))
+            .fold(0, |mask, (_, _, bit)| mask | bit);
+        self.normalize(mask)
+    }
+
+    /// Returns the offsets(column, row) and bits of the neighbours which count.
+    pub fn neighbours(&self) -> &'static [(i64, i64, u8)] {
+        match self.connectivity {
+            Connectivity::Four => &NEIGHBOURS[..4],
+            Connectivity::Eight => &NEIGHBOURS,
+        }
+    }
+
+    /// Drops the bits the connectivity does not count.
+    fn normalize(&self, mask: u8) -> u8 {
+        match self.connectivity {
+            Connectivity::Four => mask & (NORTH | EAST | SOUTH | WEST),
+            Connectivity::Eight => CORNERS
+                .iter()
+                .filter(|(_, sides)| mask & sides != *sides)
+                .fold(mask, |mask, (corner, _)| mask & !corner),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! # Chunks
+//!
+//! Big maps are split into chunks of chunk_size x chunk_size tiles(smaller at the right and
+//! bottom borders), every chunk with its own instance buffer and vertex array. The tiles of a
+//! chunk are in the draw order of the map's layout, and so are the chunks.
+//!
+//! Before drawing, the map tests every chunk against the camera's view(widened by a margin)
+//! on the CPU and only draws the chunks overlapping it. A chunk's changed tiles are kept
+//! until the chunk is drawn again, so changing tiles outside of the view uploads nothing.
+
+extern crate glow; // OpenGL bindings
+
+use glow::*;
+
+use crate::engine::tilemap::dirty::DirtyRanges;
+use crate::engine::tilemap::layout::Geometry;
+use crate::engine::tilemap::map::{INSTANCE_ATTRIBUTES, Tile, TileInstance};
+
+/// Changed tiles less than this many tiles apart are uploaded with one `buffer_sub_data` call.
+const UPLOAD_GAP: usize = 64;
+
+pub struct Chunk {
+    /// Column and row of the chunk's top left tile in the map.
+    pub origin: (u32, u32),
+    /// Size of the chunk in tiles.
+    pub width: u32,
+    pub height: u32,
+    /// The chunk's tiles, in draw order.
+    pub instances: Vec<TileInstance>,
+    /// Index in the instances of every tile of the chunk, row after row.
+    slots: Vec<usize>,
+    /// Area(left, top, right, bottom) of the map the offsets of the tiles are in.
+    offset_bounds: [f32; 4],
+    /// The instances which changed since they were last uploaded.
+    dirty: DirtyRanges,
+    vao: NativeVertexArray,
+    instance_vbo: NativeBuffer,
+}
+
+impl Chunk {
+    /// Creates a chunk of default tiles, drawn with the tile quad in `quad_vbo`.
+    pub fn new(
+        gl: &Context,
+        quad_vbo: NativeBuffer,
+        origin: (u32, u32),
+        width: u32,
+        height: u32,
+        geometry: &Geometry,
+    ) -> Result<Self, String> {
+        let mut tiles: Vec<(u32, u32)> = (origin.1..origin.1 + height)
+            .flat_map(|row| (origin.0..origin.0 + width).map(move |column| (column, row)))
+            .collect();
+        tiles.sort_by(|a, b| {
+            let (a, b) = (geometry.draw_key(a.0, a.1), geometry.draw_key(b.0, b.1));
+            a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+        });
+
+        let mut slots = vec![0; tiles.len()];
+        let mut offset_bounds = [f32::MAX, f32::MAX, f32::MIN, f32::MIN];
+        let mut instances = Vec::with_capacity(tiles.len());
+        for (index, (column, row)) in tiles.into_iter().enumerate() {
+            slots[((row - origin.1) * width + (column - origin.0)) as usize] = index;
+            let offset = geometry.tile_offset(column, row);
+            offset_bounds = [
+                offset_bounds[0].min(offset[0]),
+                offset_bounds[1].min(offset[1]),
+                offset_bounds[2].max(offset[0]),
+                offset_bounds[3].max(offset[1]),
+            ];
+            instances.push(Tile::default().to_instance(offset, [column as f32, row as f32]));
+        }
+
+        unsafe {
+            let vao = gl.create_vertex_array()?;
+            let instance_vbo = gl.create_buffer()?;
+
+            gl.bind_vertex_array(Some(vao));
+
+            gl.bind_buffer(ARRAY_BUFFER, Some(quad_vbo));
+            let stride = 4 * std::mem::size_of::<f32>() as i32;
+            gl.vertex_attrib_pointer_f32(0, 2, FLOAT, false, stride, 0);
+            gl.enable_vertex_attrib_array(0);
+            gl.vertex_attrib_pointer_f32(1, 2, FLOAT, false, stride, 2 * std::mem::size_of::<f32>() as i32);
+            gl.enable_vertex_attrib_array(1);
+
+            gl.bind_buffer(ARRAY_BUFFER, Some(instance_vbo));
+            gl.buffer_data_u8_slice(ARRAY_BUFFER, bytemuck::cast_slice(&instances), DYNAMIC_DRAW);
+            let stride = std::mem::size_of::<TileInstance>() as i32;
+            let mut offset = 0;
+            for (index, components) in INSTANCE_ATTRIBUTES.into_iter().enumerate() {
+                let location = 2 + index as u32;
+                gl.vertex_attrib_pointer_f32(location, components, FLOAT, false, stride, offset);
+                gl.enable_vertex_attrib_array(location);
+                // Advance the attribute once per tile instead of once per vertex.
+                gl.vertex_attrib_divisor(location, 1);
+                offset += components * std::mem::size_of::<f32>() as i32;
+            }
+
+            gl.bind_vertex_array(None);
+            gl.bind_buffer(ARRAY_BUFFER, None);
+
+            Ok(Chunk {
+                origin,
+                width,
+                height,
+                instances,
+                slots,
+                offset_bounds,
+                dirty: DirtyRanges::default(),
+                vao,
+                instance_vbo,
+            })
+        }
+    }
+
+    /// Returns the index in the instances of the tile at the given column and row of the map.
+    pub fn local_index(&self, x: u32, y: u32) -> usize {
+        self.slots[((y - self.origin.1) * self.width + (x - self.origin.0)) as usize]
+    }
+
+    /// Marks a single tile(by its index in the instances) as changed.
+    pub fn mark(&mut self, index: usize) {
+        self.dirty.mark(index);
+    }
+
+    /// Marks all the tiles as changed.
+    pub fn mark_all(&mut self) {
+        self.dirty.mark_range(0..self.instances.len());
+    }
+
+    /// Returns the area(left, top, right, bottom) of the map the offsets of the chunk's
+    /// tiles are in(in pixels).
+    pub fn offset_bounds(&self) -> [f32; 4] {
+        self.offset_bounds
+    }
+
+    /// Uploads the tiles which changed since the last upload.
+    pub fn upload(&mut self, gl: &Context) {
+        if self.dirty.is_empty() {
+            return;
+        }
+        unsafe {
+            gl.bind_buffer(ARRAY_BUFFER, Some(self.instance_vbo));
+            let instance_size = std::mem::size_of::<TileInstance>();
+            for range in self.dirty.take(UPLOAD_GAP) {
+                let offset = (range.start * instance_size) as i32;
+                gl.buffer_sub_data_u8_slice(ARRAY_BUFFER, offset, bytemuck::cast_slice(&self.instances[range]));
+            }
+            gl.bind_buffer(ARRAY_BUFFER, None);
+        }
+    }
+
+    /// Draws the chunk's tiles with the bound shader program.
+    pub fn draw(&self, gl: &Context) {
+        unsafe {
+            gl.bind_vertex_array(Some(self.vao));
+            gl.draw_arrays_instanced(TRIANGLES, 0, 6, self.instances.len() as i32);
+        }
+    }
+
+    pub fn delete(&self, gl: &Context) {
+        unsafe {
+            gl.delete_vertex_array(self.vao);
+            gl.delete_buffer(self.instance_vbo);
+        }
+    }
+}
+
+/// Returns whether tiles whose offsets are in an area(left, top, right, bottom) of the map
+/// overlap the view once rotated like the map vertex shader rotates tiles: their offsets turn
+/// around `center` by `rotation` degrees, and the tile quads(of `quad_size`) are added unrotated.
+pub fn is_visible(bounds: [f32; 4], view: [f32; 4], rotation: f32, center: (f32, f32), quad_size: (f32, f32)) -> bool {
+    let [left, top, right, bottom] = if rotation == 0.0 {
+        [bounds[0], bounds[1], bounds[2] + quad_size.0, bounds[3] + quad_size.1]
+    } else {
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let corners = [
+            (bounds[0], bounds[1]),
+            (bounds[2], bounds[1]),
+            (bounds[0], bounds[3]),
+            (bounds[2], bounds[3]),
+        ];
+        let mut rotated = [f32::MAX, f32::MAX, f32::MIN, f32::MIN];
+        for (x, y) in corners {
+            let (dx, dy) = (x - center.0, y - center.1);
+            let x = cos * dx + sin * dy + center.0;
+            let y = -sin * dx + cos * dy + center.1;
+            rotated = [rotated[0].min(x), rotated[1].min(y), rotated[2].max(x), rotated[3].max(y)];
+        }
+        // A tile's quad goes to the right and down from its rotated offset.
+        [rotated[0], rotated[1], rotated[2] + quad_size.0, rotated[3] + quad_size.1]
+    };
+    left < view[2] && right > view[0] && top < view[3] && bottom > view[1]
+}
@@ -0,0 +1,376 @@
+//! # Day Night Cycle
+//!
+//! The day and night lighting of the map shader. A cycle lasts `day_duration` ticks of game
+//! time, the first part of it being the day and the last `night_portion` of it the night.
+//!
+//! The sun direction, light intensity and light colors are computed once per draw on the
+//! CPU and handed to map_frag.glsl as uniforms, so every parameter of the cycle can be
+//! changed from python. The defaults light the map like the map shader always did.
+
+extern crate glow; // OpenGL bindings
+
+use std::f32::consts::PI;
+
+use glow::*;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// The lighting of a moment of the cycle, as map_frag.glsl uses it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lighting {
+    /// Direction(normalized) the sun light comes from.
+    pub sun_direction: [f32; 3],
+    /// Color of the ambient light, already scaled by the ambient strength.
+    pub ambient: [f32; 3],
+    pub sun_color: [f32; 3],
+    /// Multiplier of the whole light.
+    pub intensity: f32,
+}
+
+impl Lighting {
+    /// Sets the lighting uniforms of the map shader program.
+    pub fn set_uniforms(&self, gl: &Context, program: NativeProgram) {
+        unsafe {
+            let vectors = [
+                ("sunDirection", self.sun_direction),
+                ("ambientLight", self.ambient),
+                ("sunColor", self.sun_color),
+            ];
+            for (name, [x, y, z]) in vectors {
+                let location = gl.get_uniform_location(program, name);
+                gl.uniform_3_f32(location.as_ref(), x, y, z);
+            }
+            let intensity_location = gl.get_uniform_location(program, "lightIntensity");
+            gl.uniform_1_f32(intensity_location.as_ref(), self.intensity);
+        }
+    }
+}
+
+/// A cycle of days and nights following the game time.
+///
+/// Light colors change over the cycle with gradients of color stops (phase, [r, g, b]),
+/// the phase going from 0.0 at the start of the day to 1.0 at the end of the night. The
+/// gradients wrap around, so the colors between the last stop and the first one of the
+/// next cycle are blended too.
+///
+/// _Example(python)_:
+/// ```python
+/// cycle = tge.tilemap.DayNightCycle(day_duration=6000.0, night_portion=0.3)
+/// cycle.set_sun_colors([(0.0, [1.0, 0.8, 0.6]), (0.35, [1.0, 1.0, 1.0]), (0.7, [1.0, 0.6, 0.4])])
+/// cycle.set_ambient_colors([(0.0, [1.0, 1.0, 1.0]), (0.85, [0.4, 0.5, 1.0])])
+/// world.day_night = cycle
+///
+/// cycle.advance(delta_ticks)
+/// if not cycle.is_day:
+///     light_torches()
+/// ```
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DayNightCycle {
+    /// Game time(in ticks) since the start of the first day.
+    #[pyo3(get, set)]
+    pub time: f32,
+    /// Duration(in ticks) of a whole day and night.
+    day_duration: f32,
+    /// Part of the cycle which is night, from 0.0 to 1.0.
+    night_portion: f32,
+    /// How far(from 0.0 to 1.0) along its arc the sun is when the day starts, the day
+    /// starting mid morning by default.
+    #[pyo3(get, set)]
+    pub morning_offset: f32,
+    /// How much brighter than the rest of the day noon is.
+    #[pyo3(get, set)]
+    pub noon_boost: f32,
+    /// How sharply the light peaks around noon, higher values making a shorter peak.
+    #[pyo3(get, set)]
+    pub noon_focus: f32,
+    /// Light intensity at night, and the least light of the day.
+    #[pyo3(get, set)]
+    pub min_intensity: f32,
+    /// Strength of the ambient light, lit sides or not.
+    #[pyo3(get, set)]
+    pub ambient_strength: f32,
+    ambient_colors: Vec<(f32, [f32; 3])>,
+    sun_colors: Vec<(f32, [f32; 3])>,
+}
+
+impl Default for DayNightCycle {
+    fn default() -> Self {
+        DayNightCycle {
+            time: 0.0,
+            day_duration: 4500.0,
+            night_portion: 0.2,
+            morning_offset: 1.0 / 3.0,
+            noon_boost: 1.25,
+            noon_focus: 3.0,
+            min_intensity: 0.3,
+            ambient_strength: 0.2,
+            ambient_colors: vec![(0.0, [1.0; 3])],
+            sun_colors: vec![(0.0, [1.0; 3])],
+        }
+    }
+}
+
+#[pymethods]
+impl DayNightCycle {
+    #[new]
+    #[pyo3(signature = (
+        day_duration=4500.0,
+        night_portion=0.2,
+        morning_offset=1.0 / 3.0,
+        noon_boost=1.25,
+        noon_focus=3.0,
+        min_intensity=0.3,
+        ambient_strength=0.2,
+        time=0.0
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        day_duration: f32,
+        night_portion: f32,
+        morning_offset: f32,
+        noon_boost: f32,
+        noon_focus: f32,
+        min_intensity: f32,
+        ambient_strength: f32,
+        time: f32,
+    ) -> PyResult<Self> {
+        check_day_duration(day_duration)?;
+        check_night_portion(night_portion)?;
+        Ok(DayNightCycle {
+            time,
+            day_duration,
+            night_portion,
+            morning_offset,
+            noon_boost,
+            noon_focus,
+            min_intensity,
+            ambient_strength,
+            ..DayNightCycle::default()
+        })
+    }
+
+    #[getter]
+    pub fn get_day_duration(&self) -> f32 {
+        self.day_duration
+    }
+
+    #[setter]
+    pub fn set_day_duration(&mut self, day_duration: f32) -> PyResult<()> {
+        check_day_duration(day_duration)?;
+        self.day_duration = day_duration;
+        Ok(())
+    }
+
+    #[getter]
+    pub fn get_night_portion(&self) -> f32 {
+        self.night_portion
+    }
+
+    #[setter]
+    pub fn set_night_portion(&mut self, night_portion: f32) -> PyResult<()> {
+        check_night_portion(night_portion)?;
+        self.night_portion = night_portion;
+        Ok(())
+    }
+
+    /// Advances the game time by the given number of ticks.
+    pub fn advance(&mut self, ticks: f32) {
+        self.time += ticks;
+    }
+
+    /// How far(from 0.0 to 1.0) the current cycle is, the night starting at 1.0 - night_portion.
+    #[getter]
+    pub fn phase(&self) -> f32 {
+        let phase = self.time.rem_euclid(self.day_duration) / self.day_duration;
+        // rem_euclid rounds tiny negative times to day_duration itself, and a time which is
+        // not finite has no phase.
+        if (0.0..1.0).contains(&phase) { phase } else { 0.0 }
+    }
+
+    #[getter]
+    pub fn is_day(&self) -> bool {
+        self.phase() < 1.0 - self.night_portion
+    }
+
+    /// How far(from 0.0 to 1.0) the current day, or night, is.
+    #[getter]
+    pub fn progress(&self) -> f32 {
+        let phase = self.phase();
+        let day_portion = 1.0 - self.night_portion;
+        if phase < day_portion {
+            phase / day_portion
+        } else {
+            (phase - day_portion) / self.night_portion
+        }
+    }
+
+    /// Returns the number of the current cycle, the first one being 0.
+    #[getter]
+    pub fn day(&self) -> u32 {
+        (self.time / self.day_duration).floor().max(0.0) as u32
+    }
+
+    /// Sets the color stops of the ambient light over the cycle.
+    pub fn set_ambient_colors(&mut self, stops: Vec<(f32, [f32; 3])>) -> PyResult<()> {
+        self.ambient_colors = sort_stops(stops)?;
+        Ok(())
+    }
+
+    pub fn get_ambient_colors(&self) -> Vec<(f32, [f32; 3])> {
+        self.ambient_colors.clone()
+    }
+
+    /// Sets the color stops of the sun light over the cycle.
+    pub fn set_sun_colors(&mut self, stops: Vec<(f32, [f32; 3])>) -> PyResult<()> {
+        self.sun_colors = sort_stops(stops)?;
+        Ok(())
+    }
+
+    pub fn get_sun_colors(&self) -> Vec<(f32, [f32; 3])> {
+        self.sun_colors.clone()
+    }
+
+    /// Returns the direction(normalized x, y, z) the sun light comes from.
+    pub fn sun_direction(&self) -> [f32; 3] {
+        self.lighting().sun_direction
+    }
+
+    /// Returns the multiplier of the whole light.
+    pub fn intensity(&self) -> f32 {
+        self.lighting().intensity
+    }
+
+    pub fn ambient_color(&self) -> [f32; 3] {
+        sample_stops(&self.ambient_colors, self.phase())
+    }
+
+    pub fn sun_color(&self) -> [f32; 3] {
+        sample_stops(&self.sun_colors, self.phase())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "DayNightCycle(time={}, day_duration={}, phase={:.3}, is_day={})",
+            self.time,
+            self.day_duration,
+            self.phase(),
+            self.is_day()
+        )
+    }
+}
+
+impl DayNightCycle {
+    /// Returns the lighting at the current time.
+    pub fn lighting(&self) -> Lighting {
+        let progress = self.progress();
+
+        // The sun goes over the map(from 0 to PI) during the day and under it at night.
+        let arc = if self.is_day() { progress } else { 1.0 + progress };
+        let sun_angle = (arc + self.morning_offset) * PI;
+        let (sin, cos) = sun_angle.sin_cos();
+        let length = (cos * cos + sin * sin + 0.25).sqrt();
+        let sun_direction = [cos / length, sin / length, 0.5 / length];
+
+        let intensity = if self.is_day() {
+            let day_progress = (progress + self.morning_offset).fract();
+            let peak = (day_progress * PI).sin().max(0.0).powf(self.noon_focus);
+            (peak * self.noon_boost + self.min_intensity).max(self.min_intensity)
+        } else {
+            self.min_intensity
+        };
+
+        let ambient_color = self.ambient_color();
+        Lighting {
+            sun_direction,
+            ambient: ambient_color.map(|channel| channel * self.ambient_strength),
+            sun_color: self.sun_color(),
+            intensity,
+        }
+    }
+}
+
+fn check_day_duration(day_duration: f32) -> PyResult<()> {
+    if day_duration.is_nan() || day_duration <= 0.0 {
+        return Err(PyValueError::new_err(format!(
+            "A day must last more than 0 ticks, got {}",
+            day_duration
+        )));
+    }
+    Ok(())
+}
+
+fn check_night_portion(night_portion: f32) -> PyResult<()> {
+    if !(0.0..1.0).contains(&night_portion) {
+        return Err(PyValueError::new_err(format!(
+            "The night portion goes from 0.0 to less than 1.0, got {}",
+            night_portion
+        )));
+    }
+    Ok(())
+}
+
+fn sort_stops(mut stops: Vec<(f32, [f32; 3])>) -> PyResult<Vec<(f32, [f32; 3])>> {
+    if stops.is_empty() {
+        return Err(PyValueError::new_err("A color gradient needs at least one color stop"));
+    }
+    if let Some((phase, _)) = stops.iter().find(|(phase, _)| !(0.0..=1.0).contains(phase)) {
+        return Err(PyValueError::new_err(format!(
+            "Color stops are at phases from 0.0 to 1.0, got {}",
+            phase
+        )));
+    }
+    stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(stops)
+}
+
+/// Returns the color of sorted color stops at the given phase, blending the last stop into
+/// the first one of the next cycle.
+fn sample_stops(stops: &[(f32, [f32; 3])], phase: f32) -> [f32; 3] {
+    let (Some(first), Some(last)) = (stops.first(), stops.last()) else {
+        return [1.0; 3];
+    };
+    // The stops around the phase, wrapping around the cycle.
+    let (from, to) = match stops.iter().position(|(stop, _)| *stop > phase) {
+        Some(0) => ((last.0 - 1.0, last.1), *first),
+        Some(index) => (stops[index - 1], stops[index]),
+        None => (*last, (first.0 + 1.0, first.1)),
+    };
+    let span = to.0 - from.0;
+    let t = if span > 0.0 { (phase - from.0) / span } else { 0.0 };
+    [0, 1, 2].map(|channel| from.1[channel] + (to.1[channel] - from.1[channel]) * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cycle(time: f32, night_portion: f32) -> DayNightCycle {
+        DayNightCycle::new(4500.0, night_portion, 1.0 / 3.0, 1.25, 3.0, 0.3, 0.2, time).unwrap()
+    }
+
+    #[test]
+    fn keeps_the_phase_below_one() {
+        for time in [-1e-6, -0.0, f32::NAN, f32::INFINITY] {
+            let cycle = cycle(time, 0.0);
+            assert_eq!(cycle.phase(), 0.0, "time {}", time);
+            assert!(cycle.is_day());
+            assert_eq!(cycle.progress(), 0.0);
+        }
+        let cycle = cycle(-1125.0, 0.2);
+        assert_eq!(cycle.phase(), 0.75);
+    }
+
+    #[test]
+    fn splits_the_cycle_into_day_and_night() {
+        let day = cycle(1800.0, 0.2);
+        assert!(day.is_day());
+        assert!((day.progress() - 0.5).abs() < 1e-6);
+
+        let night = cycle(4500.0 * 1.9, 0.2);
+        assert!(!night.is_day());
+        assert!((night.progress() - 0.5).abs() < 1e-4);
+        assert_eq!(night.day(), 1);
+        assert!(night.lighting().intensity.is_finite());
+    }
+}
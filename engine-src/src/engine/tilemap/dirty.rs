@@ -0,0 +1,108 @@
+//! # Dirty Ranges
+//!
+//! Keeps track of which tiles changed since the instance buffer was last uploaded, so that
+//! changing a few tiles of a big map only uploads those tiles with `buffer_sub_data`.
+
+use std::ops::Range;
+
+/// Number of ranges kept before they are all merged into the one range covering them, so
+/// that scattered changes never make the list grow without bound.
+const MAX_RANGES: usize = 1024;
+
+/// Ranges of tile indices which changed since they were last uploaded.
+#[derive(Clone, Debug, Default)]
+pub struct DirtyRanges {
+    ranges: Vec<Range<usize>>,
+}
+
+impl DirtyRanges {
+    /// Marks a single tile as changed.
+    pub fn mark(&mut self, index: usize) {
+        self.mark_range(index..index + 1);
+    }
+
+    /// Marks a range of tiles as changed.
+    pub fn mark_range(&mut self, range: Range<usize>) {
+        if range.is_empty() {
+            return;
+        }
+        // Tiles are often changed one after another(rows, fills), which just grows the last range.
+        if let Some(last) = self.ranges.last_mut()
+            && range.start <= last.end
+            && range.end >= last.start
+        {
+            last.start = last.start.min(range.start);
+            last.end = last.end.max(range.end);
+            return;
+        }
+        if self.ranges.len() >= MAX_RANGES {
+            let start = self.ranges.iter().map(|range| range.start).fold(range.start, usize::min);
+            let end = self.ranges.iter().map(|range| range.end).fold(range.end, usize::max);
+            self.ranges.clear();
+            self.ranges.push(start..end);
+            return;
+        }
+        self.ranges.push(range);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns the changed ranges sorted and merged, with ranges less than `max_gap` tiles
+    /// apart merged together, and clears them.
+    ///
+    /// Uploading the unchanged tiles between two close ranges costs less than a separate
+    /// upload call for each range.
+    pub fn take(&mut self, max_gap: usize) -> Vec<Range<usize>> {
+        let mut ranges = std::mem::take(&mut self.ranges);
+        ranges.sort_by_key(|range| range.start);
+
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            if let Some(last) = merged.last_mut()
+                && range.start <= last.end + max_gap
+            {
+                last.end = last.end.max(range.end);
+                continue;
+            }
+            merged.push(range);
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_close_ranges() {
+        let mut dirty = DirtyRanges::default();
+        dirty.mark(5);
+        dirty.mark(6);
+        dirty.mark_range(20..30);
+        dirty.mark(0);
+        dirty.mark_range(40..40);
+        assert_eq!(dirty.take(0), [0..1, 5..7, 20..30]);
+        assert!(dirty.is_empty());
+
+        dirty.mark(0);
+        dirty.mark(5);
+        dirty.mark(20);
+        assert_eq!(dirty.take(4), [0..6, 20..21]);
+    }
+
+    #[test]
+    fn collapses_scattered_changes() {
+        let mut dirty = DirtyRanges::default();
+        for index in 0..MAX_RANGES {
+            dirty.mark(10 + index * 2);
+        }
+        assert_eq!(dirty.ranges.len(), MAX_RANGES);
+        dirty.mark(1);
+        let ranges = dirty.take(0);
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0], 1..10 + MAX_RANGES * 2 - 1);
+    }
+}
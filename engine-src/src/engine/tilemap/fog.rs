@@ -0,0 +1,179 @@
+//! # Fog of War
+//!
+//! The visibility of every tile of a map for the player: unexplored, explored or visible.
+//!
+//! The states are kept in a texture with a texel per tile, which the map fragment shader
+//! samples with bilinear filtering at the fragment's position in the grid. The fog is so
+//! blended between neighbouring tiles, which gives it soft edges. Unexplored tiles are
+//! black, explored tiles are dimmed and desaturated and visible tiles are left as they are.
+
+extern crate glow; // OpenGL bindings
+
+use glow::*;
+use pyo3::prelude::*;
+
+/// What the player knows of a tile.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Visibility {
+    /// Never seen, drawn black.
+    #[default]
+    Unexplored = 0,
+    /// Seen before but not in sight, drawn dimmed.
+    Explored = 1,
+    /// In sight, drawn as it is.
+    Visible = 2,
+}
+
+impl Visibility {
+    pub fn from_value(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Visibility::Unexplored),
+            1 => Some(Visibility::Explored),
+            2 => Some(Visibility::Visible),
+            _ => None,
+        }
+    }
+
+    /// Value of the state in the fog texture, explored tiles being halfway between
+    /// unexplored(0.0) and visible(1.0) tiles.
+    fn texel(self) -> u8 {
+        match self {
+            Visibility::Unexplored => 0,
+            Visibility::Explored => 128,
+            Visibility::Visible => 255,
+        }
+    }
+}
+
+pub struct FogLayer {
+    width: u32,
+    height: u32,
+    texture: NativeTexture,
+    states: Vec<Visibility>,
+    /// Whether the states changed since the texture was last uploaded.
+    dirty: bool,
+}
+
+impl FogLayer {
+    /// Creates the fog of a width x height map, every tile being unexplored.
+    pub fn new(gl: &Context, width: u32, height: u32) -> Result<Self, String> {
+        let texture = unsafe {
+            let texture = gl
+                .create_texture()
+                .map_err(|e| format!("Failed to create fog texture: {}", e))?;
+            gl.bind_texture(TEXTURE_2D, Some(texture));
+            // Linear filtering blends the fog between tiles.
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MIN_FILTER, LINEAR as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MAG_FILTER, LINEAR as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_WRAP_S, CLAMP_TO_EDGE as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_WRAP_T, CLAMP_TO_EDGE as i32);
+            gl.tex_image_2d(
+                TEXTURE_2D,
+                0,
+                R8 as i32,
+                width as i32,
+                height as i32,
+                0,
+                RED,
+                UNSIGNED_BYTE,
+                PixelUnpackData::Slice(None),
+            );
+            gl.bind_texture(TEXTURE_2D, None);
+            texture
+        };
+
+        Ok(FogLayer {
+            width,
+            height,
+            texture,
+            states: vec![Visibility::Unexplored; (width * height) as usize],
+            dirty: true,
+        })
+    }
+
+    pub fn texture(&self) -> NativeTexture {
+        self.texture
+    }
+
+    /// Returns the state of the tile at the given index(row after row).
+    pub fn get(&self, index: usize) -> Visibility {
+        self.states[index]
+    }
+
+    pub fn set(&mut self, index: usize, state: Visibility) {
+        if self.states[index] != state {
+            self.states[index] = state;
+            self.dirty = true;
+        }
+    }
+
+    /// Replaces all the states, which must be as many as the tiles.
+    pub fn set_all(&mut self, states: Vec<Visibility>) {
+        debug_assert_eq!(states.len(), self.states.len());
+        self.states = states;
+        self.dirty = true;
+    }
+
+    /// Sets every tile to the given state.
+    pub fn fill(&mut self, state: Visibility) {
+        self.states.fill(state);
+        self.dirty = true;
+    }
+
+    /// Turns the visible tiles into explored tiles, before the tiles in sight are set again.
+    pub fn clear_visible(&mut self) {
+        for state in self.states.iter_mut() {
+            if *state == Visibility::Visible {
+                *state = Visibility::Explored;
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// Turns the visible tiles into explored tiles and makes the tiles with a non zero value
+    /// in the mask(a byte per tile) visible.
+    pub fn reveal(&mut self, mask: &[u8]) {
+        for (state, seen) in self.states.iter_mut().zip(mask) {
+            if *seen != 0 {
+                *state = Visibility::Visible;
+            } else if *state == Visibility::Visible {
+                *state = Visibility::Explored;
+            }
+        }
+        self.dirty = true;
+    }
+
+    /// Uploads the states if they changed.
+    pub fn upload(&mut self, gl: &Context) {
+        if !self.dirty {
+            return;
+        }
+        let texels: Vec<u8> = self.states.iter().map(|state| state.texel()).collect();
+        unsafe {
+            gl.bind_texture(TEXTURE_2D, Some(self.texture));
+            // Rows of single bytes are not 4 byte aligned.
+            gl.pixel_store_i32(UNPACK_ALIGNMENT, 1);
+            gl.tex_sub_image_2d(
+                TEXTURE_2D,
+                0,
+                0,
+                0,
+                self.width as i32,
+                self.height as i32,
+                RED,
+                UNSIGNED_BYTE,
+                PixelUnpackData::Slice(Some(&texels)),
+            );
+            gl.pixel_store_i32(UNPACK_ALIGNMENT, 4);
+            gl.bind_texture(TEXTURE_2D, None);
+        }
+        self.dirty = false;
+    }
+
+    pub fn delete(&self, gl: &Context) {
+        unsafe {
+            gl.delete_texture(self.texture);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! # Grid Layouts
+//!
+//! Where the tiles of a map are, for square, isometric(diamond) and hexagonal grids.
+//!
+//! Tiles are addressed by their column and row in the map's width x height grid whatever the
+//! layout. Hexagonal layouts place the columns and rows in one of the ways of the hex grid
+//! guide below: offset coordinates, with every other row(pointy hexes) or column(flat hexes)
+//! shoved by half a tile, or axial coordinates, where the grid is a rhombus.
+//!
+//! Every tile is drawn as a quad as big as its shape, the map fragment shader discarding the
+//! pixels outside of the shape. "World" positions are in pixels of the map, before the camera.
+//! Reference: https://www.redblobgames.com/grids/hexagons/
+
+use pyo3::prelude::*;
+
+const SQRT_3: f32 = 1.732_050_8;
+
+/// How the tiles of a map are laid out.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GridLayout {
+    /// Square tiles in rows and columns.
+    #[default]
+    Square,
+    /// Diamonds half as high as they are wide, columns going down to the right and rows going
+    /// down to the left from the top corner of the map.
+    Isometric,
+    /// Pointy top hexes, odd rows shoved right.
+    HexPointyOdd,
+    /// Pointy top hexes, even rows shoved right.
+    HexPointyEven,
+    /// Flat top hexes, odd columns shoved down.
+    HexFlatOdd,
+    /// Flat top hexes, even columns shoved down.
+    HexFlatEven,
+    /// Pointy top hexes in axial coordinates(q is the column, r the row).
+    HexPointyAxial,
+    /// Flat top hexes in axial coordinates(q is the column, r the row).
+    HexFlatAxial,
+}
+
+/// The shapes the map fragment shader cuts tiles to, must match the TILE_SHAPE constants of map_frag.glsl.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileShape {
+    Square = 0,
+    Diamond = 1,
+    HexPointy = 2,
+    HexFlat = 3,
+}
+
+/// The layout of a map with its tile size and number of rows, which together place its tiles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Geometry {
+    pub layout: GridLayout,
+    /// Width of a tile's quad(in pixels), the height of pointy hexes.
+    pub tile_size: f32,
+    pub rows: u32,
+}
+
+impl Geometry {
+    pub fn new(layout: GridLayout, tile_size: f32, rows: u32) -> Self {
+        Geometry {
+            layout,
+            tile_size,
+            rows,
+        }
+    }
+
+    pub fn shape(&self) -> TileShape {
+        match self.layout {
+            GridLayout::Square => TileShape::Square,
+            GridLayout::Isometric => TileShape::Diamond,
+            GridLayout::HexPointyOdd | GridLayout::HexPointyEven | GridLayout::HexPointyAxial => TileShape::HexPointy,
+            GridLayout::HexFlatOdd | GridLayout::HexFlatEven | GridLayout::HexFlatAxial => TileShape::HexFlat,
+        }
+    }
+
+    /// Returns the size(width, height) of a tile's quad in pixels.
+    pub fn quad_size(&self) -> (f32, f32) {
+        let size = self.tile_size;
+        match self.shape() {
+            TileShape::Square => (size, size),
+            TileShape::Diamond => (size, size / 2.0),
+            TileShape::HexPointy => (size * SQRT_3 / 2.0, size),
+            TileShape::HexFlat => (size, size * SQRT_3 / 2.0),
+        }
+    }
+
+    /// Returns the center of the tile at the given column and row in the world.
+    pub fn tile_center(&self, column: i64, row: i64) -> (f32, f32) {
+        let size = self.tile_size;
+        let (width, height) = self.quad_size();
+        match self.shape() {
+            TileShape::Square => ((column as f32 + 0.5) * size, (row as f32 + 0.5) * size),
+            // The top corner of the map is at the middle of the top, so that no tile is left of 0.
+            TileShape::Diamond => (
+                (column - row + self.rows as i64) as f32 * width / 2.0,
+                (column + row + 1) as f32 * height / 2.0,
+            ),
+            TileShape::HexPointy | TileShape::HexFlat => {
+                let (q, r) = self.offset_to_axial(column, row);
+                let (shift_x, shift_y) = self.hex_shift();
+                let (x, y) = self.axial_to_world(q as f32, r as f32);
+                (x + width / 2.0 + shift_x, y + height / 2.0 + shift_y)
+            }
+        }
+    }
+
+    /// Returns the top left corner of the quad of a tile in the world, the offset of its instance.
+    pub fn tile_offset(&self, column: u32, row: u32) -> [f32; 2] {
+        let (x, y) = self.tile_center(column as i64, row as i64);
+        let (width, height) = self.quad_size();
+        [x - width / 2.0, y - height / 2.0]
+    }
+
+    /// Returns the column and row of the tile at a world position, which can be outside of
+    /// the map.
+    pub fn world_to_grid(&self, x: f32, y: f32) -> (i64, i64) {
+        let size = self.tile_size;
+        let (width, height) = self.quad_size();
+        match self.shape() {
+            TileShape::Square => ((x / size).floor() as i64, (y / size).floor() as i64),
+            // Diamonds are the unit squares around whole columns and rows, once the position is
+            // turned back into columns and rows.
+            TileShape::Diamond => {
+                let difference = x / (width / 2.0) - self.rows as f32;
+                let sum = y / (height / 2.0) - 1.0;
+                (
+                    ((sum + difference) / 2.0).round() as i64,
+                    ((sum - difference) / 2.0).round() as i64,
+                )
+            }
+            TileShape::HexPointy | TileShape::HexFlat => {
+                let (shift_x, shift_y) = self.hex_shift();
+                let (q, r) = self.world_to_axial(x - width / 2.0 - shift_x, y - height / 2.0 - shift_y);
+                let (q, r) = round_axial(q, r);
+                self.axial_to_offset(q, r)
+            }
+        }
+    }
+
+    /// Returns the distance from a point of a tile's quad(in pixels from its top left corner)
+    /// to the border of the tile's shape, negative outside of it, like distanceToBorder in
+    /// map_frag.glsl.
+    pub fn distance_to_border(&self, x: f32, y: f32) -> f32 {
+        let (width, height) = self.quad_size();
+        let (half_width, half_height) = (width / 2.0, height / 2.0);
+        let (px, py) = ((x - half_width).abs(), (y - half_height).abs());
+        match self.shape() {
+            TileShape::Square => (half_width - px).min(half_height - py),
+            TileShape::Diamond => {
+                let (inverse_x, inverse_y) = (1.0 / half_width, 1.0 / half_height);
+                (1.0 - px * inverse_x - py * inverse_y) / inverse_x.hypot(inverse_y)
+            }
+            TileShape::HexPointy => half_width - px.max(px * 0.5 + py * SQRT_3 / 2.0),
+            TileShape::HexFlat => half_height - py.max(px * SQRT_3 / 2.0 + py * 0.5),
+        }
+    }
+
+    /// Returns the key tiles are drawn in the order of, so that tiles lower on the screen are
+    /// drawn over the ones above them.
+    pub fn draw_key(&self, column: u32, row: u32) -> (f32, f32) {
+        let (x, y) = self.tile_center(column as i64, row as i64);
+        (y, x)
+    }
+
+    /// Returns the axial coordinates of a tile of a hexagonal layout.
+    fn offset_to_axial(&self, column: i64, row: i64) -> (i64, i64) {
+        match self.layout {
+            GridLayout::HexPointyOdd => (column - (row - (row & 1)) / 2, row),
+            GridLayout::HexPointyEven => (column - (row + (row & 1)) / 2, row),
+            GridLayout::HexFlatOdd => (column, row - (column - (column & 1)) / 2),
+            GridLayout::HexFlatEven => (column, row - (column + (column & 1)) / 2),
+            _ => (column, row),
+        }
+    }
+
+    /// Returns the column and row of a tile of a hexagonal layout from its axial coordinates.
+    fn axial_to_offset(&self, q: i64, r: i64) -> (i64, i64) {
+        match self.layout {
+            GridLayout::HexPointyOdd => (q + (r - (r & 1)) / 2, r),
+            GridLayout::HexPointyEven => (q + (r + (r & 1)) / 2, r),
+            GridLayout::HexFlatOdd => (q, r + (q - (q & 1)) / 2),
+            GridLayout::HexFlatEven => (q, r + (q + (q & 1)) / 2),
+            _ => (q, r),
+        }
+    }
+
+    /// Offset of the whole map for even layouts, whose shoved rows(or columns) are the
+    /// first ones, so that no tile is left of(or above) 0.
+    fn hex_shift(&self) -> (f32, f32) {
+        let (width, height) = self.quad_size();
+        match self.layout {
+            GridLayout::HexPointyEven => (width / 2.0, 0.0),
+            GridLayout::HexFlatEven => (0.0, height / 2.0),
+            _ => (0.0, 0.0),
+        }
+    }
+
+    fn hex_radius(&self) -> f32 {
+        self.tile_size / 2.0
+    }
+
+    fn axial_to_world(&self, q: f32, r: f32) -> (f32, f32) {
+        let radius = self.hex_radius();
+        match self.shape() {
+            TileShape::HexPointy => (radius * SQRT_3 * (q + r / 2.0), radius * 1.5 * r),
+            _ => (radius * 1.5 * q, radius * SQRT_3 * (r + q / 2.0)),
+        }
+    }
+
+    fn world_to_axial(&self, x: f32, y: f32) -> (f32, f32) {
+        let radius = self.hex_radius();
+        match self.shape() {
+            TileShape::HexPointy => ((SQRT_3 / 3.0 * x - y / 3.0) / radius, (2.0 / 3.0 * y) / radius),
+            _ => ((2.0 / 3.0 * x) / radius, (-x / 3.0 + SQRT_3 / 3.0 * y) / radius),
+        }
+    }
+}
+
+/// Rounds fractional axial coordinates to the hex they are in.
+fn round_axial(q: f32, r: f32) -> (i64, i64) {
+    let s = -q - r;
+    let (mut rounded_q, mut rounded_r, rounded_s) = (q.round(), r.round(), s.round());
+    let (q_error, r_error, s_error) = ((rounded_q - q).abs(), (rounded_r - r).abs(), (rounded_s - s).abs());
+    // The coordinate which was rounded the most is the one to fix, as q + r + s must be 0.
+    if q_error > r_error && q_error > s_error {
+        rounded_q = -rounded_r - rounded_s;
+    } else if r_error > s_error {
+        rounded_r = -rounded_q - rounded_s;
+    }
+    (rounded_q as i64, rounded_r as i64)
+}
@@ -0,0 +1,1284 @@
+//! # Tile Map
+//!
+//! Draws a grid of tiles with the map shaders(map_vert.glsl and map_frag.glsl), with an
+//! instanced draw call for every chunk of the map in view(see the chunks module).
+//!
+//! Every tile is an instance of the same quad. The per tile fields(texture, highlight,
+//! resource, worker, troops, structure, ...) are kept in instance buffers, whose
+//! attributes advance once per tile instead of once per vertex(an attribute divisor of 1).
+//! Only the tiles which changed since the last draw are uploaded(see the dirty module).
+//! Which atlas cells the ground textures and overlays are drawn with comes from the overlay
+//! table(see the overlays module) and the animation table(see the animations module), which
+//! can be changed from python, and the fog of war over the tiles from the fog layer(see the
+//! fog module).
+//! Reference: https://learnopengl.com/Advanced-OpenGL/Instancing
+
+extern crate glow; // OpenGL bindings
+
+use std::cell::RefCell;
+use std::ops::Range;
+use std::rc::{Rc, Weak};
+use std::time::Instant;
+
+use bytemuck::{Pod, Zeroable};
+use glow::*;
+use pyo3::buffer::PyBuffer;
+use pyo3::exceptions::{PyIOError, PyIndexError, PyKeyError, PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::T_CONTEXT;
+use crate::engine::gltext::textures::load_texture;
+use crate::engine::helpers::get_tctx;
+use crate::engine::tilemap::animations::{Animation, AnimationTable, MAX_ANIMATIONS};
+use crate::engine::tilemap::autotile::AutotileRules;
+use crate::engine::tilemap::chunks::{Chunk, is_visible};
+use crate::engine::tilemap::daynight::DayNightCycle;
+use crate::engine::tilemap::dirty::DirtyRanges;
+use crate::engine::tilemap::fog::{FogLayer, Visibility};
+use crate::engine::tilemap::layout::{Geometry, GridLayout};
+use crate::engine::tilemap::overlays::{MAX_STRUCTURES, MAX_TEXTURES, Overlay, OverlayEntry, OverlayTable};
+
+/// Name of the tile map shader program in the global shader programs.
+pub const MAP_SHADER: &str = "map-shader";
+
+/// Number of components of every per tile attribute of the map vertex shader, from
+/// location 2(aOffset, with the cell) to location 15(aTextureIndex). GL 3.3 only
+/// guarantees 16 attribute locations, there is none left.
+pub const INSTANCE_ATTRIBUTES: [i32; 14] = [4, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 4, 1, 1];
+
+/// Number of floats of a tile in the data of `TileMap.set_tiles_data`, which is a tile's
+/// instance without its offset and cell.
+const TILE_FIELDS: usize = 16;
+
+/// The tile fields which are a single float of the instance, with their index in it.
+const FIELDS: [(&str, usize); 12] = [
+    ("texture", 4),
+    ("highlight", 5),
+    ("selected", 6),
+    ("resource", 7),
+    ("worker", 8),
+    ("troops", 9),
+    ("structure", 10),
+    ("reserved", 11),
+    ("grid", 12),
+    ("highlight_resources", 13),
+    ("texture_dimensions", 18),
+    ("texture_sub_index", 19),
+];
+
+/// The per tile attributes as the map vertex shader reads them from the instance buffer.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct TileInstance {
+    /// Position of the tile's top left corner in the map(in pixels).
+    pub offset: [f32; 2],
+    /// Column and row of the tile, where the fog of war is sampled. The shader reads it
+    /// with the offset, as one attribute.
+    pub cell: [f32; 2],
+    pub texture: f32,
+    pub highlight: f32,
+    pub selected: f32,
+    pub resource: f32,
+    pub worker: f32,
+    pub troops: f32,
+    pub structure: f32,
+    pub reserved: f32,
+    pub grid: f32,
+    pub highlight_resources: f32,
+    pub highlight_color: [f32; 4],
+    pub texture_dimensions: f32,
+    pub texture_sub_index: f32,
+}
+
+/// The fields of a single tile of a `TileMap`.
+///
+/// _Example(python)_:
+/// ```python
+/// tile = tge.tilemap.Tile(texture=2, resource=1, structure=26)
+/// world.set_tile(12, 7, tile)
+/// ```
+#[pyclass(get_all, set_all)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tile {
+    /// Index of the tile's ground texture in the top row of the atlas.
+    pub texture: u32,
+    pub highlight: bool,
+    pub selected: bool,
+    /// Resource on the tile, 0 for none.
+    pub resource: u32,
+    pub worker: bool,
+    pub troops: bool,
+    /// Structure(building or road piece) drawn over the tile, 0 for none.
+    pub structure: u32,
+    /// Whether the tile's border is drawn as a grid line.
+    pub grid: bool,
+    /// Marks the tile if it has a resource.
+    pub highlight_resources: bool,
+    /// Color of the tile's border, no border is drawn if its alpha is 0.
+    pub highlight_color: [f32; 4],
+    /// Overlays are cut into texture_dimensions x texture_dimensions parts, of which the
+    /// one at texture_sub_index is drawn.
+    pub texture_dimensions: u32,
+    pub texture_sub_index: u32,
+    /// Free for the game to use, the shaders don't read it yet.
+    pub reserved: f32,
+}
+
+impl Default for Tile {
+    fn default() -> Self {
+        Tile {
+            texture: 0,
+            highlight: false,
+            selected: false,
+            resource: 0,
+            worker: false,
+            troops: false,
+            structure: 0,
+            grid: false,
+            highlight_resources: false,
+            highlight_color: [0.0; 4],
+            texture_dimensions: 1,
+            texture_sub_index: 0,
+            reserved: 0.0,
+        }
+    }
+}
+
+#[pymethods]
+impl Tile {
+    #[new]
+    #[pyo3(signature = (
+        texture=0,
+        highlight=false,
+        selected=false,
+        resource=0,
+        worker=false,
+        troops=false,
+        structure=0,
+        grid=false,
+        highlight_resources=false,
+        highlight_color=[0.0; 4],
+        texture_dimensions=1,
+        texture_sub_index=0,
+        reserved=0.0
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        texture: u32,
+        highlight: bool,
+        selected: bool,
+        resource: u32,
+        worker: bool,
+        troops: bool,
+        structure: u32,
+        grid: bool,
+        highlight_resources: bool,
+        highlight_color: [f32; 4],
+        texture_dimensions: u32,
+        texture_sub_index: u32,
+        reserved: f32,
+    ) -> Self {
+        Tile {
+            texture,
+            highlight,
+            selected,
+            resource,
+            worker,
+            troops,
+            structure,
+            grid,
+            highlight_resources,
+            highlight_color,
+            texture_dimensions,
+            texture_sub_index,
+            reserved,
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+impl Tile {
+    pub fn to_instance(&self, offset: [f32; 2], cell: [f32; 2]) -> TileInstance {
+        let flag = |value: bool| if value { 1.0 } else { 0.0 };
+        TileInstance {
+            offset,
+            cell,
+            texture: self.texture as f32,
+            highlight: flag(self.highlight),
+            selected: flag(self.selected),
+            resource: self.resource as f32,
+            worker: flag(self.worker),
+            troops: flag(self.troops),
+            structure: self.structure as f32,
+            reserved: self.reserved,
+            grid: flag(self.grid),
+            highlight_resources: flag(self.highlight_resources),
+            highlight_color: self.highlight_color,
+            texture_dimensions: self.texture_dimensions as f32,
+            texture_sub_index: self.texture_sub_index as f32,
+        }
+    }
+
+    pub fn from_instance(instance: &TileInstance) -> Self {
+        Tile {
+            texture: instance.texture as u32,
+            highlight: instance.highlight > 0.0,
+            selected: instance.selected > 0.0,
+            resource: instance.resource as u32,
+            worker: instance.worker > 0.0,
+            troops: instance.troops > 0.0,
+            structure: instance.structure as u32,
+            grid: instance.grid > 0.0,
+            highlight_resources: instance.highlight_resources > 0.0,
+            highlight_color: instance.highlight_color,
+            texture_dimensions: instance.texture_dimensions as u32,
+            texture_sub_index: instance.texture_sub_index as u32,
+            reserved: instance.reserved,
+        }
+    }
+}
+
+/// A width x height grid of tiles drawn from a texture atlas, whose cells are tile_size
+/// pixels wide and high.
+///
+/// Tiles are square, or isometric diamonds or hexes depending on the map's `GridLayout`,
+/// their quad being tile_size pixels wide(pointy hexes are tile_size pixels high). The
+/// atlas cells are stretched over the quads, the pixels outside of the tile shape being cut.
+///
+/// The map is split into chunks of chunk_size x chunk_size tiles, and only the chunks in
+/// view of the camera(widened by `cull_margin` pixels) are drawn.
+///
+/// The map's top left corner is at (0, 0) of the map and `camera` is the point of the map
+/// at the top left of the window. The map is lit by its `day_night` cycle(see
+/// `DayNightCycle`), and animated textures and overlays(forts, troops, ...) follow `ticks`,
+/// which the map advances by the milliseconds since its last draw.
+///
+/// _Example(python)_:
+/// ```python
+/// world = tge.tilemap.TileMap(256, 256, 32.0, "assets/atlas.png")
+/// world.fill(tge.tilemap.Tile(texture=1, grid=True))
+/// world.camera = (camera_x, camera_y)
+/// world.draw()
+/// print(f"{world.visible_chunks}/{world.chunk_count} chunks drawn")
+///
+/// hexes = tge.tilemap.TileMap(64, 64, 48.0, "assets/hexes.png", layout=tge.tilemap.GridLayout.HexPointyOdd)
+/// x, y = hexes.grid_to_world(10, 3)
+/// ```
+#[pyclass(unsendable)]
+pub struct TileMap {
+    width: u32,
+    height: u32,
+    geometry: Geometry,
+    atlas: NativeTexture,
+    atlas_path: String,
+    atlas_size: [f32; 2],
+    /// The quad shared by the chunks' vertex arrays.
+    quad_vbo: NativeBuffer,
+    chunk_size: u32,
+    /// Number of chunks in a row of chunks.
+    chunk_columns: u32,
+    chunks: Vec<Chunk>,
+    /// Indices of the chunks in the order they are drawn in.
+    chunk_order: Vec<usize>,
+    /// Number of chunks drawn by the last draw.
+    visible_chunks: usize,
+    overlays: OverlayTable,
+    animations: AnimationTable,
+    /// When the map was last drawn, to advance `ticks`.
+    last_draw: Instant,
+    fog: FogLayer,
+    /// The tiles(row after row) whose tile or fog changed since each attached minimap last
+    /// took them. Minimaps own their changes, those of dropped minimaps are forgotten.
+    minimap_changes: Vec<Weak<RefCell<DirtyRanges>>>,
+    /// Whether the fog of war is drawn over the map.
+    #[pyo3(get, set)]
+    fog_enabled: bool,
+    /// Brightness(from 0.0 to 1.0) of the explored tiles which are not visible.
+    #[pyo3(get, set)]
+    fog_explored_brightness: f32,
+    /// Saturation(from 0.0 for gray to 1.0) of the explored tiles which are not visible.
+    #[pyo3(get, set)]
+    fog_explored_saturation: f32,
+    /// Distance(in pixels) around the view in which chunks are still drawn.
+    #[pyo3(get, set)]
+    cull_margin: f32,
+    #[pyo3(get, set)]
+    camera: (f32, f32),
+    /// Rotation(in degrees) of the map around the center of the screen.
+    #[pyo3(get, set)]
+    rotation: f32,
+    /// The day and night cycle lighting the map, which can be shared with the game.
+    #[pyo3(get, set)]
+    day_night: Py<DayNightCycle>,
+    /// Time(in milliseconds) the animations are at, can be set to jump in time.
+    #[pyo3(get, set)]
+    ticks: f32,
+    /// How fast `ticks` goes compared to real time, 0.0 pausing the animations.
+    #[pyo3(get, set)]
+    animation_speed: f32,
+}
+
+#[pymethods]
+impl TileMap {
+    #[new]
+    #[pyo3(signature = (width, height, tile_size, atlas_path, chunk_size=32, layout=GridLayout::Square))]
+    pub fn new(
+        py: Python<'_>,
+        width: u32,
+        height: u32,
+        tile_size: f32,
+        atlas_path: &str,
+        chunk_size: u32,
+        layout: GridLayout,
+    ) -> PyResult<Self> {
+        if width == 0 || height == 0 || tile_size <= 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "Invalid tile map of {}x{} tiles of {} pixels",
+                width, height, tile_size
+            )));
+        }
+        if chunk_size == 0 {
+            return Err(PyValueError::new_err("Chunks must have at least 1 tile"));
+        }
+
+        let ctx = get_tctx();
+        let gl = ctx.get_gl();
+
+        let (atlas_width, atlas_height) =
+            image::image_dimensions(atlas_path).map_err(|e| PyIOError::new_err(format!("{}", e)))?;
+        let atlas = load_texture(&gl, atlas_path).map_err(PyIOError::new_err)?;
+        let overlays = OverlayTable::new(&gl).map_err(PyRuntimeError::new_err)?;
+        let animations = AnimationTable::new(&gl).map_err(PyRuntimeError::new_err)?;
+        let fog = FogLayer::new(&gl, width, height).map_err(PyRuntimeError::new_err)?;
+
+        let geometry = Geometry::new(layout, tile_size, height);
+        // The quad every tile is drawn with, positions go from 0 to the quad's size as the
+        // fragment shader measures the distance to the tile's border with them.
+        let (quad_width, quad_height) = geometry.quad_size();
+        #[rustfmt::skip]
+        let quad: [f32; 24] = [
+            0.0, 0.0, 0.0, 0.0,
+            quad_width, 0.0, 1.0, 0.0,
+            0.0, quad_height, 0.0, 1.0,
+            quad_width, 0.0, 1.0, 0.0,
+            quad_width, quad_height, 1.0, 1.0,
+            0.0, quad_height, 0.0, 1.0,
+        ];
+
+        let quad_vbo = unsafe {
+            let quad_vbo = gl.create_buffer().map_err(PyRuntimeError::new_err)?;
+            gl.bind_buffer(ARRAY_BUFFER, Some(quad_vbo));
+            gl.buffer_data_u8_slice(ARRAY_BUFFER, bytemuck::cast_slice(&quad), STATIC_DRAW);
+            gl.bind_buffer(ARRAY_BUFFER, None);
+            quad_vbo
+        };
+
+        let chunk_columns = width.div_ceil(chunk_size);
+        let chunk_rows = height.div_ceil(chunk_size);
+        let mut chunks = Vec::with_capacity((chunk_columns * chunk_rows) as usize);
+        for row in 0..chunk_rows {
+            for column in 0..chunk_columns {
+                let origin = (column * chunk_size, row * chunk_size);
+                let chunk_width = chunk_size.min(width - origin.0);
+                let chunk_height = chunk_size.min(height - origin.1);
+                let chunk = Chunk::new(&gl, quad_vbo, origin, chunk_width, chunk_height, &geometry)
+                    .map_err(PyRuntimeError::new_err)?;
+                chunks.push(chunk);
+            }
+        }
+        // Chunks are drawn in the order of their top left tiles, like the tiles in a chunk.
+        let mut chunk_order: Vec<usize> = (0..chunks.len()).collect();
+        chunk_order.sort_by(|a, b| {
+            let (a, b) = (&chunks[*a], &chunks[*b]);
+            let a = geometry.draw_key(a.origin.0, a.origin.1);
+            let b = geometry.draw_key(b.origin.0, b.origin.1);
+            a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+        });
+
+        Ok(TileMap {
+            width,
+            height,
+            geometry,
+            atlas,
+            atlas_path: atlas_path.to_string(),
+            atlas_size: [atlas_width as f32, atlas_height as f32],
+            quad_vbo,
+            chunk_size,
+            chunk_columns,
+            chunks,
+            chunk_order,
+            visible_chunks: 0,
+            overlays,
+            animations,
+            last_draw: Instant::now(),
+            fog,
+            minimap_changes: Vec::new(),
+            fog_enabled: false,
+            fog_explored_brightness: 0.5,
+            fog_explored_saturation: 0.0,
+            cull_margin: tile_size * 2.0,
+            camera: (0.0, 0.0),
+            rotation: 0.0,
+            day_night: Py::new(py, DayNightCycle::default())?,
+            ticks: 0.0,
+            animation_speed: 1.0,
+        })
+    }
+
+    /// Width of the map in tiles.
+    #[getter]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the map in tiles.
+    #[getter]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Size of a tile in pixels.
+    #[getter]
+    pub fn tile_size(&self) -> f32 {
+        self.geometry.tile_size
+    }
+
+    #[getter]
+    pub fn layout(&self) -> GridLayout {
+        self.geometry.layout
+    }
+
+    /// Size(width, height) of a tile's quad in pixels.
+    #[getter]
+    pub fn quad_size(&self) -> (f32, f32) {
+        self.geometry.quad_size()
+    }
+
+    /// Size of the chunks in tiles.
+    #[getter]
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size
+    }
+
+    #[getter]
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Number of chunks drawn by the last draw.
+    #[getter]
+    pub fn visible_chunks(&self) -> usize {
+        self.visible_chunks
+    }
+
+    /// Returns the center of the tile at the given column(x) and row(y) in the map(in pixels).
+    /// Tiles outside of the map are placed like the tiles in it would be.
+    pub fn grid_to_world(&self, x: i64, y: i64) -> (f32, f32) {
+        self.geometry.tile_center(x, y)
+    }
+
+    /// Returns the column and row of the tile at a position of the map(in pixels), None if
+    /// there is no tile there.
+    pub fn world_to_grid(&self, x: f32, y: f32) -> Option<(u32, u32)> {
+        let (column, row) = self.geometry.world_to_grid(x, y);
+        self.contains(column, row).then_some((column as u32, row as u32))
+    }
+
+    /// Returns where a position of the map is on the screen, for the current camera. The
+    /// rotation of the map is not applied(see `pick`).
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        (x - self.camera.0, y - self.camera.1)
+    }
+
+    /// Returns the position of the map at a point of the screen, for the current camera. The
+    /// rotation of the map is not applied.
+    pub fn screen_to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        (x + self.camera.0, y + self.camera.1)
+    }
+
+    /// Returns the column and row of the tile drawn at a point of the screen(like the mouse
+    /// position), None if no tile is drawn there. The camera and rotation are applied like
+    /// the map shaders apply them, in every layout.
+    ///
+    /// _Example(python)_:
+    /// ```python
+    /// tile = world.pick(mouse_x, mouse_y)
+    /// if tile is not None:
+    ///     world.set_field(*tile, "selected", 1.0)
+    /// ```
+    pub fn pick(&self, x: f32, y: f32) -> Option<(u32, u32)> {
+        let screen_center = screen_center();
+        let (quad_width, quad_height) = self.geometry.quad_size();
+        let world = (x + self.camera.0, y + self.camera.1);
+
+        // The tile whose quad would be centered on the point, which only rotates the offsets
+        // back. Quads are not rotated, so the tile drawn there can be one of its neighbours.
+        let offset = self.rotate_offset(
+            (world.0 - quad_width / 2.0, world.1 - quad_height / 2.0),
+            screen_center,
+            -self.rotation,
+        );
+        let (column, row) = self
+            .geometry
+            .world_to_grid(offset.0 + quad_width / 2.0, offset.1 + quad_height / 2.0);
+
+        let mut picked: Option<((f32, f32), (u32, u32))> = None;
+        for (column, row) in (row - 1..=row + 1)
+            .flat_map(|row| (column - 1..=column + 1).map(move |column| (column, row)))
+        {
+            if !self.contains(column, row) {
+                continue;
+            }
+            let (column, row) = (column as u32, row as u32);
+            let [offset_x, offset_y] = self.geometry.tile_offset(column, row);
+            let (left, top) =
+                self.rotate_offset((offset_x, offset_y), screen_center, self.rotation);
+            if self
+                .geometry
+                .distance_to_border(world.0 - left, world.1 - top)
+                < 0.0
+            {
+                continue;
+            }
+            // Of overlapping tiles, the one drawn last is on top.
+            let key = self.geometry.draw_key(column, row);
+            if picked.is_none_or(|(picked_key, _)| {
+                key.0
+                    .total_cmp(&picked_key.0)
+                    .then(key.1.total_cmp(&picked_key.1))
+                    .is_gt()
+            }) {
+                picked = Some((key, (column, row)));
+            }
+        }
+        picked.map(|(_, tile)| tile)
+    }
+
+    /// Returns where the center of the tile at the given column(x) and row(y) is drawn on
+    /// the screen, the inverse of `pick`.
+    pub fn tile_to_screen(&self, x: i64, y: i64) -> (f32, f32) {
+        let (center_x, center_y) = self.geometry.tile_center(x, y);
+        let (quad_width, quad_height) = self.geometry.quad_size();
+        let (left, top) = self.rotate_offset(
+            (center_x - quad_width / 2.0, center_y - quad_height / 2.0),
+            screen_center(),
+            self.rotation,
+        );
+        (
+            left + quad_width / 2.0 - self.camera.0,
+            top + quad_height / 2.0 - self.camera.1,
+        )
+    }
+
+    pub fn get_tile(&self, x: u32, y: u32) -> PyResult<Tile> {
+        let (chunk, index) = self.tile_index(x, y)?;
+        Ok(Tile::from_instance(&self.chunks[chunk].instances[index]))
+    }
+
+    pub fn set_tile(&mut self, x: u32, y: u32, tile: Tile) -> PyResult<()> {
+        let (chunk, index) = self.tile_index(x, y)?;
+        let chunk = &mut self.chunks[chunk];
+        let instance = &mut chunk.instances[index];
+        *instance = tile.to_instance(instance.offset, instance.cell);
+        chunk.mark(index);
+        self.mark_changed((y * self.width + x) as usize);
+        Ok(())
+    }
+
+    /// Sets a single field(like "structure" or "troops") of a tile, flags being 0.0 or 1.0.
+    pub fn set_field(&mut self, x: u32, y: u32, field: &str, value: f32) -> PyResult<()> {
+        let field_index = field_index(field)?;
+        self.set_field_value(x, y, field_index, value)
+    }
+
+    /// Sets every tile of the map to the given tile.
+    pub fn fill(&mut self, tile: Tile) {
+        for chunk in self.chunks.iter_mut() {
+            for instance in chunk.instances.iter_mut() {
+                *instance = tile.to_instance(instance.offset, instance.cell);
+            }
+            chunk.mark_all();
+        }
+        self.mark_all_changed();
+    }
+
+    /// Places a tile of an autotiling set(like a road) at (x, y), with the variant of the
+    /// neighbours it connects to, and updates the tiles of the set around it. Returns the
+    /// structure placed.
+    pub fn place_autotile(
+        &mut self,
+        x: u32,
+        y: u32,
+        rules: PyRef<'_, AutotileRules>,
+    ) -> PyResult<u32> {
+        let structure = self.autotile(x, y, &rules)?;
+        self.autotile_neighbours(x, y, &rules)?;
+        Ok(structure)
+    }
+
+    /// Removes the structure at (x, y) and updates the tiles of the autotiling set around it.
+    pub fn remove_autotile(
+        &mut self,
+        x: u32,
+        y: u32,
+        rules: PyRef<'_, AutotileRules>,
+    ) -> PyResult<()> {
+        self.set_field_value(x, y, field_index("structure")?, 0.0)?;
+        self.autotile_neighbours(x, y, &rules)
+    }
+
+    /// Updates every tile of an autotiling set on the map, after structures were set without
+    /// `place_autotile`(like with `set_tiles_data`).
+    pub fn update_autotiles(&mut self, rules: PyRef<'_, AutotileRules>) -> PyResult<()> {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if rules.is_variant(self.instance(x, y)?.structure as u32) {
+                    self.autotile(x, y, &rules)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the mask of the neighbours of the tile at (x, y) which the tiles of an
+    /// autotiling set connect to.
+    pub fn autotile_mask(&self, x: u32, y: u32, rules: PyRef<'_, AutotileRules>) -> PyResult<u8> {
+        self.tile_index(x, y)?;
+        Ok(rules.mask(x as i64, y as i64, |x, y| self.structure_at(x, y)))
+    }
+
+    /// Replaces all the tiles at once with the tiles in `data`, a buffer(numpy float32
+    /// array, bytes, bytearray, ...) of 16 float32 values per tile, row after row:
+    ///
+    /// texture, highlight, selected, resource, worker, troops, structure, reserved, grid,
+    /// highlight_resources, highlight_color(r, g, b, a), texture_dimensions, texture_sub_index
+    ///
+    /// Flags are 0.0 or 1.0.
+    ///
+    /// _Example(python)_:
+    /// ```python
+    /// tiles = numpy.zeros((world.height, world.width, 16), dtype=numpy.float32)
+    /// tiles[:, :, 0] = terrain        # texture
+    /// tiles[:, :, 14] = 1.0           # texture_dimensions
+    /// world.set_tiles_data(tiles)
+    /// ```
+    pub fn set_tiles_data(&mut self, py: Python<'_>, data: &Bound<'_, PyAny>) -> PyResult<()> {
+        let values: Vec<f32> = match PyBuffer::<f32>::get(data) {
+            Ok(buffer) => buffer.to_vec(py)?,
+            // Raw bytes of native endian float32 values.
+            Err(_) => {
+                let bytes = PyBuffer::<u8>::get(data)?.to_vec(py)?;
+                if bytes.len() % std::mem::size_of::<f32>() != 0 {
+                    return Err(PyValueError::new_err("Tile data is not made of float32 values"));
+                }
+                bytemuck::pod_collect_to_vec(&bytes)
+            }
+        };
+
+        let expected = (self.width * self.height) as usize * TILE_FIELDS;
+        if values.len() != expected {
+            return Err(PyValueError::new_err(format!(
+                "Tile data has {} values, a {}x{} map needs {}",
+                values.len(),
+                self.width,
+                self.height,
+                expected
+            )));
+        }
+
+        for (tile, fields) in values.chunks_exact(TILE_FIELDS).enumerate() {
+            let (x, y) = (tile as u32 % self.width, tile as u32 / self.width);
+            let (chunk, index) = self.tile_index(x, y)?;
+            // Everything after the offset and the cell, in the order of the fields.
+            let instance_values: &mut [f32] =
+                bytemuck::cast_slice_mut(std::slice::from_mut(&mut self.chunks[chunk].instances[index]));
+            instance_values[4..4 + TILE_FIELDS].copy_from_slice(fields);
+        }
+        for chunk in self.chunks.iter_mut() {
+            chunk.mark_all();
+        }
+        self.mark_all_changed();
+        Ok(())
+    }
+
+    pub fn get_visibility(&self, x: u32, y: u32) -> PyResult<Visibility> {
+        let index = self.grid_index(x, y)?;
+        Ok(self.fog.get(index))
+    }
+
+    /// Sets the fog of war state of a tile, which is drawn once `fog_enabled` is set.
+    pub fn set_visibility(&mut self, x: u32, y: u32, visibility: Visibility) -> PyResult<()> {
+        let index = self.grid_index(x, y)?;
+        self.fog.set(index, visibility);
+        self.mark_changed(index);
+        Ok(())
+    }
+
+    /// Sets the fog of war state of every tile.
+    pub fn fill_visibility(&mut self, visibility: Visibility) {
+        self.fog.fill(visibility);
+        self.mark_all_changed();
+    }
+
+    /// Turns the visible tiles into explored ones, before the tiles now in sight are set
+    /// visible again.
+    pub fn fade_visible(&mut self) {
+        self.fog.clear_visible();
+        self.mark_all_changed();
+    }
+
+    /// Updates the fog of war for what the player sees this turn: the tiles which are non zero
+    /// in `seen`(a buffer of a byte per tile, row after row, like `SightGrid.fields_of_view`
+    /// returns) become visible, and the other visible tiles become explored.
+    pub fn reveal(&mut self, py: Python<'_>, seen: &Bound<'_, PyAny>) -> PyResult<()> {
+        let mask = PyBuffer::<u8>::get(seen)?.to_vec(py)?;
+        let expected = (self.width * self.height) as usize;
+        if mask.len() != expected {
+            return Err(PyValueError::new_err(format!(
+                "Seen tiles have {} values, a {}x{} map needs {}",
+                mask.len(),
+                self.width,
+                self.height,
+                expected
+            )));
+        }
+        self.fog.reveal(&mask);
+        self.mark_all_changed();
+        Ok(())
+    }
+
+    /// Replaces the fog of war states of all the tiles at once with `data`, a buffer(numpy
+    /// uint8 array, bytes, bytearray, ...) of a byte per tile, row after row: 0 for
+    /// unexplored, 1 for explored and 2 for visible tiles.
+    ///
+    /// _Example(python)_:
+    /// ```python
+    /// world.fog_enabled = True
+    /// world.set_visibility_data(numpy.where(in_sight, 2, numpy.minimum(explored, 1)).astype(numpy.uint8))
+    /// ```
+    pub fn set_visibility_data(&mut self, py: Python<'_>, data: &Bound<'_, PyAny>) -> PyResult<()> {
+        let bytes = PyBuffer::<u8>::get(data)?.to_vec(py)?;
+        let expected = (self.width * self.height) as usize;
+        if bytes.len() != expected {
+            return Err(PyValueError::new_err(format!(
+                "Visibility data has {} values, a {}x{} map needs {}",
+                bytes.len(),
+                self.width,
+                self.height,
+                expected
+            )));
+        }
+        let states = bytes
+            .iter()
+            .map(|value| {
+                Visibility::from_value(*value)
+                    .ok_or_else(|| PyValueError::new_err(format!("Invalid visibility {}, expected 0, 1 or 2", value)))
+            })
+            .collect::<PyResult<Vec<Visibility>>>()?;
+        self.fog.set_all(states);
+        self.mark_all_changed();
+        Ok(())
+    }
+
+    /// Sets the atlas cell(x, y) a structure is drawn with. Animated structures have
+    /// `frames` frames in the cells to the right of it, shown for `frame_duration` ticks each.
+    /// A structure with 0 frames is not drawn.
+    ///
+    /// _Example(python)_:
+    /// ```python
+    /// world.set_structure_overlay(42, (20, 1), frames=4, frame_duration=150.0)  # windmill
+    /// ```
+    #[pyo3(signature = (structure, cell, frames=1, frame_duration=0.0))]
+    pub fn set_structure_overlay(&mut self, structure: u32, cell: [u32; 2], frames: u32, frame_duration: f32) -> PyResult<()> {
+        let entry = OverlayEntry::new(cell, frames, frame_duration);
+        if !self.overlays.set_structure(structure, entry) {
+            return Err(PyValueError::new_err(format!(
+                "Structure ids go from 1 to {}, got {}",
+                MAX_STRUCTURES - 1,
+                structure
+            )));
+        }
+        Ok(())
+    }
+
+    /// Sets the atlas cell(x, y) of the worker, troops or highlight overlays, animated like
+    /// structures are(see `set_structure_overlay`).
+    #[pyo3(signature = (overlay, cell, frames=1, frame_duration=0.0))]
+    pub fn set_overlay(&mut self, overlay: Overlay, cell: [u32; 2], frames: u32, frame_duration: f32) {
+        self.overlays.set_overlay(overlay, OverlayEntry::new(cell, frames, frame_duration));
+    }
+
+    /// Returns the atlas cell, frame count and frame duration of a structure.
+    pub fn get_structure_overlay(&self, structure: u32) -> Option<([u32; 2], u32, f32)> {
+        let entry = self.overlays.structure(structure)?;
+        Some((entry.cell, entry.frames, entry.frame_duration))
+    }
+
+    /// Returns the atlas cell, frame count and frame duration of an overlay.
+    pub fn get_overlay(&self, overlay: Overlay) -> ([u32; 2], u32, f32) {
+        let entry = self.overlays.overlay(overlay);
+        (entry.cell, entry.frames, entry.frame_duration)
+    }
+
+    /// Sets the atlas cell(x, y) a ground texture is drawn with, animated like structures
+    /// are(see `set_structure_overlay`). Textures are the cells of the top row by default,
+    /// from 4 on the textures being drawn with the cell of texture 4.
+    #[pyo3(signature = (texture, cell, frames=1, frame_duration=0.0))]
+    pub fn set_texture_cell(
+        &mut self,
+        texture: u32,
+        cell: [u32; 2],
+        frames: u32,
+        frame_duration: f32,
+    ) -> PyResult<()> {
+        let entry = OverlayEntry::new(cell, frames, frame_duration);
+        if !self.overlays.set_texture(texture, entry) {
+            return Err(texture_error(texture));
+        }
+        self.mark_all_changed();
+        Ok(())
+    }
+
+    /// Returns the atlas cell, frame count and frame duration of a ground texture.
+    pub fn get_texture_cell(&self, texture: u32) -> Option<([u32; 2], u32, f32)> {
+        let entry = self.overlays.texture_entry(texture)?;
+        Some((entry.cell, entry.frames, entry.frame_duration))
+    }
+
+    /// Adds an animation to the map, returns the id textures, structures and overlays
+    /// reference it with. The animation starts at the current `ticks`.
+    pub fn add_animation(&mut self, animation: Animation) -> PyResult<u32> {
+        self.animations.add(animation, self.ticks).ok_or_else(|| {
+            PyRuntimeError::new_err(format!("The map already has {} animations", MAX_ANIMATIONS))
+        })
+    }
+
+    /// Replaces the animation of the given id, everything drawn with it changes too.
+    pub fn set_animation(&mut self, animation_id: u32, animation: Animation) -> PyResult<()> {
+        if !self.animations.set(animation_id, animation) {
+            return Err(animation_error(animation_id));
+        }
+        // The minimaps color animated ground textures with their first frame.
+        self.mark_all_changed();
+        Ok(())
+    }
+
+    pub fn get_animation(&self, animation_id: u32) -> Option<Animation> {
+        self.animations.get(animation_id).cloned()
+    }
+
+    /// Removes an animation, what is drawn with it is not drawn anymore.
+    pub fn remove_animation(&mut self, animation_id: u32) -> PyResult<()> {
+        if !self.animations.remove(animation_id) {
+            return Err(animation_error(animation_id));
+        }
+        self.mark_all_changed();
+        Ok(())
+    }
+
+    /// Starts an animation again from its first frame, like a `LoopMode.Once` explosion.
+    pub fn restart_animation(&mut self, animation_id: u32) -> PyResult<()> {
+        if !self.animations.restart(animation_id, self.ticks) {
+            return Err(animation_error(animation_id));
+        }
+        Ok(())
+    }
+
+    /// Draws a ground texture with an animation.
+    pub fn set_texture_animation(&mut self, texture: u32, animation_id: u32) -> PyResult<()> {
+        self.animations
+            .get(animation_id)
+            .ok_or_else(|| animation_error(animation_id))?;
+        if !self
+            .overlays
+            .set_texture(texture, OverlayEntry::animated(animation_id))
+        {
+            return Err(texture_error(texture));
+        }
+        self.mark_all_changed();
+        Ok(())
+    }
+
+    /// Draws a structure with an animation.
+    pub fn set_structure_animation(&mut self, structure: u32, animation_id: u32) -> PyResult<()> {
+        self.animations
+            .get(animation_id)
+            .ok_or_else(|| animation_error(animation_id))?;
+        if !self
+            .overlays
+            .set_structure(structure, OverlayEntry::animated(animation_id))
+        {
+            return Err(PyValueError::new_err(format!(
+                "Structure ids go from 1 to {}, got {}",
+                MAX_STRUCTURES - 1,
+                structure
+            )));
+        }
+        Ok(())
+    }
+
+    /// Draws the worker, troops or highlight overlay with an animation.
+    pub fn set_overlay_animation(&mut self, overlay: Overlay, animation_id: u32) -> PyResult<()> {
+        self.animations
+            .get(animation_id)
+            .ok_or_else(|| animation_error(animation_id))?;
+        self.overlays
+            .set_overlay(overlay, OverlayEntry::animated(animation_id));
+        Ok(())
+    }
+
+    /// Returns the id of the animation a structure is drawn with, if any.
+    pub fn get_structure_animation(&self, structure: u32) -> Option<u32> {
+        self.overlays.structure(structure)?.animation
+    }
+
+    /// Returns the id of the animation an overlay is drawn with, if any.
+    pub fn get_overlay_animation(&self, overlay: Overlay) -> Option<u32> {
+        self.overlays.overlay(overlay).animation
+    }
+
+    /// Returns the id of the animation a ground texture is drawn with, if any.
+    pub fn get_texture_animation(&self, texture: u32) -> Option<u32> {
+        self.overlays.texture_entry(texture)?.animation
+    }
+
+    /// Draws the chunks in view with an instanced draw call each, uploading their tiles
+    /// which changed since they were last drawn first.
+    pub fn draw(&mut self, py: Python<'_>) {
+        let ctx = get_tctx();
+        let gl = ctx.get_gl();
+        let program = ctx.get_shader_program(String::from(MAP_SHADER));
+
+        let (width, height) = ctx.get_window().size();
+        let (camera_x, camera_y) = self.camera;
+        let projection = orthographic_projection(
+            camera_x,
+            camera_x + width as f32,
+            camera_y,
+            camera_y + height as f32,
+        );
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_draw).as_secs_f32() * 1000.0;
+        self.ticks += elapsed * self.animation_speed;
+        self.last_draw = now;
+
+        ctx.apply_blend_mode(&gl, program);
+
+        unsafe {
+            self.overlays.upload(&gl);
+            self.animations.upload(&gl);
+            self.fog.upload(&gl);
+
+            gl.use_program(Some(program));
+
+            let projection_location = gl.get_uniform_location(program, "projection");
+            gl.uniform_matrix_4_f32_slice(projection_location.as_ref(), true, &projection);
+            let screen_location = gl.get_uniform_location(program, "screenDimensions");
+            gl.uniform_2_f32(screen_location.as_ref(), width as f32, height as f32);
+            let camera_location = gl.get_uniform_location(program, "cameraOffset");
+            gl.uniform_2_f32(camera_location.as_ref(), camera_x, camera_y);
+            let atlas_size_location = gl.get_uniform_location(program, "textureAtlasSize");
+            gl.uniform_2_f32(atlas_size_location.as_ref(), self.atlas_size[0], self.atlas_size[1]);
+
+            self.day_night.borrow(py).lighting().set_uniforms(&gl, program);
+
+            let uniforms = [
+                ("panningRotation", self.rotation),
+                ("textureSize", self.geometry.tile_size),
+                ("ticks", self.ticks),
+            ];
+            for (name, value) in uniforms {
+                let location = gl.get_uniform_location(program, name);
+                gl.uniform_1_f32(location.as_ref(), value);
+            }
+
+            gl.active_texture(TEXTURE0);
+            gl.bind_texture(TEXTURE_2D, Some(self.atlas));
+            let atlas_location = gl.get_uniform_location(program, "textureatlas");
+            gl.uniform_1_i32(atlas_location.as_ref(), 0);
+
+            gl.active_texture(TEXTURE1);
+            gl.bind_texture(TEXTURE_2D, Some(self.overlays.texture()));
+            let table_location = gl.get_uniform_location(program, "overlayTable");
+            gl.uniform_1_i32(table_location.as_ref(), 1);
+            let table_width_location = gl.get_uniform_location(program, "overlayTableWidth");
+            gl.uniform_1_i32(table_width_location.as_ref(), MAX_STRUCTURES as i32);
+
+            gl.active_texture(TEXTURE3);
+            gl.bind_texture(TEXTURE_2D, Some(self.animations.texture()));
+            let animations_location = gl.get_uniform_location(program, "animationTable");
+            gl.uniform_1_i32(animations_location.as_ref(), 3);
+
+            gl.active_texture(TEXTURE2);
+            gl.bind_texture(TEXTURE_2D, Some(self.fog.texture()));
+            let fog_location = gl.get_uniform_location(program, "fogTexture");
+            gl.uniform_1_i32(fog_location.as_ref(), 2);
+            let fog_enabled_location = gl.get_uniform_location(program, "fogEnabled");
+            gl.uniform_1_i32(fog_enabled_location.as_ref(), self.fog_enabled as i32);
+            let map_size_location = gl.get_uniform_location(program, "mapSize");
+            gl.uniform_2_f32(map_size_location.as_ref(), self.width as f32, self.height as f32);
+            let brightness_location = gl.get_uniform_location(program, "fogExploredBrightness");
+            gl.uniform_1_f32(brightness_location.as_ref(), self.fog_explored_brightness);
+            let saturation_location = gl.get_uniform_location(program, "fogExploredSaturation");
+            gl.uniform_1_f32(saturation_location.as_ref(), self.fog_explored_saturation);
+
+            let quad_size = self.geometry.quad_size();
+            let dimensions_location = gl.get_uniform_location(program, "tileDimensions");
+            gl.uniform_2_f32(dimensions_location.as_ref(), quad_size.0, quad_size.1);
+            let shape_location = gl.get_uniform_location(program, "tileShape");
+            gl.uniform_1_i32(shape_location.as_ref(), self.geometry.shape() as i32);
+
+            let margin = self.cull_margin;
+            let view = [
+                camera_x - margin,
+                camera_y - margin,
+                camera_x + width as f32 + margin,
+                camera_y + height as f32 + margin,
+            ];
+            let center = (width as f32 * 0.5, height as f32 * 0.5);
+            self.visible_chunks = 0;
+            for index in self.chunk_order.iter() {
+                let chunk = &mut self.chunks[*index];
+                if !is_visible(chunk.offset_bounds(), view, self.rotation, center, quad_size) {
+                    continue;
+                }
+                chunk.upload(&gl);
+                chunk.draw(&gl);
+                self.visible_chunks += 1;
+            }
+
+            gl.bind_vertex_array(None);
+            for unit in [TEXTURE3, TEXTURE2, TEXTURE1, TEXTURE0] {
+                gl.active_texture(unit);
+                gl.bind_texture(TEXTURE_2D, None);
+            }
+
+            // Switch back to the default shader program for the other draw functions.
+            gl.use_program(Some(ctx.get_glob_glprogam()));
+        }
+    }
+}
+
+impl TileMap {
+    /// Sets the float at `field_index`(see `field_index`) of a tile's instance.
+    pub fn set_field_value(&mut self, x: u32, y: u32, field_index: usize, value: f32) -> PyResult<()> {
+        let (chunk, index) = self.tile_index(x, y)?;
+        let chunk = &mut self.chunks[chunk];
+        let instance_values: &mut [f32] = bytemuck::cast_slice_mut(std::slice::from_mut(&mut chunk.instances[index]));
+        instance_values[field_index] = value;
+        chunk.mark(index);
+        self.mark_changed((y * self.width + x) as usize);
+        Ok(())
+    }
+
+    /// Sets the structure of the tile at (x, y) to the variant of an autotiling set for its
+    /// neighbours.
+    fn autotile(&mut self, x: u32, y: u32, rules: &AutotileRules) -> PyResult<u32> {
+        let mask = rules.mask(x as i64, y as i64, |x, y| self.structure_at(x, y));
+        let structure = rules.resolve(mask).ok_or_else(|| {
+            PyValueError::new_err(format!(
+                "The autotiling rules have no variant for mask {}",
+                mask
+            ))
+        })?;
+        if self.instance(x, y)?.structure as u32 != structure {
+            self.set_field_value(x, y, field_index("structure")?, structure as f32)?;
+        }
+        Ok(structure)
+    }
+
+    /// Updates the neighbours of (x, y) which are tiles of an autotiling set.
+    fn autotile_neighbours(&mut self, x: u32, y: u32, rules: &AutotileRules) -> PyResult<()> {
+        for (dx, dy, _) in rules.neighbours() {
+            let (x, y) = (x as i64 + dx, y as i64 + dy);
+            if let Some(structure) = self.structure_at(x, y)
+                && rules.is_variant(structure)
+            {
+                self.autotile(x as u32, y as u32, rules)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the structure of the tile at (x, y), None outside of the map.
+    fn structure_at(&self, x: i64, y: i64) -> Option<u32> {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return None;
+        }
+        let instance = self.instance(x as u32, y as u32).ok()?;
+        Some(instance.structure as u32)
+    }
+
+    /// Returns the instance of the tile at the given column(x) and row(y).
+    pub fn instance(&self, x: u32, y: u32) -> PyResult<&TileInstance> {
+        let (chunk, index) = self.tile_index(x, y)?;
+        Ok(&self.chunks[chunk].instances[index])
+    }
+
+    /// Returns the fog of war state of the tile at the given index(row after row), visible if
+    /// the fog is not drawn.
+    pub fn visibility_at(&self, index: usize) -> Visibility {
+        if self.fog_enabled {
+            self.fog.get(index)
+        } else {
+            Visibility::Visible
+        }
+    }
+
+    pub fn atlas_path(&self) -> &str {
+        &self.atlas_path
+    }
+
+    /// Returns the atlas cell a ground texture is drawn with at the start of its frames or
+    /// animation, None if it is not drawn. Textures past the table are drawn like the last one.
+    pub fn ground_cell(&self, texture: u32) -> Option<[u32; 2]> {
+        let entry = self.overlays.texture_entry(texture.min(MAX_TEXTURES as u32 - 1))?;
+        match entry.animation {
+            Some(animation) => self.animations.get(animation).map(|animation| animation.cell_at(0.0)),
+            None => (entry.frames > 0).then_some(entry.cell),
+        }
+    }
+
+    pub fn is_fog_enabled(&self) -> bool {
+        self.fog_enabled
+    }
+
+    /// Returns the changes a new minimap takes the changed tiles from, every tile being
+    /// changed at first. Tiles are only recorded for the minimaps still holding them.
+    pub fn attach_minimap(&mut self) -> Rc<RefCell<DirtyRanges>> {
+        let mut changes = DirtyRanges::default();
+        changes.mark_range(0..(self.width * self.height) as usize);
+        let changes = Rc::new(RefCell::new(changes));
+        self.minimap_changes.push(Rc::downgrade(&changes));
+        changes
+    }
+
+    /// Returns whether the given changes are those of a minimap attached to this map.
+    pub fn is_minimap_attached(&self, changes: &Rc<RefCell<DirtyRanges>>) -> bool {
+        let changes = Rc::downgrade(changes);
+        self.minimap_changes.iter().any(|attached| attached.ptr_eq(&changes))
+    }
+
+    /// Marks all the tiles as changed for the minimaps.
+    fn mark_all_changed(&mut self) {
+        self.mark_changed_range(0..(self.width * self.height) as usize);
+    }
+
+    /// Marks a tile(its index row after row) as changed for the minimaps.
+    fn mark_changed(&mut self, index: usize) {
+        self.mark_changed_range(index..index + 1);
+    }
+
+    fn mark_changed_range(&mut self, range: Range<usize>) {
+        self.minimap_changes.retain(|changes| match changes.upgrade() {
+            Some(changes) => {
+                changes.borrow_mut().mark_range(range.clone());
+                true
+            }
+            None => false,
+        });
+    }
+
+    /// Returns the index of the chunk with the tile at the given column(x) and row(y), and
+    /// the tile's index in the chunk's instances.
+    fn tile_index(&self, x: u32, y: u32) -> PyResult<(usize, usize)> {
+        if x >= self.width || y >= self.height {
+            return Err(PyIndexError::new_err(format!(
+                "Tile ({}, {}) is outside of the {}x{} map",
+                x, y, self.width, self.height
+            )));
+        }
+        let chunk = ((y / self.chunk_size) * self.chunk_columns + x / self.chunk_size) as usize;
+        Ok((chunk, self.chunks[chunk].local_index(x, y)))
+    }
+
+    /// Rotates a tile offset around the center of the screen by `rotation` degrees, like
+    /// map_vert.glsl does.
+    fn rotate_offset(&self, offset: (f32, f32), center: (f32, f32), rotation: f32) -> (f32, f32) {
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let (dx, dy) = (offset.0 - center.0, offset.1 - center.1);
+        // The shader's mat2(c, -s, s, c) is column major.
+        (
+            cos * dx + sin * dy + center.0,
+            -sin * dx + cos * dy + center.1,
+        )
+    }
+
+    /// Returns the index of the tile at the given column(x) and row(y) in a row after row grid.
+    fn grid_index(&self, x: u32, y: u32) -> PyResult<usize> {
+        self.tile_index(x, y)?;
+        Ok((y * self.width + x) as usize)
+    }
+
+    /// Returns whether there is a tile at the given column and row.
+    fn contains(&self, x: i64, y: i64) -> bool {
+        (0..self.width as i64).contains(&x) && (0..self.height as i64).contains(&y)
+    }
+}
+
+impl Drop for TileMap {
+    fn drop(&mut self) {
+        let _ = T_CONTEXT.try_with(|context| {
+            if let Some(ctx) = context.get() {
+                let gl = ctx.get_gl();
+                for chunk in self.chunks.iter() {
+                    chunk.delete(&gl);
+                }
+                unsafe {
+                    gl.delete_buffer(self.quad_vbo);
+                    gl.delete_texture(self.atlas);
+                }
+                self.overlays.delete(&gl);
+                self.animations.delete(&gl);
+                self.fog.delete(&gl);
+            }
+        });
+    }
+}
+
+fn animation_error(animation_id: u32) -> PyErr {
+    PyKeyError::new_err(format!("The map has no animation {}", animation_id))
+}
+
+fn texture_error(texture: u32) -> PyErr {
+    PyValueError::new_err(format!(
+        "Textures go from 0 to {}, got {}",
+        MAX_TEXTURES - 1,
+        texture
+    ))
+}
+
+/// Returns the index in a tile's instance(as floats) of the field with the given name.
+pub fn field_index(name: &str) -> PyResult<usize> {
+    FIELDS
+        .iter()
+        .find(|(field, _)| *field == name)
+        .map(|(_, index)| *index)
+        .ok_or_else(|| {
+            let names: Vec<&str> = FIELDS.iter().map(|(field, _)| *field).collect();
+            PyValueError::new_err(format!("Unknown tile field '{}', the fields are {}", name, names.join(", ")))
+        })
+}
+
+/// Returns the center of the window, which the map shader rotates the map around.
+fn screen_center() -> (f32, f32) {
+    let (width, height) = get_tctx().get_window().size();
+    (width as f32 * 0.5, height as f32 * 0.5)
+}
+
+/// Returns the orthographic projection(row major) which maps the given area to the screen,
+/// with y going down.
+pub fn orthographic_projection(left: f32, right: f32, top: f32, bottom: f32) -> [f32; 16] {
+    #[rustfmt::skip]
+    let projection = [
+        2.0 / (right - left), 0.0, 0.0, -(right + left) / (right - left),
+        0.0, 2.0 / (top - bottom), 0.0, -(top + bottom) / (top - bottom),
+        0.0, 0.0, -1.0, 0.0,
+        0.0, 0.0, 0.0, 1.0,
+    ];
+    projection
+}
@@ -0,0 +1,378 @@
+//! # Minimap
+//!
+//! A small overview of a tile map, as a texture with a texel per tile which can be drawn
+//! anywhere on the screen.
+//!
+//! A tile's color is the color of its ground texture in the minimap's palette, or the
+//! average color of the atlas cell the map draws the texture with(the first frame of
+//! animated textures). Structures and units(workers, troops) are
+//! drawn over it with a color of their own, and unexplored or explored tiles like the fog of
+//! war draws them. The minimap only recolors the tiles which changed since its last update.
+//!
+//! The texture follows the columns and rows of the map, so isometric and hexagonal maps
+//! show as a rectangle too.
+
+extern crate glow; // OpenGL bindings
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use glow::*;
+use pyo3::exceptions::{PyIOError, PyRuntimeError};
+use pyo3::prelude::*;
+
+use crate::T_CONTEXT;
+use crate::engine::gltext::text::draw_quads_with;
+use crate::engine::gltext::textures::TEXTURE_SHADER;
+use crate::engine::helpers::get_tctx;
+use crate::engine::tilemap::dirty::DirtyRanges;
+use crate::engine::tilemap::fog::Visibility;
+use crate::engine::tilemap::map::TileMap;
+
+/// Color of the tiles whose texture has no color.
+const UNKNOWN_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+/// An overview of a tile map.
+///
+/// _Example(python)_:
+/// ```python
+/// minimap = tge.tilemap.Minimap(world)
+/// minimap.set_color(3, [0.1, 0.3, 0.8, 1.0])  # water
+///
+/// minimap.update(world)
+/// minimap.draw(1060.0, 500.0, 200.0, 200.0)
+///
+/// if clicked:
+///     target = minimap.click_to_world(world, mouse_x, mouse_y)
+///     if target is not None:
+///         world.camera = (target[0] - 640.0, target[1] - 360.0)
+/// ```
+#[pyclass(unsendable)]
+pub struct Minimap {
+    width: u32,
+    height: u32,
+    texture: NativeTexture,
+    /// Colors of the ground textures set from python, by texture index.
+    palette: HashMap<u32, [f32; 4]>,
+    /// Average colors of the atlas cells, row after row.
+    atlas_colors: Vec<[f32; 4]>,
+    /// Number of cells in a row of the atlas.
+    atlas_columns: u32,
+    /// The tiles which changed since the last update, marked by the map the minimap was
+    /// created for.
+    changes: Rc<RefCell<DirtyRanges>>,
+    /// Whether every tile must be recolored at the next update.
+    outdated: bool,
+    /// Whether the fog of war of the map was drawn at the last update.
+    fog_enabled: bool,
+    /// The area(x, y, width, height) the minimap was last drawn at.
+    drawn_at: Option<[f32; 4]>,
+    /// Color of the tiles with a structure, not drawn if its alpha is 0.
+    #[pyo3(get, set)]
+    structure_color: [f32; 4],
+    /// Color of the tiles with a worker or troops, not drawn if its alpha is 0.
+    #[pyo3(get, set)]
+    unit_color: [f32; 4],
+    /// Brightness(from 0.0 to 1.0) of the explored tiles which are not visible.
+    #[pyo3(get, set)]
+    explored_brightness: f32,
+}
+
+#[pymethods]
+impl Minimap {
+    /// Creates the minimap of a tile map, with the average colors of its atlas.
+    #[new]
+    pub fn new(mut tilemap: PyRefMut<'_, TileMap>) -> PyResult<Self> {
+        let (atlas_columns, atlas_colors) =
+            atlas_colors(tilemap.atlas_path(), tilemap.tile_size() as u32)
+                .map_err(PyIOError::new_err)?;
+        let (width, height) = (tilemap.width(), tilemap.height());
+
+        let gl = get_tctx().get_gl();
+        let texture = unsafe {
+            let texture = gl.create_texture().map_err(|e| {
+                PyRuntimeError::new_err(format!("Failed to create minimap texture: {}", e))
+            })?;
+            gl.bind_texture(TEXTURE_2D, Some(texture));
+            // Tiles stay sharp squares however big the minimap is drawn.
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MIN_FILTER, LINEAR as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MAG_FILTER, NEAREST as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_WRAP_S, CLAMP_TO_EDGE as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_WRAP_T, CLAMP_TO_EDGE as i32);
+            gl.tex_image_2d(
+                TEXTURE_2D,
+                0,
+                RGBA8 as i32,
+                width as i32,
+                height as i32,
+                0,
+                RGBA,
+                UNSIGNED_BYTE,
+                PixelUnpackData::Slice(None),
+            );
+            gl.bind_texture(TEXTURE_2D, None);
+            texture
+        };
+
+        Ok(Minimap {
+            width,
+            height,
+            texture,
+            palette: HashMap::new(),
+            atlas_colors,
+            atlas_columns,
+            changes: tilemap.attach_minimap(),
+            outdated: true,
+            fog_enabled: false,
+            drawn_at: None,
+            structure_color: [0.9, 0.9, 0.9, 1.0],
+            unit_color: [0.9, 0.1, 0.1, 1.0],
+            explored_brightness: 0.5,
+        })
+    }
+
+    /// Sets the color of the tiles with the given ground texture, instead of the average
+    /// color of its atlas cell.
+    pub fn set_color(&mut self, texture: u32, color: [f32; 4]) {
+        self.palette.insert(texture, color);
+        self.outdated = true;
+    }
+
+    /// Goes back to the average color of the atlas cell for the given ground texture.
+    pub fn reset_color(&mut self, texture: u32) {
+        self.palette.remove(&texture);
+        self.outdated = true;
+    }
+
+    /// Returns the color the tiles with the given ground texture are drawn with.
+    pub fn get_color(&self, tilemap: PyRef<'_, TileMap>, texture: u32) -> [f32; 4] {
+        self.texture_color(&tilemap, texture)
+    }
+
+    /// Recolors every tile at the next update, after the overlay colors were changed.
+    pub fn invalidate(&mut self) {
+        self.outdated = true;
+    }
+
+    /// Recolors the tiles of the map which changed since the last update.
+    pub fn update(&mut self, tilemap: PyRef<'_, TileMap>) -> PyResult<()> {
+        if !tilemap.is_minimap_attached(&self.changes) {
+            return Err(PyRuntimeError::new_err(
+                "The minimap was created for another map, create one for this map instead",
+            ));
+        }
+        let fog_enabled = tilemap.is_fog_enabled();
+        if self.outdated || fog_enabled != self.fog_enabled {
+            self.changes
+                .borrow_mut()
+                .mark_range(0..(self.width * self.height) as usize);
+        }
+        self.outdated = false;
+        self.fog_enabled = fog_enabled;
+        // Rows of a range are recolored whole, so ranges less than a row apart are merged.
+        let changes = self.changes.borrow_mut().take(self.width as usize);
+
+        let gl = get_tctx().get_gl();
+        for range in changes {
+            let first_row = range.start as u32 / self.width;
+            let last_row = (range.end as u32 - 1) / self.width;
+            let mut texels: Vec<u8> =
+                Vec::with_capacity(((last_row - first_row + 1) * self.width * 4) as usize);
+            for y in first_row..=last_row {
+                for x in 0..self.width {
+                    let color = self.tile_color(&tilemap, x, y)?;
+                    texels.extend(
+                        color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8),
+                    );
+                }
+            }
+            unsafe {
+                gl.bind_texture(TEXTURE_2D, Some(self.texture));
+                gl.tex_sub_image_2d(
+                    TEXTURE_2D,
+                    0,
+                    0,
+                    first_row as i32,
+                    self.width as i32,
+                    (last_row - first_row + 1) as i32,
+                    RGBA,
+                    UNSIGNED_BYTE,
+                    PixelUnpackData::Slice(Some(&texels)),
+                );
+                gl.bind_texture(TEXTURE_2D, None);
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the minimap in the given area of the screen(in pixels).
+    #[pyo3(signature = (x, y, width, height, alpha=1.0))]
+    pub fn draw(&mut self, x: f32, y: f32, width: f32, height: f32, alpha: f32) {
+        let ctx = get_tctx();
+        let gl = ctx.get_gl();
+        let (right, bottom) = (x + width, y + height);
+        #[rustfmt::skip]
+        let vertices = [
+            x, y, 0.0, 0.0,
+            right, y, 1.0, 0.0,
+            x, bottom, 0.0, 1.0,
+            right, y, 1.0, 0.0,
+            right, bottom, 1.0, 1.0,
+            x, bottom, 0.0, 1.0,
+        ];
+        draw_quads_with(
+            &ctx,
+            &gl,
+            TEXTURE_SHADER,
+            self.texture,
+            "image",
+            &vertices,
+            |gl, program| unsafe {
+                let color_location = gl.get_uniform_location(program, "color");
+                gl.uniform_4_f32(color_location.as_ref(), 1.0, 1.0, 1.0, alpha);
+            },
+        );
+        self.drawn_at = Some([x, y, width, height]);
+    }
+
+    /// Returns the column and row of the tile at a point of the screen(like a click) in the
+    /// minimap where it was last drawn, None if the point is outside of it.
+    pub fn click_to_tile(&self, x: f32, y: f32) -> Option<(u32, u32)> {
+        let [left, top, width, height] = self.drawn_at?;
+        let (u, v) = ((x - left) / width, (y - top) / height);
+        if !(0.0..1.0).contains(&u) || !(0.0..1.0).contains(&v) {
+            return None;
+        }
+        Some((
+            (u * self.width as f32) as u32,
+            (v * self.height as f32) as u32,
+        ))
+    }
+
+    /// Returns the center(in pixels of the map) of the tile at a point of the screen in the
+    /// minimap, to move the camera to it. None if the point is outside of the minimap.
+    pub fn click_to_world(
+        &self,
+        tilemap: PyRef<'_, TileMap>,
+        x: f32,
+        y: f32,
+    ) -> Option<(f32, f32)> {
+        let (column, row) = self.click_to_tile(x, y)?;
+        Some(tilemap.grid_to_world(column as i64, row as i64))
+    }
+
+    /// Returns the point of the screen where a tile is in the minimap where it was last drawn.
+    pub fn tile_to_click(&self, x: u32, y: u32) -> Option<(f32, f32)> {
+        let [left, top, width, height] = self.drawn_at?;
+        Some((
+            left + (x as f32 + 0.5) / self.width as f32 * width,
+            top + (y as f32 + 0.5) / self.height as f32 * height,
+        ))
+    }
+}
+
+impl Minimap {
+    fn texture_color(&self, tilemap: &TileMap, texture: u32) -> [f32; 4] {
+        if let Some(color) = self.palette.get(&texture) {
+            return *color;
+        }
+        tilemap
+            .ground_cell(texture)
+            .filter(|[x, _]| *x < self.atlas_columns)
+            .and_then(|[x, y]| self.atlas_colors.get((y * self.atlas_columns + x) as usize))
+            .copied()
+            .unwrap_or(UNKNOWN_COLOR)
+    }
+
+    fn tile_color(&self, tilemap: &TileMap, x: u32, y: u32) -> PyResult<[f32; 4]> {
+        let instance = tilemap.instance(x, y)?;
+        let mut color = self.texture_color(tilemap, instance.texture as u32);
+
+        let overlays = [
+            (instance.structure > 0.0, self.structure_color),
+            (
+                instance.worker > 0.5 || instance.troops > 0.5,
+                self.unit_color,
+            ),
+        ];
+        for (shown, overlay) in overlays {
+            if shown {
+                color = blend(color, overlay);
+            }
+        }
+
+        let visibility = tilemap.visibility_at((y * self.width + x) as usize);
+        Ok(match visibility {
+            Visibility::Unexplored => [0.0, 0.0, 0.0, color[3]],
+            Visibility::Explored => {
+                // Gray and dimmed, like the fog of war draws explored tiles by default.
+                let gray = (color[0] * 0.299 + color[1] * 0.587 + color[2] * 0.114)
+                    * self.explored_brightness;
+                [gray, gray, gray, color[3]]
+            }
+            Visibility::Visible => color,
+        })
+    }
+}
+
+impl Drop for Minimap {
+    fn drop(&mut self) {
+        let _ = T_CONTEXT.try_with(|context| {
+            if let Some(ctx) = context.get() {
+                unsafe {
+                    ctx.get_gl().delete_texture(self.texture);
+                }
+            }
+        });
+    }
+}
+
+/// Blends a color over another by its alpha.
+fn blend(base: [f32; 4], over: [f32; 4]) -> [f32; 4] {
+    let alpha = over[3];
+    [
+        base[0] + (over[0] - base[0]) * alpha,
+        base[1] + (over[1] - base[1]) * alpha,
+        base[2] + (over[2] - base[2]) * alpha,
+        base[3],
+    ]
+}
+
+/// Returns the number of columns of the atlas and the average colors of its tile_size x
+/// tile_size cells, row after row. Transparent pixels count less.
+fn atlas_colors(atlas_path: &str, tile_size: u32) -> Result<(u32, Vec<[f32; 4]>), String> {
+    let atlas = image::open(atlas_path)
+        .map_err(|e| format!("Failed to load atlas {}: {}", atlas_path, e))?
+        .to_rgba8();
+    let tile_size = tile_size.max(1).min(atlas.height());
+    let (columns, rows) = (atlas.width() / tile_size, atlas.height() / tile_size);
+
+    let colors = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .map(|(column, row)| {
+            let mut sum = [0.0f64; 4];
+            for y in row * tile_size..(row + 1) * tile_size {
+                for x in column * tile_size..(column + 1) * tile_size {
+                    let [r, g, b, a] = atlas.get_pixel(x, y).0;
+                    let alpha = a as f64 / 255.0;
+                    sum[0] += r as f64 / 255.0 * alpha;
+                    sum[1] += g as f64 / 255.0 * alpha;
+                    sum[2] += b as f64 / 255.0 * alpha;
+                    sum[3] += alpha;
+                }
+            }
+            if sum[3] == 0.0 {
+                return [0.0; 4];
+            }
+            let pixels = (tile_size * tile_size) as f64;
+            [
+                (sum[0] / sum[3]) as f32,
+                (sum[1] / sum[3]) as f32,
+                (sum[2] / sum[3]) as f32,
+                (sum[3] / pixels) as f32,
+            ]
+        })
+        .collect();
+    Ok((columns, colors))
+}
@@ -0,0 +1,40 @@
+use pyo3::prelude::*;
+use pyo3::wrap_pyfunction;
+
+pub mod animations;
+pub mod autotile;
+pub mod chunks;
+pub mod daynight;
+pub mod dirty;
+pub mod fog;
+pub mod layout;
+pub mod map;
+pub mod minimap;
+pub mod overlays;
+pub mod sight;
+pub mod tiled;
+
+/// Declaration of the tilemap submodule for the parent python module.
+/// Reference: https://pyo3.rs/v0.23.4/module.html
+pub fn register_tilemap_module(parent_module: &Bound<'_, PyModule>) -> PyResult<()> {
+    let child_module = PyModule::new(parent_module.py(), "tilemap")?;
+    child_module.add_class::<map::Tile>()?;
+    child_module.add_class::<map::TileMap>()?;
+    child_module.add_class::<layout::GridLayout>()?;
+    child_module.add_class::<fog::Visibility>()?;
+    child_module.add_class::<sight::SightGrid>()?;
+    child_module.add_class::<autotile::Connectivity>()?;
+    child_module.add_class::<autotile::AutotileRules>()?;
+    child_module.add_class::<minimap::Minimap>()?;
+    child_module.add_class::<overlays::Overlay>()?;
+    child_module.add_class::<animations::Animation>()?;
+    child_module.add_class::<animations::LoopMode>()?;
+    child_module.add_class::<daynight::DayNightCycle>()?;
+    child_module.add_class::<tiled::TiledMap>()?;
+    child_module.add_class::<tiled::TiledTileset>()?;
+    child_module.add_class::<tiled::TiledTileLayer>()?;
+    child_module.add_class::<tiled::TiledObjectLayer>()?;
+    child_module.add_class::<tiled::TiledObject>()?;
+    child_module.add_function(wrap_pyfunction!(tiled::load_tiled_map, &child_module)?)?;
+    parent_module.add_submodule(&child_module)
+}
@@ -0,0 +1,250 @@
+//! # Overlays
+//!
+//! The lookup table telling the map shader which atlas cells the ground texture and the
+//! overlays of a tile(its structure, worker, troops and highlights) are drawn with.
+//!
+//! The table is a small float texture the map shader reads with texelFetch. Row 0 has an
+//! entry for every structure id, row 1 one for every other `Overlay` and row 2 one for every
+//! ground texture. Every entry is the atlas cell(x, y) of the first frame, the number of
+//! frames and how many ticks a frame lasts, frames being the cells to the right of the first
+//! one. Entries drawn with an animation(see the animations module) are (animation id, -1,
+//! 1, 0) instead.
+
+extern crate glow; // OpenGL bindings
+
+use glow::*;
+use pyo3::prelude::*;
+
+/// Number of structure ids in the table, structure 0 is no structure.
+pub const MAX_STRUCTURES: usize = 256;
+/// Number of ground textures in the table, whose rows are as wide as the structures' one.
+pub const MAX_TEXTURES: usize = MAX_STRUCTURES;
+/// Number of rows of the table.
+const ROWS: usize = 3;
+
+/// The overlays of a tile other than its structure.
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overlay {
+    Worker,
+    Troops,
+    /// Drawn over the selected tile.
+    Selected,
+    /// Drawn over highlighted tiles which are not selected.
+    Highlight,
+    /// Drawn over tiles with a resource when resources are highlighted.
+    ResourceHighlight,
+}
+
+/// Where an overlay is in the atlas and how it is animated.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OverlayEntry {
+    pub cell: [u32; 2],
+    /// Number of frames, 0 for an overlay which is not drawn.
+    pub frames: u32,
+    /// Ticks every frame is shown for.
+    pub frame_duration: f32,
+    /// The animation drawn instead of the frames, if any.
+    pub animation: Option<u32>,
+}
+
+impl OverlayEntry {
+    pub fn new(cell: [u32; 2], frames: u32, frame_duration: f32) -> Self {
+        OverlayEntry {
+            cell,
+            frames,
+            frame_duration,
+            animation: None,
+        }
+    }
+
+    /// An entry drawn with the animation of the given id.
+    pub fn animated(animation: u32) -> Self {
+        OverlayEntry {
+            frames: 1,
+            animation: Some(animation),
+            ..OverlayEntry::default()
+        }
+    }
+
+    /// Returns the entry's texel in the table.
+    fn texel(&self) -> [f32; 4] {
+        match self.animation {
+            Some(animation) => [animation as f32, -1.0, 1.0, 0.0],
+            None => [
+                self.cell[0] as f32,
+                self.cell[1] as f32,
+                self.frames as f32,
+                self.frame_duration,
+            ],
+        }
+    }
+
+    fn still(x: u32, y: u32) -> Self {
+        OverlayEntry::new([x, y], 1, 0.0)
+    }
+}
+
+pub struct OverlayTable {
+    texture: NativeTexture,
+    structures: Vec<OverlayEntry>,
+    overlays: [OverlayEntry; 5],
+    textures: Vec<OverlayEntry>,
+    /// Whether the entries changed since the texture was last uploaded.
+    dirty: bool,
+}
+
+impl OverlayTable {
+    /// Creates the table with the overlays of the TerraTactica atlas.
+    pub fn new(gl: &Context) -> Result<Self, String> {
+        let mut structures = vec![OverlayEntry::default(); MAX_STRUCTURES];
+        // Buildings, from structure 1 to 24(structure 5 is set below).
+        let first_row = [
+            5, 6, 7, 11, 0, 10, 9, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27, 58,
+        ];
+        for (index, x) in first_row.into_iter().enumerate() {
+            structures[index + 1] = OverlayEntry::still(x, 0);
+        }
+        // The fort is animated.
+        structures[5] = OverlayEntry::new([17, 1], 3, 200.0);
+        // Roads, from 25(road end) to 41(vertical road).
+        for x in 0..17 {
+            structures[25 + x as usize] = OverlayEntry::still(x, 1);
+        }
+
+        let overlays = [
+            OverlayEntry::still(28, 0),
+            OverlayEntry::new([36, 0], 3, 200.0),
+            OverlayEntry::still(35, 0),
+            OverlayEntry::still(30, 0),
+            OverlayEntry::still(34, 0),
+        ];
+
+        // The ground textures of the top row, every texture from 4 on being drawn with the last one.
+        let textures = (0..MAX_TEXTURES as u32)
+            .map(|texture| OverlayEntry::still(texture.min(4), 0))
+            .collect();
+
+        let texture = unsafe {
+            let texture = gl
+                .create_texture()
+                .map_err(|e| format!("Failed to create overlay table texture: {}", e))?;
+            gl.bind_texture(TEXTURE_2D, Some(texture));
+            // Entries are read with texelFetch, they must never be filtered.
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MIN_FILTER, NEAREST as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MAG_FILTER, NEAREST as i32);
+            gl.tex_image_2d(
+                TEXTURE_2D,
+                0,
+                RGBA32F as i32,
+                MAX_STRUCTURES as i32,
+                ROWS as i32,
+                0,
+                RGBA,
+                FLOAT,
+                PixelUnpackData::Slice(None),
+            );
+            gl.bind_texture(TEXTURE_2D, None);
+            texture
+        };
+
+        Ok(OverlayTable {
+            texture,
+            structures,
+            overlays,
+            textures,
+            dirty: true,
+        })
+    }
+
+    pub fn texture(&self) -> NativeTexture {
+        self.texture
+    }
+
+    /// Sets the entry of a structure, returns false if the structure id is not in the table.
+    pub fn set_structure(&mut self, structure: u32, entry: OverlayEntry) -> bool {
+        match self.structures.get_mut(structure as usize) {
+            Some(current) if structure > 0 => {
+                *current = entry;
+                self.dirty = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn structure(&self, structure: u32) -> Option<OverlayEntry> {
+        self.structures.get(structure as usize).copied()
+    }
+
+    pub fn set_overlay(&mut self, overlay: Overlay, entry: OverlayEntry) {
+        self.overlays[overlay as usize] = entry;
+        self.dirty = true;
+    }
+
+    pub fn overlay(&self, overlay: Overlay) -> OverlayEntry {
+        self.overlays[overlay as usize]
+    }
+
+    /// Sets the entry of a ground texture, returns false if the texture is not in the table.
+    pub fn set_texture(&mut self, texture: u32, entry: OverlayEntry) -> bool {
+        match self.textures.get_mut(texture as usize) {
+            Some(current) => {
+                *current = entry;
+                self.dirty = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn texture_entry(&self, texture: u32) -> Option<OverlayEntry> {
+        self.textures.get(texture as usize).copied()
+    }
+
+    /// Uploads the table if it changed.
+    pub fn upload(&mut self, gl: &Context) {
+        if !self.dirty {
+            return;
+        }
+        let mut texels: Vec<f32> = Vec::with_capacity(MAX_STRUCTURES * ROWS * 4);
+        let overlays = self
+            .overlays
+            .iter()
+            .copied()
+            .chain(std::iter::repeat(OverlayEntry::default()))
+            .take(MAX_STRUCTURES);
+        let entries = self
+            .structures
+            .iter()
+            .copied()
+            .chain(overlays)
+            .chain(self.textures.iter().copied());
+        for entry in entries {
+            texels.extend_from_slice(&entry.texel());
+        }
+
+        unsafe {
+            gl.bind_texture(TEXTURE_2D, Some(self.texture));
+            gl.tex_sub_image_2d(
+                TEXTURE_2D,
+                0,
+                0,
+                0,
+                MAX_STRUCTURES as i32,
+                ROWS as i32,
+                RGBA,
+                FLOAT,
+                PixelUnpackData::Slice(Some(bytemuck::cast_slice(&texels))),
+            );
+            gl.bind_texture(TEXTURE_2D, None);
+        }
+        self.dirty = false;
+    }
+
+    pub fn delete(&self, gl: &Context) {
+        unsafe {
+            gl.delete_texture(self.texture);
+        }
+    }
+}
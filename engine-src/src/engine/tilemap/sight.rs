@@ -0,0 +1,286 @@
+//! # Sight
+//!
+//! Field of view and line of sight on a grid of tiles, some of which block the sight.
+//!
+//! Fields of view are computed with recursive shadowcasting: every octant around the viewer
+//! is scanned row by row, away from the viewer, and the slopes shadowed by blocking tiles
+//! are skipped in the rows after them. Lines of sight follow the tiles of a Bresenham line.
+//! Many viewers or lines are computed in parallel(with rayon), without holding the GIL.
+//!
+//! Tiles are addressed by their column and row, neighbours being the 8 tiles around a tile
+//! like on a square grid.
+//! Reference: https://www.roguebasin.com/index.php/FOV_using_recursive_shadowcasting
+
+use pyo3::buffer::PyBuffer;
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use rayon::prelude::*;
+
+/// Multipliers(xx, xy, yx, yy) turning the coordinates of the first octant into the
+/// coordinates of each octant.
+const OCTANTS: [[i64; 4]; 8] = [
+    [1, 0, 0, 1],
+    [0, 1, 1, 0],
+    [0, -1, 1, 0],
+    [-1, 0, 0, 1],
+    [-1, 0, 0, -1],
+    [0, -1, -1, 0],
+    [0, 1, -1, 0],
+    [1, 0, 0, -1],
+];
+
+/// Which tiles of a width x height grid block the sight.
+///
+/// _Example(python)_:
+/// ```python
+/// sight = tge.tilemap.SightGrid(world.width, world.height)
+/// sight.set_blocking_data((terrain == MOUNTAIN).astype(numpy.uint8))
+///
+/// seen = sight.fields_of_view([(unit.x, unit.y, unit.sight) for unit in player.units])
+/// world.reveal(seen)
+///
+/// if sight.line_of_sight((archer.x, archer.y), (target.x, target.y)):
+///     shoot(archer, target)
+/// ```
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct SightGrid {
+    width: u32,
+    height: u32,
+    blocking: Vec<bool>,
+}
+
+#[pymethods]
+impl SightGrid {
+    #[new]
+    pub fn new(width: u32, height: u32) -> PyResult<Self> {
+        if width == 0 || height == 0 {
+            return Err(PyValueError::new_err(format!("Invalid sight grid of {}x{} tiles", width, height)));
+        }
+        Ok(SightGrid {
+            width,
+            height,
+            blocking: vec![false; (width * height) as usize],
+        })
+    }
+
+    #[getter]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[getter]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn is_blocking(&self, x: u32, y: u32) -> PyResult<bool> {
+        let index = self.index(x, y)?;
+        Ok(self.blocking[index])
+    }
+
+    pub fn set_blocking(&mut self, x: u32, y: u32, blocking: bool) -> PyResult<()> {
+        let index = self.index(x, y)?;
+        self.blocking[index] = blocking;
+        Ok(())
+    }
+
+    /// Replaces which tiles block the sight with `data`, a buffer(numpy uint8 array, bytes,
+    /// bytearray, ...) of a byte per tile, row after row, blocking tiles being non zero.
+    pub fn set_blocking_data(&mut self, py: Python<'_>, data: &Bound<'_, PyAny>) -> PyResult<()> {
+        let bytes = PyBuffer::<u8>::get(data)?.to_vec(py)?;
+        if bytes.len() != self.blocking.len() {
+            return Err(PyValueError::new_err(format!(
+                "Blocking data has {} values, a {}x{} grid needs {}",
+                bytes.len(),
+                self.width,
+                self.height,
+                self.blocking.len()
+            )));
+        }
+        self.blocking = bytes.into_iter().map(|value| value != 0).collect();
+        Ok(())
+    }
+
+    /// Returns the tiles a viewer at (x, y) sees up to `radius` tiles away, itself included.
+    /// Blocking tiles are seen, the tiles behind them are not.
+    pub fn field_of_view(&self, x: u32, y: u32, radius: u32) -> PyResult<Vec<(u32, u32)>> {
+        self.index(x, y)?;
+        let mut mask = vec![0; self.blocking.len()];
+        self.cast_field_of_view(&mut mask, x, y, radius);
+        Ok(mask
+            .iter()
+            .enumerate()
+            .filter(|(_, seen)| **seen != 0)
+            .map(|(index, _)| (index as u32 % self.width, index as u32 / self.width))
+            .collect())
+    }
+
+    /// Returns the tiles seen by any of the viewers (x, y, radius), as bytes with a byte per
+    /// tile row after row, 1 for seen tiles and 0 for the others. Viewers outside of the grid
+    /// see nothing.
+    pub fn fields_of_view<'py>(&self, py: Python<'py>, viewers: Vec<(u32, u32, u32)>) -> Bound<'py, PyBytes> {
+        let size = self.blocking.len();
+        let mask = py.allow_threads(|| {
+            viewers
+                .par_iter()
+                .filter(|(x, y, _)| *x < self.width && *y < self.height)
+                // A mask per thread, merged at the end.
+                .fold(
+                    || vec![0u8; size],
+                    |mut mask, (x, y, radius)| {
+                        self.cast_field_of_view(&mut mask, *x, *y, *radius);
+                        mask
+                    },
+                )
+                .reduce(
+                    || vec![0u8; size],
+                    |mut mask, other| {
+                        for (seen, other) in mask.iter_mut().zip(other) {
+                            *seen |= other;
+                        }
+                        mask
+                    },
+                )
+        });
+        PyBytes::new(py, &mask)
+    }
+
+    /// Returns whether nothing blocks the sight between two tiles. The tiles themselves can be
+    /// blocking, like a wall being looked at.
+    pub fn line_of_sight(&self, from: (i64, i64), to: (i64, i64)) -> bool {
+        self.is_line_clear(from, to)
+    }
+
+    /// Returns the lines of sight of many pairs of tiles (from, to), computed in parallel.
+    pub fn lines_of_sight(&self, py: Python<'_>, pairs: Vec<((i64, i64), (i64, i64))>) -> Vec<bool> {
+        py.allow_threads(|| pairs.par_iter().map(|(from, to)| self.is_line_clear(*from, *to)).collect())
+    }
+
+    /// Returns the tiles of the Bresenham line between two tiles, both included.
+    #[staticmethod]
+    pub fn line(from: (i64, i64), to: (i64, i64)) -> Vec<(i64, i64)> {
+        bresenham_line(from, to)
+    }
+}
+
+impl SightGrid {
+    fn index(&self, x: u32, y: u32) -> PyResult<usize> {
+        if x >= self.width || y >= self.height {
+            return Err(PyIndexError::new_err(format!(
+                "Tile ({}, {}) is outside of the {}x{} grid",
+                x, y, self.width, self.height
+            )));
+        }
+        Ok((y * self.width + x) as usize)
+    }
+
+    /// Returns whether the tile at (x, y) blocks the sight, tiles outside of the grid do.
+    fn blocks(&self, x: i64, y: i64) -> bool {
+        self.is_outside(x, y) || self.blocking[(y * self.width as i64 + x) as usize]
+    }
+
+    fn is_line_clear(&self, from: (i64, i64), to: (i64, i64)) -> bool {
+        let line = bresenham_line(from, to);
+        // Only the tiles between the two ends can block.
+        line.len() < 3 || !line[1..line.len() - 1].iter().any(|(x, y)| self.blocks(*x, *y))
+    }
+
+    /// Sets the tiles seen from (x, y) to 1 in the mask.
+    fn cast_field_of_view(&self, mask: &mut [u8], x: u32, y: u32, radius: u32) {
+        mask[(y * self.width + x) as usize] = 1;
+        for transform in OCTANTS {
+            self.cast_light(mask, (x as i64, y as i64), radius as i64, 1, 1.0, 0.0, transform);
+        }
+    }
+
+    /// Scans an octant from `row` on, between the slopes `start` and `end`, recursing for the
+    /// parts of the next rows a blocking tile splits the light into.
+    #[allow(clippy::too_many_arguments)]
+    fn cast_light(
+        &self,
+        mask: &mut [u8],
+        origin: (i64, i64),
+        radius: i64,
+        row: i64,
+        mut start: f32,
+        end: f32,
+        [xx, xy, yx, yy]: [i64; 4],
+    ) {
+        if start < end {
+            return;
+        }
+        let radius_squared = radius * radius;
+        let mut next_start = start;
+        for distance in row..=radius {
+            let dy = -distance;
+            let mut blocked = false;
+            for dx in -distance..=0 {
+                let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+                let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+                if start < right_slope {
+                    continue;
+                } else if end > left_slope {
+                    break;
+                }
+
+                let x = origin.0 + dx * xx + dy * xy;
+                let y = origin.1 + dx * yx + dy * yy;
+                if dx * dx + dy * dy <= radius_squared && !self.is_outside(x, y) {
+                    mask[(y * self.width as i64 + x) as usize] = 1;
+                }
+
+                let blocking = self.blocks(x, y);
+                if blocked {
+                    if blocking {
+                        next_start = right_slope;
+                    } else {
+                        blocked = false;
+                        start = next_start;
+                    }
+                } else if blocking && distance < radius {
+                    // The light goes on past the left of the blocking tile in the next rows.
+                    blocked = true;
+                    self.cast_light(mask, origin, radius, distance + 1, start, left_slope, [xx, xy, yx, yy]);
+                    next_start = right_slope;
+                }
+            }
+            if blocked {
+                break;
+            }
+        }
+    }
+
+    fn is_outside(&self, x: i64, y: i64) -> bool {
+        x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64
+    }
+}
+
+/// Returns the tiles of the line between two tiles, both included.
+/// Reference: https://en.wikipedia.org/wiki/Bresenham%27s_line_algorithm
+pub fn bresenham_line(from: (i64, i64), to: (i64, i64)) -> Vec<(i64, i64)> {
+    let (mut x, mut y) = from;
+    let dx = (to.0 - from.0).abs();
+    let dy = -(to.1 - from.1).abs();
+    let step_x = if from.0 < to.0 { 1 } else { -1 };
+    let step_y = if from.1 < to.1 { 1 } else { -1 };
+    let mut error = dx + dy;
+
+    let mut line = Vec::with_capacity((dx.max(-dy) + 1) as usize);
+    loop {
+        line.push((x, y));
+        if (x, y) == to {
+            return line;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+    }
+}
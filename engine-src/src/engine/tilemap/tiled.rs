@@ -0,0 +1,928 @@
+//! # Tiled
+//!
+//! Loads maps made with the Tiled editor, saved as TMX(.tmx) or JSON(.tmj/.json) files,
+//! with their tilesets(embedded, or external .tsx/.tsj files), tile layers, object layers
+//! and custom properties.
+//!
+//! Layer data can be CSV, uncompressed base64 or base64 compressed with zlib or gzip(and
+//! tile elements in TMX files). Infinite maps and zstd compression are not supported.
+//! Group layers are flattened, their layers are listed with the others.
+//!
+//! Tile layers are copied into a `TileMap` with `TiledMap.populate`, a tile's value for the
+//! field being set is its id in its tileset, or its custom property with the field's name.
+//! Reference: https://doc.mapeditor.org/en/stable/reference/tmx-map-format/
+//! Reference: https://doc.mapeditor.org/en/stable/reference/json-map-format/
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use base64::Engine;
+use base64::alphabet;
+use base64::engine::general_purpose::PAD;
+use base64::engine::{DecodePaddingMode, GeneralPurpose};
+use pyo3::exceptions::{PyIOError, PyKeyError};
+use pyo3::prelude::*;
+use roxmltree::{Document, Node, ParsingOptions};
+use serde_json::Value;
+
+use crate::engine::tilemap::map::{TileMap, field_index};
+
+/// Flags in the highest bits of a gid, for tiles which are flipped or rotated.
+const GID_FLAGS: u32 = 0xF000_0000;
+
+/// Standard base64, Tiled pads layer data but not every tool does.
+const BASE64: GeneralPurpose =
+    GeneralPurpose::new(&alphabet::STANDARD, PAD.with_decode_padding_mode(DecodePaddingMode::Indifferent));
+
+/// The value of a custom property. Colors, files and class names are strings and object
+/// references are ints, class properties(with members) are left out.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    String(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl PropertyValue {
+    fn parse(kind: &str, value: &str) -> Option<Self> {
+        match kind {
+            "int" | "object" => value.trim().parse().ok().map(PropertyValue::Int),
+            "float" => value.trim().parse().ok().map(PropertyValue::Float),
+            "bool" => Some(PropertyValue::Bool(value.trim() == "true")),
+            "class" => None,
+            _ => Some(PropertyValue::String(value.to_string())),
+        }
+    }
+
+    fn from_json(kind: &str, value: &Value) -> Option<Self> {
+        match (kind, value) {
+            ("int" | "object", Value::Number(number)) => {
+                number.as_i64().or(number.as_f64().map(|number| number as i64)).map(PropertyValue::Int)
+            }
+            ("float", Value::Number(number)) => number.as_f64().map(PropertyValue::Float),
+            ("bool", Value::Bool(flag)) => Some(PropertyValue::Bool(*flag)),
+            (_, Value::String(text)) => Some(PropertyValue::String(text.clone())),
+            _ => None,
+        }
+    }
+
+    /// The value as a number, for tile fields.
+    fn as_f32(&self) -> Option<f32> {
+        match self {
+            PropertyValue::Int(value) => Some(*value as f32),
+            PropertyValue::Float(value) => Some(*value as f32),
+            PropertyValue::Bool(value) => Some(if *value { 1.0 } else { 0.0 }),
+            PropertyValue::String(_) => None,
+        }
+    }
+}
+
+impl<'py> IntoPyObject<'py> for PropertyValue {
+    type Target = PyAny;
+    type Output = Bound<'py, PyAny>;
+    type Error = std::convert::Infallible;
+
+    fn into_pyobject(self, py: Python<'py>) -> Result<Self::Output, Self::Error> {
+        Ok(match self {
+            PropertyValue::String(value) => value.into_pyobject(py)?.into_any(),
+            PropertyValue::Int(value) => value.into_pyobject(py)?.into_any(),
+            PropertyValue::Float(value) => value.into_pyobject(py)?.into_any(),
+            PropertyValue::Bool(value) => value.into_pyobject(py)?.to_owned().into_any(),
+        })
+    }
+}
+
+pub type Properties = HashMap<String, PropertyValue>;
+
+/// A tileset of a Tiled map, its tiles have the gids first_gid to first_gid + tile_count - 1.
+#[pyclass(get_all)]
+#[derive(Clone, Debug, Default)]
+pub struct TiledTileset {
+    pub first_gid: u32,
+    pub name: String,
+    pub tile_count: u32,
+    pub columns: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Path of the tileset image, relative to the working directory like the map's path.
+    pub image: Option<String>,
+    pub properties: Properties,
+    /// Custom properties of the tiles, by their id in the tileset.
+    pub tile_properties: HashMap<u32, Properties>,
+}
+
+/// A tile layer, `gids` has width * height gids row after row, 0 being no tile.
+#[pyclass(get_all)]
+#[derive(Clone, Debug, Default)]
+pub struct TiledTileLayer {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub gids: Vec<u32>,
+    pub visible: bool,
+    pub opacity: f32,
+    pub properties: Properties,
+}
+
+/// An object of an object layer, positions and sizes are in pixels.
+///
+/// `shape` is "rectangle", "ellipse", "point", "polygon", "polyline", "text" or "tile"(a tile
+/// object, whose tile is `gid`). Polygons and polylines have their `points` relative to
+/// the object's position.
+#[pyclass(get_all)]
+#[derive(Clone, Debug, Default)]
+pub struct TiledObject {
+    pub id: u32,
+    pub name: String,
+    /// The object's class(called type before Tiled 1.9).
+    pub kind: String,
+    pub shape: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Rotation in degrees, clockwise.
+    pub rotation: f32,
+    pub visible: bool,
+    pub gid: Option<u32>,
+    pub points: Vec<(f32, f32)>,
+    pub properties: Properties,
+}
+
+#[pyclass(get_all)]
+#[derive(Clone, Debug, Default)]
+pub struct TiledObjectLayer {
+    pub name: String,
+    pub objects: Vec<TiledObject>,
+    pub visible: bool,
+    pub properties: Properties,
+}
+
+/// A map loaded from a Tiled file.
+///
+/// _Example(python)_:
+/// ```python
+/// level = tge.tilemap.load_tiled_map("maps/valley.tmx")
+/// world = tge.tilemap.TileMap(level.width, level.height, level.tile_width, "assets/atlas.png")
+/// level.populate(world, "Ground")
+/// level.populate(world, "Buildings", "structure")
+/// for spawn in level.object_layer("Spawns").objects:
+///     spawn_unit(spawn.kind, spawn.x, spawn.y, spawn.properties.get("team", 0))
+/// ```
+#[pyclass(get_all)]
+#[derive(Clone, Debug, Default)]
+pub struct TiledMap {
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// "orthogonal", "isometric", "staggered" or "hexagonal".
+    pub orientation: String,
+    pub properties: Properties,
+    pub tilesets: Vec<TiledTileset>,
+    pub tile_layers: Vec<TiledTileLayer>,
+    pub object_layers: Vec<TiledObjectLayer>,
+}
+
+#[pymethods]
+impl TiledMap {
+    pub fn tile_layer(&self, name: &str) -> PyResult<TiledTileLayer> {
+        self.tile_layers
+            .iter()
+            .find(|layer| layer.name == name)
+            .cloned()
+            .ok_or_else(|| PyKeyError::new_err(format!("The map has no tile layer '{}'", name)))
+    }
+
+    pub fn object_layer(&self, name: &str) -> PyResult<TiledObjectLayer> {
+        self.object_layers
+            .iter()
+            .find(|layer| layer.name == name)
+            .cloned()
+            .ok_or_else(|| PyKeyError::new_err(format!("The map has no object layer '{}'", name)))
+    }
+
+    /// Returns the tileset a gid belongs to(flip flags are ignored).
+    pub fn tileset_for_gid(&self, gid: u32) -> Option<TiledTileset> {
+        self.tileset_index(gid).map(|index| self.tilesets[index].clone())
+    }
+
+    /// Sets `field`(see `TileMap.set_field`) of the tiles of `tilemap` from the tile layer
+    /// with the given name. Empty cells of the layer leave the tile as it is.
+    #[pyo3(signature = (tilemap, layer, field="texture"))]
+    pub fn populate(&self, mut tilemap: PyRefMut<'_, TileMap>, layer: &str, field: &str) -> PyResult<()> {
+        let field_index = field_index(field)?;
+        let layer = self
+            .tile_layers
+            .iter()
+            .find(|tile_layer| tile_layer.name == layer)
+            .ok_or_else(|| PyKeyError::new_err(format!("The map has no tile layer '{}'", layer)))?;
+
+        let width = layer.width.min(tilemap.width());
+        let height = layer.height.min(tilemap.height());
+        for y in 0..height {
+            for x in 0..width {
+                let gid = layer.gids[(y * layer.width + x) as usize] & !GID_FLAGS;
+                let Some(tileset) = self.tileset_index(gid).map(|index| &self.tilesets[index]) else {
+                    continue;
+                };
+                let id = gid - tileset.first_gid;
+                let value = tileset
+                    .tile_properties
+                    .get(&id)
+                    .and_then(|properties| properties.get(field))
+                    .and_then(PropertyValue::as_f32)
+                    .unwrap_or(id as f32);
+                tilemap.set_field_value(x, y, field_index, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "TiledMap(width={}, height={}, tile_layers={}, object_layers={})",
+            self.width,
+            self.height,
+            self.tile_layers.len(),
+            self.object_layers.len()
+        )
+    }
+}
+
+impl TiledMap {
+    /// Returns the index of the tileset with the given gid, None for empty cells(gid 0).
+    fn tileset_index(&self, gid: u32) -> Option<usize> {
+        let gid = gid & !GID_FLAGS;
+        if gid == 0 {
+            return None;
+        }
+        // The tileset with the highest first gid not above the gid.
+        self.tilesets
+            .iter()
+            .enumerate()
+            .filter(|(_, tileset)| tileset.first_gid <= gid)
+            .max_by_key(|(_, tileset)| tileset.first_gid)
+            .map(|(index, _)| index)
+    }
+
+    /// Loads a .tmx map, or a JSON map for any other extension.
+    pub fn load(path: &str) -> Result<TiledMap, String> {
+        let path = Path::new(path);
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Failed to read map file {}: {}", path.display(), e))?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+
+        let map = if text.trim_start().starts_with('<') {
+            read_tmx_map(parse_xml(&text)?.root_element(), directory)
+        } else {
+            read_json_map(&parse_json(&text)?, directory)
+        };
+        map.map_err(|e| format!("Failed to load map {}: {}", path.display(), e))
+    }
+}
+
+/// Loads a Tiled map(.tmx, .tmj or .json).
+#[pyfunction]
+pub fn load_tiled_map(path: &str) -> PyResult<TiledMap> {
+    TiledMap::load(path).map_err(PyIOError::new_err)
+}
+
+/// Returns the path of a file referenced by a map or tileset in the given directory.
+fn resolve_path(directory: &Path, file: &str) -> String {
+    let path: PathBuf = directory.join(file);
+    path.to_string_lossy().into_owned()
+}
+
+/// Loads an external tileset file, TSX or JSON.
+fn read_tileset_file(directory: &Path, source: &str, first_gid: u32) -> Result<TiledTileset, String> {
+    let path = directory.join(source);
+    let text = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read tileset {}: {}", path.display(), e))?;
+    let tileset_directory = path.parent().unwrap_or(Path::new(""));
+    if text.trim_start().starts_with('<') {
+        read_tmx_tileset(parse_xml(&text)?.root_element(), first_gid, tileset_directory)
+    } else {
+        read_json_tileset(&parse_json(&text)?, first_gid, tileset_directory)
+    }
+}
+
+/// Decodes the data of a tile layer into gids.
+fn decode_layer_data(data: &str, encoding: &str, compression: &str) -> Result<Vec<u32>, String> {
+    match encoding {
+        "csv" => data
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| value.parse().map_err(|_| format!("Invalid gid '{}' in layer data", value)))
+            .collect(),
+        "base64" => {
+            // The data of TMX files is indented.
+            let data: String = data.split_ascii_whitespace().collect();
+            let bytes = BASE64
+                .decode(data)
+                .map_err(|e| format!("Invalid base64 layer data: {}", e))?;
+            let bytes = match compression {
+                "" => bytes,
+                "zlib" => inflate(flate2::read::ZlibDecoder::new(bytes.as_slice()))?,
+                "gzip" => inflate(flate2::read::GzDecoder::new(bytes.as_slice()))?,
+                _ => return Err(format!("Unsupported layer compression '{}'", compression)),
+            };
+            // Gids are 32 bit little endian integers.
+            Ok(bytes
+                .chunks_exact(4)
+                .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                .collect())
+        }
+        _ => Err(format!("Unsupported layer encoding '{}'", encoding)),
+    }
+}
+
+fn inflate(mut decoder: impl Read) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    decoder
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to decompress layer data: {}", e))?;
+    Ok(bytes)
+}
+
+fn check_layer_size(layer: &TiledTileLayer) -> Result<(), String> {
+    let size = layer.width.checked_mul(layer.height).and_then(|size| usize::try_from(size).ok());
+    if size != Some(layer.gids.len()) {
+        return Err(format!(
+            "Layer '{}' has {} tiles instead of {}x{}",
+            layer.name,
+            layer.gids.len(),
+            layer.width,
+            layer.height
+        ));
+    }
+    Ok(())
+}
+
+/// Parses an XML document, allowing the DTD old TMX files start with.
+fn parse_xml(text: &str) -> Result<Document<'_>, String> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    Document::parse_with_options(text, options).map_err(|e| format!("Invalid XML: {}", e))
+}
+
+fn parse_json(text: &str) -> Result<Value, String> {
+    serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {}", e))
+}
+
+/// Parses an attribute, returning None if it is missing and an error if it is invalid.
+fn parse_attribute<T: FromStr>(element: Node, name: &str) -> Result<Option<T>, String> {
+    match element.attribute(name) {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("Invalid value '{}' of {} in <{}>", value, name, element.tag_name().name())),
+        None => Ok(None),
+    }
+}
+
+fn children_named<'a, 'input>(element: Node<'a, 'input>, name: &'a str) -> impl Iterator<Item = Node<'a, 'input>> {
+    element.children().filter(move |child| child.has_tag_name(name))
+}
+
+fn child<'a, 'input>(element: Node<'a, 'input>, name: &'a str) -> Option<Node<'a, 'input>> {
+    children_named(element, name).next()
+}
+
+/// Returns the text directly inside an element, CDATA sections included.
+fn text(element: Node) -> String {
+    element.children().filter(Node::is_text).filter_map(|child| child.text()).collect()
+}
+
+fn read_tmx_map(root: Node, directory: &Path) -> Result<TiledMap, String> {
+    if !root.has_tag_name("map") {
+        return Err(format!("Expected a <map> element, found <{}>", root.tag_name().name()));
+    }
+    if root.attribute("infinite") == Some("1") {
+        return Err(String::from("Infinite maps are not supported"));
+    }
+
+    let mut map = TiledMap {
+        width: parse_attribute(root, "width")?.unwrap_or(0),
+        height: parse_attribute(root, "height")?.unwrap_or(0),
+        tile_width: parse_attribute(root, "tilewidth")?.unwrap_or(0),
+        tile_height: parse_attribute(root, "tileheight")?.unwrap_or(0),
+        orientation: root.attribute("orientation").unwrap_or("orthogonal").to_string(),
+        properties: tmx_properties(root),
+        ..TiledMap::default()
+    };
+
+    for element in children_named(root, "tileset") {
+        let first_gid = parse_attribute(element, "firstgid")?.unwrap_or(1);
+        let tileset = match element.attribute("source") {
+            Some(source) => read_tileset_file(directory, source, first_gid)?,
+            None => read_tmx_tileset(element, first_gid, directory)?,
+        };
+        map.tilesets.push(tileset);
+    }
+
+    read_tmx_layers(root, &mut map)?;
+    Ok(map)
+}
+
+fn read_tmx_layers(parent: Node, map: &mut TiledMap) -> Result<(), String> {
+    for element in parent.children() {
+        match element.tag_name().name() {
+            "layer" => map.tile_layers.push(read_tmx_tile_layer(element)?),
+            "objectgroup" => map.object_layers.push(TiledObjectLayer {
+                name: element.attribute("name").unwrap_or_default().to_string(),
+                objects: children_named(element, "object")
+                    .map(read_tmx_object)
+                    .collect::<Result<_, _>>()?,
+                visible: element.attribute("visible") != Some("0"),
+                properties: tmx_properties(element),
+            }),
+            "group" => read_tmx_layers(element, map)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn read_tmx_tile_layer(element: Node) -> Result<TiledTileLayer, String> {
+    let mut layer = TiledTileLayer {
+        name: element.attribute("name").unwrap_or_default().to_string(),
+        width: parse_attribute(element, "width")?.unwrap_or(0),
+        height: parse_attribute(element, "height")?.unwrap_or(0),
+        visible: element.attribute("visible") != Some("0"),
+        opacity: parse_attribute(element, "opacity")?.unwrap_or(1.0),
+        properties: tmx_properties(element),
+        ..TiledTileLayer::default()
+    };
+
+    let data = child(element, "data").ok_or(format!("Layer '{}' has no data", layer.name))?;
+    if child(data, "chunk").is_some() {
+        return Err(String::from("Infinite maps are not supported"));
+    }
+    layer.gids = match data.attribute("encoding") {
+        Some(encoding) => decode_layer_data(&text(data), encoding, data.attribute("compression").unwrap_or(""))?,
+        // Uncompressed XML, a <tile> element for every cell.
+        None => children_named(data, "tile")
+            .map(|tile| parse_attribute(tile, "gid").map(Option::unwrap_or_default))
+            .collect::<Result<_, _>>()?,
+    };
+    check_layer_size(&layer)?;
+    Ok(layer)
+}
+
+fn read_tmx_object(element: Node) -> Result<TiledObject, String> {
+    let mut object = TiledObject {
+        id: parse_attribute(element, "id")?.unwrap_or(0),
+        name: element.attribute("name").unwrap_or_default().to_string(),
+        kind: element
+            .attribute("class")
+            .or(element.attribute("type"))
+            .unwrap_or_default()
+            .to_string(),
+        shape: String::from("rectangle"),
+        x: parse_attribute(element, "x")?.unwrap_or(0.0),
+        y: parse_attribute(element, "y")?.unwrap_or(0.0),
+        width: parse_attribute(element, "width")?.unwrap_or(0.0),
+        height: parse_attribute(element, "height")?.unwrap_or(0.0),
+        rotation: parse_attribute(element, "rotation")?.unwrap_or(0.0),
+        visible: element.attribute("visible") != Some("0"),
+        gid: parse_attribute::<u32>(element, "gid")?.map(|gid| gid & !GID_FLAGS),
+        points: Vec::new(),
+        properties: tmx_properties(element),
+    };
+    if object.gid.is_some() {
+        object.shape = String::from("tile");
+    }
+
+    for child in element.children() {
+        match child.tag_name().name() {
+            shape @ ("ellipse" | "point" | "text") => object.shape = shape.to_string(),
+            shape @ ("polygon" | "polyline") => {
+                object.shape = shape.to_string();
+                // "x1,y1 x2,y2 ..."
+                object.points = child
+                    .attribute("points")
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .map(|point| {
+                        let (x, y) = point.split_once(',').ok_or(format!("Invalid point '{}'", point))?;
+                        let parse = |value: &str| value.parse::<f32>().map_err(|_| format!("Invalid point '{}'", point));
+                        Ok((parse(x)?, parse(y)?))
+                    })
+                    .collect::<Result<_, String>>()?;
+            }
+            _ => {}
+        }
+    }
+    Ok(object)
+}
+
+fn read_tmx_tileset(element: Node, first_gid: u32, directory: &Path) -> Result<TiledTileset, String> {
+    let mut tileset = TiledTileset {
+        first_gid,
+        name: element.attribute("name").unwrap_or_default().to_string(),
+        tile_count: parse_attribute(element, "tilecount")?.unwrap_or(0),
+        columns: parse_attribute(element, "columns")?.unwrap_or(0),
+        tile_width: parse_attribute(element, "tilewidth")?.unwrap_or(0),
+        tile_height: parse_attribute(element, "tileheight")?.unwrap_or(0),
+        image: child(element, "image")
+            .and_then(|image| image.attribute("source"))
+            .map(|source| resolve_path(directory, source)),
+        properties: tmx_properties(element),
+        tile_properties: HashMap::new(),
+    };
+    for tile in children_named(element, "tile") {
+        let properties = tmx_properties(tile);
+        if !properties.is_empty() {
+            tileset
+                .tile_properties
+                .insert(parse_attribute(tile, "id")?.unwrap_or(0), properties);
+        }
+    }
+    Ok(tileset)
+}
+
+/// Reads the <properties> child of an element.
+fn tmx_properties(element: Node) -> Properties {
+    let Some(properties) = child(element, "properties") else {
+        return Properties::new();
+    };
+    children_named(properties, "property")
+        .filter_map(|property| {
+            let name = property.attribute("name")?;
+            // Multiline strings are stored as the element's text.
+            let value = property.attribute("value").map(str::to_string).unwrap_or_else(|| text(property));
+            let value = PropertyValue::parse(property.attribute("type").unwrap_or("string"), &value)?;
+            Some((name.to_string(), value))
+        })
+        .collect()
+}
+
+fn json_u32(value: &Value, key: &str) -> u32 {
+    value.get(key).and_then(Value::as_f64).unwrap_or(0.0) as u32
+}
+
+fn json_f32(value: &Value, key: &str) -> f32 {
+    value.get(key).and_then(Value::as_f64).unwrap_or(0.0) as f32
+}
+
+/// Returns an array of a JSON object, or an empty slice if it is missing.
+fn json_array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value.get(key).and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default()
+}
+
+fn json_string(value: &Value, key: &str) -> String {
+    value.get(key).and_then(Value::as_str).unwrap_or_default().to_string()
+}
+
+fn json_visible(value: &Value) -> bool {
+    value.get("visible").and_then(Value::as_bool).unwrap_or(true)
+}
+
+fn read_json_map(root: &Value, directory: &Path) -> Result<TiledMap, String> {
+    if root.get("infinite").and_then(Value::as_bool) == Some(true) {
+        return Err(String::from("Infinite maps are not supported"));
+    }
+
+    let mut map = TiledMap {
+        width: json_u32(root, "width"),
+        height: json_u32(root, "height"),
+        tile_width: json_u32(root, "tilewidth"),
+        tile_height: json_u32(root, "tileheight"),
+        orientation: root
+            .get("orientation")
+            .and_then(Value::as_str)
+            .unwrap_or("orthogonal")
+            .to_string(),
+        properties: json_properties(root),
+        ..TiledMap::default()
+    };
+
+    for tileset in json_array(root, "tilesets") {
+        let first_gid = json_u32(tileset, "firstgid").max(1);
+        let tileset = match tileset.get("source").and_then(Value::as_str) {
+            Some(source) => read_tileset_file(directory, source, first_gid)?,
+            None => read_json_tileset(tileset, first_gid, directory)?,
+        };
+        map.tilesets.push(tileset);
+    }
+
+    read_json_layers(root, &mut map)?;
+    Ok(map)
+}
+
+fn read_json_layers(parent: &Value, map: &mut TiledMap) -> Result<(), String> {
+    for layer in json_array(parent, "layers") {
+        match layer.get("type").and_then(Value::as_str) {
+            Some("tilelayer") => map.tile_layers.push(read_json_tile_layer(layer)?),
+            Some("objectgroup") => map.object_layers.push(TiledObjectLayer {
+                name: json_string(layer, "name"),
+                objects: json_array(layer, "objects").iter().map(read_json_object)
+                    .collect(),
+                visible: json_visible(layer),
+                properties: json_properties(layer),
+            }),
+            Some("group") => read_json_layers(layer, map)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn read_json_tile_layer(value: &Value) -> Result<TiledTileLayer, String> {
+    let mut layer = TiledTileLayer {
+        name: json_string(value, "name"),
+        width: json_u32(value, "width"),
+        height: json_u32(value, "height"),
+        visible: json_visible(value),
+        opacity: value.get("opacity").and_then(Value::as_f64).unwrap_or(1.0) as f32,
+        properties: json_properties(value),
+        ..TiledTileLayer::default()
+    };
+    if value.get("chunks").is_some() {
+        return Err(String::from("Infinite maps are not supported"));
+    }
+
+    layer.gids = match value.get("data") {
+        Some(Value::Array(gids)) => gids
+            .iter()
+            .map(|gid| gid.as_u64().and_then(|gid| u32::try_from(gid).ok()))
+            .collect::<Option<_>>()
+            .ok_or(format!("Layer '{}' has invalid gids", layer.name))?,
+        Some(Value::String(data)) => decode_layer_data(
+            data,
+            value.get("encoding").and_then(Value::as_str).unwrap_or("base64"),
+            value.get("compression").and_then(Value::as_str).unwrap_or(""),
+        )?,
+        _ => return Err(format!("Layer '{}' has no data", layer.name)),
+    };
+    check_layer_size(&layer)?;
+    Ok(layer)
+}
+
+fn read_json_object(value: &Value) -> TiledObject {
+    let flag = |key: &str| value.get(key).and_then(Value::as_bool).unwrap_or(false);
+    let points = |key: &str| -> Option<Vec<(f32, f32)>> {
+        let points = value.get(key)?.as_array()?;
+        Some(points.iter().map(|point| (json_f32(point, "x"), json_f32(point, "y"))).collect())
+    };
+
+    let mut object = TiledObject {
+        id: json_u32(value, "id"),
+        name: json_string(value, "name"),
+        kind: value
+            .get("class")
+            .or(value.get("type"))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        shape: String::from("rectangle"),
+        x: json_f32(value, "x"),
+        y: json_f32(value, "y"),
+        width: json_f32(value, "width"),
+        height: json_f32(value, "height"),
+        rotation: json_f32(value, "rotation"),
+        visible: json_visible(value),
+        gid: value
+            .get("gid")
+            .and_then(Value::as_f64)
+            .map(|gid| gid as u32 & !GID_FLAGS),
+        points: Vec::new(),
+        properties: json_properties(value),
+    };
+
+    if object.gid.is_some() {
+        object.shape = String::from("tile");
+    } else if flag("ellipse") {
+        object.shape = String::from("ellipse");
+    } else if flag("point") {
+        object.shape = String::from("point");
+    } else if value.get("text").is_some() {
+        object.shape = String::from("text");
+    } else if let Some(polygon) = points("polygon") {
+        object.shape = String::from("polygon");
+        object.points = polygon;
+    } else if let Some(polyline) = points("polyline") {
+        object.shape = String::from("polyline");
+        object.points = polyline;
+    }
+    object
+}
+
+fn read_json_tileset(value: &Value, first_gid: u32, directory: &Path) -> Result<TiledTileset, String> {
+    let mut tileset = TiledTileset {
+        first_gid,
+        name: json_string(value, "name"),
+        tile_count: json_u32(value, "tilecount"),
+        columns: json_u32(value, "columns"),
+        tile_width: json_u32(value, "tilewidth"),
+        tile_height: json_u32(value, "tileheight"),
+        image: value
+            .get("image")
+            .and_then(Value::as_str)
+            .map(|image| resolve_path(directory, image)),
+        properties: json_properties(value),
+        tile_properties: HashMap::new(),
+    };
+    for tile in json_array(value, "tiles") {
+        let properties = json_properties(tile);
+        if !properties.is_empty() {
+            tileset.tile_properties.insert(json_u32(tile, "id"), properties);
+        }
+    }
+    Ok(tileset)
+}
+
+/// Reads the "properties" array of a JSON object.
+fn json_properties(value: &Value) -> Properties {
+    json_array(value, "properties")
+        .iter()
+        .filter_map(|property| {
+            let name = property.get("name")?.as_str()?;
+            let kind = property.get("type").and_then(Value::as_str).unwrap_or("string");
+            let value = PropertyValue::from_json(kind, property.get("value")?)?;
+            Some((name.to_string(), value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    /// The gids of the tests, with the horizontal and vertical flip flags on some.
+    const GIDS: [u32; 6] = [1, 0, 0x8000_0002, 17, 0x4000_0011, 0xE000_0003];
+
+    fn little_endian(gids: &[u32]) -> Vec<u8> {
+        gids.iter().flat_map(|gid| gid.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn decodes_csv_layer_data() {
+        let data = "\n1,0,2147483650,\n17,1073741841,3758096387\n";
+        assert_eq!(decode_layer_data(data, "csv", "").unwrap(), GIDS);
+        assert!(decode_layer_data("1,x", "csv", "").is_err());
+        assert!(decode_layer_data("1,-1", "csv", "").is_err());
+    }
+
+    #[test]
+    fn decodes_base64_layer_data() {
+        let bytes = little_endian(&GIDS);
+        let text = format!("\n   {}\n", BASE64.encode(&bytes));
+        assert_eq!(decode_layer_data(&text, "base64", "").unwrap(), GIDS);
+        // Without the padding.
+        assert_eq!(decode_layer_data("AQAAAA", "base64", "").unwrap(), [1]);
+
+        let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        zlib.write_all(&bytes).unwrap();
+        let text = BASE64.encode(zlib.finish().unwrap());
+        assert_eq!(decode_layer_data(&text, "base64", "zlib").unwrap(), GIDS);
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(&bytes).unwrap();
+        let text = BASE64.encode(gzip.finish().unwrap());
+        assert_eq!(decode_layer_data(&text, "base64", "gzip").unwrap(), GIDS);
+    }
+
+    #[test]
+    fn rejects_unsupported_layer_data() {
+        let text = BASE64.encode(little_endian(&GIDS));
+        assert!(decode_layer_data(&text, "base64", "zstd").is_err());
+        assert!(decode_layer_data(&text, "base32", "").is_err());
+        // Not compressed data.
+        assert!(decode_layer_data(&text, "base64", "zlib").is_err());
+        assert!(decode_layer_data(&text, "base64", "gzip").is_err());
+    }
+
+    #[test]
+    fn finds_tilesets_by_first_gid_ignoring_flags() {
+        let tileset = |first_gid: u32| TiledTileset {
+            first_gid,
+            ..TiledTileset::default()
+        };
+        let map = TiledMap {
+            tilesets: vec![tileset(17), tileset(1)],
+            ..TiledMap::default()
+        };
+        let tilesets: Vec<Option<usize>> = GIDS.iter().map(|gid| map.tileset_index(*gid)).collect();
+        assert_eq!(tilesets, [Some(1), None, Some(1), Some(0), Some(0), Some(1)]);
+        assert_eq!(map.tileset_for_gid(0x8000_0012).map(|tileset| tileset.first_gid), Some(17));
+        assert_eq!(map.tileset_index(GID_FLAGS), None);
+    }
+
+    #[test]
+    fn checks_layer_sizes() {
+        let layer = |width: u32, height: u32, count: usize| TiledTileLayer {
+            width,
+            height,
+            gids: vec![0; count],
+            ..TiledTileLayer::default()
+        };
+        assert!(check_layer_size(&layer(3, 2, 6)).is_ok());
+        assert!(check_layer_size(&layer(0, 0, 0)).is_ok());
+        assert!(check_layer_size(&layer(3, 2, 5)).is_err());
+        // 65536 * 65536 wraps to 0 in u32.
+        assert!(check_layer_size(&layer(65536, 65536, 0)).is_err());
+    }
+
+    #[test]
+    fn reads_tmx_maps() {
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE map SYSTEM "http://mapeditor.org/dtd/1.0/map.dtd">
+<map version="1.10" orientation="orthogonal" width="3" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <properties>
+  <property name="title" value="Caves &amp; &#x1F600;"/>
+  <property name="note"><![CDATA[first <line>
+second]]></property>
+  <property name="depth" type="int" value=" 3 "/>
+ </properties>
+ <tileset firstgid="1" name="cave" tilewidth="16" tileheight="16" tilecount="16" columns="4">
+  <image source="cave.png" width="64" height="64"/>
+  <tile id="2"><properties><property name="solid" type="bool" value="true"/></properties></tile>
+ </tileset>
+ <group name="ground">
+  <layer id="1" name="floor" width="3" height="2" opacity="0.5">
+   <data encoding="csv">
+1,0,2147483650,
+17,1073741841,3758096387
+</data>
+  </layer>
+ </group>
+ <objectgroup name="things" visible="0">
+  <object id="4" name="door" type="exit" x="8" y="24.5" width="16" height="16"/>
+  <object id="5" gid="2147483651" x="0" y="0"/>
+  <object id="6" x="1" y="2"><polyline points="0,0 4,-2.5"/></object>
+ </objectgroup>
+</map>"#;
+        let map = read_tmx_map(parse_xml(text).unwrap().root_element(), Path::new("")).unwrap();
+        assert_eq!((map.width, map.height, map.tile_width, map.tile_height), (3, 2, 16, 16));
+        assert_eq!(map.properties["title"], PropertyValue::String(String::from("Caves & \u{1F600}")));
+        assert_eq!(map.properties["note"], PropertyValue::String(String::from("first <line>\nsecond")));
+        assert_eq!(map.properties["depth"], PropertyValue::Int(3));
+
+        let tileset = &map.tilesets[0];
+        assert_eq!((tileset.name.as_str(), tileset.tile_count, tileset.columns), ("cave", 16, 4));
+        assert_eq!(tileset.image.as_deref(), Some("cave.png"));
+        assert_eq!(tileset.tile_properties[&2]["solid"], PropertyValue::Bool(true));
+
+        let layer = &map.tile_layers[0];
+        assert_eq!((layer.name.as_str(), layer.opacity, layer.visible), ("floor", 0.5, true));
+        assert_eq!(layer.gids, GIDS);
+
+        let objects = &map.object_layers[0];
+        assert!(!objects.visible);
+        assert_eq!((objects.objects[0].kind.as_str(), objects.objects[0].y), ("exit", 24.5));
+        assert_eq!((objects.objects[1].shape.as_str(), objects.objects[1].gid), ("tile", Some(3)));
+        assert_eq!(objects.objects[2].shape, "polyline");
+        assert_eq!(objects.objects[2].points, [(0.0, 0.0), (4.0, -2.5)]);
+
+        assert!(parse_xml("<map><layer></map>").is_err());
+        let invalid = parse_xml(r#"<map width="wide"/>"#).unwrap();
+        assert!(read_tmx_map(invalid.root_element(), Path::new("")).is_err());
+    }
+
+    #[test]
+    fn reads_json_maps() {
+        let text = r#"{
+            "width": 3, "height": 2, "tilewidth": 16, "tileheight": 16, "orientation": "orthogonal",
+            "properties": [
+                {"name": "title", "type": "string", "value": "Caves \u0026 \ud83d\ude00"},
+                {"name": "depth", "type": "int", "value": 3},
+                {"name": "speed", "type": "float", "value": 1.5e0}
+            ],
+            "tilesets": [{"firstgid": 1, "name": "cave", "tilecount": 16, "columns": 4, "image": "cave.png",
+                "tiles": [{"id": 2, "properties": [{"name": "solid", "type": "bool", "value": true}]}]}],
+            "layers": [
+                {"type": "group", "name": "ground", "layers": [
+                    {"type": "tilelayer", "name": "floor", "width": 3, "height": 2, "opacity": 0.5, "visible": true,
+                        "data": [1, 0, 2147483650, 17, 1073741841, 3758096387]}
+                ]},
+                {"type": "objectgroup", "name": "things", "visible": false, "objects": [
+                    {"id": 6, "x": 1, "y": 2, "polyline": [{"x": 0, "y": 0}, {"x": 4, "y": -2.5}]}
+                ]}
+            ]
+        }"#;
+        let map = read_json_map(&parse_json(text).unwrap(), Path::new("")).unwrap();
+        assert_eq!((map.width, map.height, map.tile_width, map.tile_height), (3, 2, 16, 16));
+        assert_eq!(map.properties["title"], PropertyValue::String(String::from("Caves & \u{1F600}")));
+        assert_eq!(map.properties["depth"], PropertyValue::Int(3));
+        assert_eq!(map.properties["speed"], PropertyValue::Float(1.5));
+        assert_eq!(map.tilesets[0].tile_properties[&2]["solid"], PropertyValue::Bool(true));
+        assert_eq!(map.tile_layers[0].gids, GIDS);
+        assert_eq!(map.tile_layers[0].opacity, 0.5);
+        assert!(!map.object_layers[0].visible);
+        assert_eq!(map.object_layers[0].objects[0].shape, "polyline");
+        assert_eq!(map.object_layers[0].objects[0].points, [(0.0, 0.0), (4.0, -2.5)]);
+
+        assert!(parse_json(r#"{"width": 3"#).is_err());
+        // Deeper than serde_json's recursion limit.
+        assert!(parse_json(&"[".repeat(100_000)).is_err());
+    }
+}
@@ -21,7 +21,11 @@ use sdl3::{
 use std::rc::Rc;
 use std::string::String;
 
+use crate::engine::gl2d::sdf::SDF_SHADER;
+use crate::engine::gltext::text::{TEXT_SDF_SHADER, TEXT_SHADER};
+use crate::engine::gltext::textures::TEXTURE_SHADER;
 use crate::engine::helpers::get_tctx;
+use crate::engine::tilemap::map::MAP_SHADER;
 
 /// Declaration of the window submodule for the parent python module.
 /// Reference: https://pyo3.rs/v0.23.4/module.html
@@ -107,6 +111,48 @@ pub fn init_opengl_drawing() {
             }
         }
 
+        // The other shader programs used by the draw functions, they all need the same projection.
+        let shader_programs = [
+            (
+                SDF_SHADER,
+                include_str!("shaders/vertex/sdf_vert.glsl"),
+                include_str!("shaders/fragment/sdf_frag.glsl"),
+            ),
+            (
+                TEXT_SHADER,
+                include_str!("shaders/vertex/default_vert.glsl"),
+                include_str!("shaders/fragment/text_frag.glsl"),
+            ),
+            (
+                TEXT_SDF_SHADER,
+                include_str!("shaders/vertex/default_vert.glsl"),
+                include_str!("shaders/fragment/text_sdf_frag.glsl"),
+            ),
+            (
+                TEXTURE_SHADER,
+                include_str!("shaders/vertex/default_vert.glsl"),
+                include_str!("shaders/fragment/texture_frag.glsl"),
+            ),
+            (
+                MAP_SHADER,
+                include_str!("shaders/vertex/map_vert.glsl"),
+                include_str!("shaders/fragment/map_frag.glsl"),
+            ),
+        ];
+        for (program_name, vertex_source, fragment_source) in shader_programs {
+            ctx.create_shader_program(&gl, String::from(program_name), vertex_source, fragment_source);
+            unsafe {
+                let program = ctx.get_shader_program(String::from(program_name));
+                gl.use_program(Some(program));
+                if let Some(projection_location) = gl.get_uniform_location(program, "projection") {
+                    gl.uniform_matrix_4_f32_slice(Some(&projection_location), true, &projection);
+                }
+            }
+        }
+        unsafe {
+            gl.use_program(Some(ctx.get_glob_glprogam()));
+        }
+
         ctx.global_vao
             .write()
             .unwrap_or_else(|err| panic!("Failed to acquire write lock for global_vao: {:?}", err))
@@ -143,6 +189,7 @@ pub fn init_gl_window(gl_version: (u8, u8), title: String, initial_resolution: (
     let gl_attr = video_subsystem.gl_attr();
     gl_attr.set_context_profile(GLProfile::Core);
     gl_attr.set_context_version(gl_version.0, gl_version.1); // OpenGL version
+    gl_attr.set_stencil_size(8); // Needed for filling and stroking paths
 
     // Create a window that will be used for OpenGL rendering
     let window = video_subsystem
@@ -15,6 +15,8 @@ use pyo3::prelude::*;
 use sdl3::{Sdl, VideoSubsystem, video::GLContext, video::Window};
 use std::{cell::OnceCell, collections::HashMap, rc::Rc, sync::RwLock};
 
+use crate::engine::gl2d::blend::BlendMode;
+
 // Import engine modules
 mod ctx_impl;
 pub mod engine;
@@ -55,6 +57,8 @@ pub struct TContext {
     global_vbo: RwLock<Option<NativeBuffer>>,
     global_program: RwLock<Option<NativeProgram>>,
     shader_programs: RwLock<HashMap<String, NativeProgram>>,
+    // The blend mode stack, the last mode is the one used by the draw functions.
+    blend_modes: RwLock<Vec<BlendMode>>,
 }
 
 /// Declaration of python module.
@@ -66,7 +70,11 @@ pub struct TContext {
 pub fn terra_graphics_engine(m: &Bound<'_, PyModule>) -> PyResult<()> {
     engine::test::register_test_module(m)?;
     engine::gl2d::shapes::register_shapes_module(m)?;
+    engine::gl2d::blend::register_blend_module(m)?;
+    engine::gltext::register_text_module(m)?;
     engine::objects::register_objects_module(m)?;
+    engine::tilemap::register_tilemap_module(m)?;
     engine::window::register_window_module(m)?;
+    engine::events::register_events_module(m)?;
     Ok(())
 }